@@ -185,6 +185,7 @@ impl CompactFiltersBlockchain {
                     outpoint: OutPoint::new(tx.txid(), i as u32),
                     txout: output.clone(),
                     keychain,
+                    is_coinbase: tx.is_coin_base(),
                 })?;
                 incoming += output.value;
 
@@ -208,6 +209,7 @@ impl CompactFiltersBlockchain {
                 sent: outgoing,
                 confirmation_time: ConfirmationTime::new(height, timestamp),
                 verified: height.is_some(),
+                ancestors_fee: None,
                 fee: Some(inputs_sum.saturating_sub(outputs_sum)),
             };
 
@@ -23,15 +23,42 @@
 //! let blockchain = ElectrumBlockchain::from(client);
 //! # Ok::<(), bdk::Error>(())
 //! ```
+//!
+//! ## Subscriptions
+//!
+//! Instead of calling [`Wallet::sync`](crate::wallet::Wallet::sync) periodically, an
+//! [`ElectrumBlockchain`] can [subscribe](ElectrumBlockchain::subscribe) to the status of a set
+//! of scripts and to the new blocks. The server pushes a notification every time one of them
+//! changes, and the resulting [`ElectrumEvent`]s can be used to know when a sync is actually
+//! required.
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use bdk::blockchain::electrum::{ElectrumBlockchain, ElectrumEvent};
+//! # use bdk::database::{Database, MemoryDatabase};
+//! # let database = MemoryDatabase::new();
+//! let client = electrum_client::Client::new("ssl://electrum.blockstream.info:50002")?;
+//! let blockchain = ElectrumBlockchain::from(client);
+//!
+//! let mut subscription = blockchain.subscribe(database.iter_script_pubkeys(None)?)?;
+//! subscription.listen(Duration::from_secs(1), |event| {
+//!     if let ElectrumEvent::NewTransaction { txid, .. } = event {
+//!         println!("Received {}", txid);
+//!     }
+//!     true
+//! })?;
+//! # Ok::<(), bdk::Error>(())
+//! ```
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 
 #[allow(unused_imports)]
 use log::{debug, error, info, trace};
 
 use bitcoin::{BlockHeader, Script, Transaction, Txid};
 
-use electrum_client::{Client, ConfigBuilder, ElectrumApi, Socks5Config};
+use electrum_client::{Client, ConfigBuilder, ElectrumApi, HeaderNotification, Socks5Config};
 
 use self::utils::{ElectrumLikeSync, ElsGetHistoryRes};
 use super::*;
@@ -51,6 +78,219 @@ impl std::convert::From<Client> for ElectrumBlockchain {
     }
 }
 
+impl ElectrumBlockchain {
+    /// Subscribe to the new blocks and to the changes in the history of `scripts`
+    ///
+    /// The current history of every script is downloaded when it's subscribed, and only the
+    /// changes that happen after that are reported by the returned [`ElectrumSubscription`].
+    pub fn subscribe<I: IntoIterator<Item = Script>>(
+        &self,
+        scripts: I,
+    ) -> Result<ElectrumSubscription<'_>, Error> {
+        self.0.block_headers_subscribe()?;
+        // drop the notifications received before this subscription was created
+        while self.0.block_headers_pop()?.is_some() {}
+
+        let mut subscription = ElectrumSubscription {
+            client: &self.0,
+            histories: HashMap::new(),
+        };
+        subscription.add_scripts(scripts)?;
+
+        Ok(subscription)
+    }
+}
+
+/// Event reported by an [`ElectrumSubscription`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ElectrumEvent {
+    /// A new block was connected to the best chain
+    NewBlock {
+        /// Height of the block
+        height: u32,
+        /// Header of the block
+        header: BlockHeader,
+    },
+    /// A transaction that involves a subscribed script was seen for the first time
+    NewTransaction {
+        /// Id of the transaction
+        txid: Txid,
+        /// Script involved in the transaction
+        script: Script,
+        /// Height of the block that confirms the transaction, `None` if it's unconfirmed
+        height: Option<u32>,
+    },
+    /// A transaction seen before was confirmed, moved to a different block by a reorg or returned
+    /// to the mempool
+    TransactionUpdated {
+        /// Id of the transaction
+        txid: Txid,
+        /// Script involved in the transaction
+        script: Script,
+        /// Height of the block that confirms the transaction, `None` if it's unconfirmed
+        height: Option<u32>,
+    },
+    /// A transaction seen before is not part of the history of the script anymore, usually
+    /// because it was replaced or evicted from the mempool
+    TransactionDropped {
+        /// Id of the transaction
+        txid: Txid,
+        /// Script that was involved in the transaction
+        script: Script,
+    },
+}
+
+/// Subscription to the notifications pushed by an Electrum server
+///
+/// Created by [`ElectrumBlockchain::subscribe`]. The notifications are only read from the
+/// connection when a request is made to the server, so they are collected by calling
+/// [`poll`](ElectrumSubscription::poll) periodically, or by running the
+/// [`listen`](ElectrumSubscription::listen) loop.
+///
+/// The scripts stay subscribed on the client when the subscription is dropped, subscribing them
+/// again later with the same client is supported.
+pub struct ElectrumSubscription<'a> {
+    client: &'a Client,
+    histories: HashMap<Script, HashMap<Txid, Option<u32>>>,
+}
+
+impl<'a> ElectrumSubscription<'a> {
+    /// Subscribe to more scripts, for example after deriving new addresses
+    pub fn add_scripts<I: IntoIterator<Item = Script>>(&mut self, scripts: I) -> Result<(), Error> {
+        let mut new_scripts = vec![];
+        for script in scripts {
+            if self.histories.contains_key(&script) || new_scripts.contains(&script) {
+                continue;
+            }
+
+            match self.client.script_subscribe(&script) {
+                Ok(_) | Err(electrum_client::Error::AlreadySubscribed(_)) => {}
+                Err(e) => return Err(e.into()),
+            }
+            while self.client.script_pop(&script)?.is_some() {}
+
+            new_scripts.push(script);
+        }
+
+        let histories = self.client.batch_script_get_history(new_scripts.iter())?;
+        for (script, history) in new_scripts.into_iter().zip(histories) {
+            let history = history
+                .into_iter()
+                .map(|entry| (entry.tx_hash, confirmed_height(entry.height)))
+                .collect();
+            self.histories.insert(script, history);
+        }
+
+        Ok(())
+    }
+
+    /// Return the scripts currently subscribed
+    pub fn scripts(&self) -> impl Iterator<Item = &Script> {
+        self.histories.keys()
+    }
+
+    /// Read the notifications received since the last call and turn them into events
+    ///
+    /// Blocks are reported first, followed by the changes in the history of the scripts.
+    pub fn poll(&mut self) -> Result<Vec<ElectrumEvent>, Error> {
+        // make a request so that the client reads the pending notifications
+        self.client.ping()?;
+
+        let mut events = vec![];
+        while let Some(HeaderNotification { height, header }) = self.client.block_headers_pop()? {
+            events.push(ElectrumEvent::NewBlock {
+                height: height as u32,
+                header,
+            });
+        }
+
+        let mut changed = vec![];
+        for script in self.histories.keys() {
+            let mut notified = false;
+            while self.client.script_pop(script)?.is_some() {
+                notified = true;
+            }
+            if notified {
+                changed.push(script.clone());
+            }
+        }
+        if changed.is_empty() {
+            return Ok(events);
+        }
+
+        let histories = self.client.batch_script_get_history(changed.iter())?;
+        for (script, history) in changed.into_iter().zip(histories) {
+            let history: HashMap<_, _> = history
+                .into_iter()
+                .map(|entry| (entry.tx_hash, confirmed_height(entry.height)))
+                .collect();
+            let known = self
+                .histories
+                .get_mut(&script)
+                .expect("Changed scripts are subscribed");
+
+            for (txid, height) in &history {
+                match known.get(txid) {
+                    None => events.push(ElectrumEvent::NewTransaction {
+                        txid: *txid,
+                        script: script.clone(),
+                        height: *height,
+                    }),
+                    Some(known_height) if known_height != height => {
+                        events.push(ElectrumEvent::TransactionUpdated {
+                            txid: *txid,
+                            script: script.clone(),
+                            height: *height,
+                        })
+                    }
+                    _ => {}
+                }
+            }
+            for txid in known.keys().filter(|txid| !history.contains_key(*txid)) {
+                events.push(ElectrumEvent::TransactionDropped {
+                    txid: *txid,
+                    script: script.clone(),
+                });
+            }
+
+            *known = history;
+        }
+
+        Ok(events)
+    }
+
+    /// Poll the subscription every `interval` and pass the events to `callback`, until it
+    /// returns `false`
+    ///
+    /// To forward the events to a channel the callback can return the result of
+    /// [`Sender::send`](std::sync::mpsc::Sender::send), so that the loop stops once the
+    /// receiver is dropped.
+    pub fn listen<F: FnMut(ElectrumEvent) -> bool>(
+        &mut self,
+        interval: Duration,
+        mut callback: F,
+    ) -> Result<(), Error> {
+        loop {
+            for event in self.poll()? {
+                if !callback(event) {
+                    return Ok(());
+                }
+            }
+
+            std::thread::sleep(interval);
+        }
+    }
+}
+
+// Electrum uses `0` for txs in the mempool and `-1` for txs with unconfirmed parents
+fn confirmed_height(height: i32) -> Option<u32> {
+    if height > 0 {
+        Some(height as u32)
+    } else {
+        None
+    }
+}
+
 impl Blockchain for ElectrumBlockchain {
     fn get_capabilities(&self) -> HashSet<Capability> {
         vec![
@@ -134,6 +374,12 @@ impl ElectrumLikeSync for Client {
     ) -> Result<Vec<BlockHeader>, Error> {
         self.batch_block_header(heights).map_err(Error::Electrum)
     }
+
+    fn els_get_height(&self) -> Result<u32, Error> {
+        self.block_headers_subscribe()
+            .map(|data| data.height as u32)
+            .map_err(Error::Electrum)
+    }
 }
 
 /// Configuration for an [`ElectrumBlockchain`]
@@ -175,3 +421,139 @@ crate::bdk_blockchain_tests! {
         ElectrumBlockchain::from(Client::new(&testutils::blockchain_tests::get_electrum_url()).unwrap())
     }
 }
+
+#[cfg(all(test, feature = "test-electrum"))]
+mod test {
+    use super::*;
+    use crate::blockchain::utils::ElsScriptStatus;
+    use crate::database::MemoryDatabase;
+    use crate::serial_test::serial;
+    use crate::testutils;
+    use crate::testutils::blockchain_tests::{
+        exponential_backoff_poll, get_electrum_url, TestClient,
+    };
+    use crate::wallet::AddressIndex;
+    use crate::Wallet;
+    use bitcoin::Network;
+
+    fn next_events(subscription: &mut ElectrumSubscription) -> Vec<ElectrumEvent> {
+        exponential_backoff_poll(|| {
+            let events = subscription.poll().unwrap();
+            if events.is_empty() {
+                None
+            } else {
+                Some(events)
+            }
+        })
+    }
+
+    #[test]
+    #[serial]
+    fn test_script_status() {
+        let descriptors = testutils! {
+            @descriptors ( "wpkh(Alice)" ) ( "wpkh(Alice)" ) ( @keys ( "Alice" => (@generate_xprv "/44'/0'/0'/0/*", "/44'/0'/0'/1/*") ) )
+        };
+        let wallet = Wallet::new_offline(
+            &descriptors.0,
+            descriptors.1.as_ref(),
+            Network::Regtest,
+            MemoryDatabase::new(),
+        )
+        .unwrap();
+        let used = wallet
+            .get_address(AddressIndex::New)
+            .unwrap()
+            .script_pubkey();
+        let unused = wallet
+            .get_address(AddressIndex::New)
+            .unwrap()
+            .script_pubkey();
+
+        let mut test_client = TestClient::default();
+        test_client.receive(testutils! {
+            @tx ( (@external descriptors, 0) => 50_000 )
+        });
+        test_client.generate(1, None);
+
+        let client = Client::new(&get_electrum_url()).unwrap();
+        let history = client.script_get_history(&used).unwrap();
+        let statuses = client
+            .els_batch_script_status(vec![&used, &unused])
+            .unwrap();
+        assert_eq!(
+            statuses[0].status,
+            ElsScriptStatus::from_history(
+                history
+                    .into_iter()
+                    .map(|el| ElsGetHistoryRes {
+                        height: el.height,
+                        tx_hash: el.tx_hash,
+                    })
+                    .collect()
+            )
+            .status
+        );
+        assert_eq!(statuses[1].status, None);
+
+        // the subscriptions of the client are left untouched
+        client.script_subscribe(&used).unwrap();
+        let subscribed_statuses = client.els_batch_script_status(vec![&used]).unwrap();
+        assert_eq!(subscribed_statuses[0].status, statuses[0].status);
+        assert!(client.script_pop(&used).is_ok());
+    }
+
+    #[test]
+    #[serial]
+    fn test_subscription_events() {
+        let descriptors = testutils! {
+            @descriptors ( "wpkh(Alice)" ) ( "wpkh(Alice)" ) ( @keys ( "Alice" => (@generate_xprv "/44'/0'/0'/0/*", "/44'/0'/0'/1/*") ) )
+        };
+        let wallet = Wallet::new_offline(
+            &descriptors.0,
+            descriptors.1.as_ref(),
+            Network::Regtest,
+            MemoryDatabase::new(),
+        )
+        .unwrap();
+        let script = wallet
+            .get_address(AddressIndex::New)
+            .unwrap()
+            .script_pubkey();
+
+        let mut test_client = TestClient::default();
+        let blockchain = ElectrumBlockchain::from(Client::new(&get_electrum_url()).unwrap());
+        let mut subscription = blockchain.subscribe(vec![script.clone()]).unwrap();
+
+        let txid = test_client.receive(testutils! {
+            @tx ( (@external descriptors, 0) => 50_000 )
+        });
+        assert_eq!(
+            next_events(&mut subscription),
+            vec![ElectrumEvent::NewTransaction {
+                txid,
+                script: script.clone(),
+                height: None
+            }]
+        );
+
+        test_client.generate(1, None);
+        let events = next_events(&mut subscription);
+        let height = match events.first() {
+            Some(ElectrumEvent::NewBlock { height, .. }) => *height,
+            _ => panic!("missing new block event"),
+        };
+        let mut updates = events[1..].to_vec();
+        if updates.is_empty() {
+            // the script notification may come later than the block one
+            updates = next_events(&mut subscription);
+        }
+        assert_eq!(
+            updates,
+            vec![ElectrumEvent::TransactionUpdated {
+                txid,
+                script,
+                height: Some(height)
+            }]
+        );
+    }
+}
@@ -39,7 +39,7 @@ use bitcoin::hashes::hex::{FromHex, ToHex};
 use bitcoin::hashes::{sha256, Hash};
 use bitcoin::{BlockHash, BlockHeader, Script, Transaction, Txid};
 
-use self::utils::{ElectrumLikeSync, ElsGetHistoryRes};
+use self::utils::{ElectrumLikeSync, ElsGetHistoryRes, ElsScriptStatus};
 use super::*;
 use crate::database::BatchDatabase;
 use crate::error::Error;
@@ -274,6 +274,47 @@ impl UrlClient {
         Ok(result)
     }
 
+    async fn _script_get_status(&self, script: &Script) -> Result<ElsScriptStatus, EsploraError> {
+        let stats = self
+            .client
+            .get(&format!(
+                "{}/scripthash/{}",
+                self.url,
+                Self::script_to_scripthash(script)
+            ))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<EsploraScriptStats>()
+            .await?;
+
+        // the stats only contain counts and sums: an unconfirmed tx replaced by another one that
+        // moves the same amounts wouldn't change them, so the history is always downloaded when
+        // there are unconfirmed txs. The status then matches the one computed by Electrum
+        // servers, and differs from the hash of the stats once they are all confirmed.
+        //
+        // Without unconfirmed txs the stats change every time a tx is confirmed, but not when a
+        // confirmed tx is moved to a different block by a reorg
+        if stats.mempool_stats.tx_count > 0 {
+            return Ok(ElsScriptStatus::from_history(
+                self._script_get_history(script).await?,
+            ));
+        }
+
+        let status = if stats.chain_stats.tx_count == 0 {
+            None
+        } else {
+            Some(sha256::Hash::hash(
+                format!("{}|{}", stats.chain_stats, stats.mempool_stats).as_bytes(),
+            ))
+        };
+
+        Ok(ElsScriptStatus {
+            status,
+            history: None,
+        })
+    }
+
     async fn _get_fee_estimates(&self) -> Result<HashMap<String, f64>, EsploraError> {
         Ok(self
             .client
@@ -308,6 +349,26 @@ impl ElectrumLikeSync for UrlClient {
         await_or_block!(future)
     }
 
+    fn els_batch_script_status<'s, I: IntoIterator<Item = &'s Script>>(
+        &self,
+        scripts: I,
+    ) -> Result<Vec<ElsScriptStatus>, Error> {
+        let future = async {
+            let mut results = vec![];
+            for chunk in ChunksIterator::new(scripts.into_iter(), self.concurrency as usize) {
+                let mut futs = FuturesOrdered::new();
+                for script in chunk {
+                    futs.push(self._script_get_status(&script));
+                }
+                let partial_results: Vec<ElsScriptStatus> = futs.try_collect().await?;
+                results.extend(partial_results);
+            }
+            Ok(stream::iter(results).collect().await)
+        };
+
+        await_or_block!(future)
+    }
+
     fn els_batch_transaction_get<'s, I: IntoIterator<Item = &'s Txid>>(
         &self,
         txids: I,
@@ -347,6 +408,10 @@ impl ElectrumLikeSync for UrlClient {
 
         await_or_block!(future)
     }
+
+    fn els_get_height(&self) -> Result<u32, Error> {
+        Ok(await_or_block!(self._get_height())?)
+    }
 }
 
 #[derive(Deserialize)]
@@ -360,6 +425,35 @@ struct EsploraGetHistory {
     status: EsploraGetHistoryStatus,
 }
 
+#[derive(Deserialize)]
+struct EsploraStats {
+    funded_txo_count: u64,
+    funded_txo_sum: u64,
+    spent_txo_count: u64,
+    spent_txo_sum: u64,
+    tx_count: u64,
+}
+
+impl fmt::Display for EsploraStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}:{}:{}",
+            self.funded_txo_count,
+            self.funded_txo_sum,
+            self.spent_txo_count,
+            self.spent_txo_sum,
+            self.tx_count
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct EsploraScriptStats {
+    chain_stats: EsploraStats,
+    mempool_stats: EsploraStats,
+}
+
 /// Configuration for an [`EsploraBlockchain`]
 #[derive(Debug, serde::Deserialize, serde::Serialize, Clone, PartialEq)]
 pub struct EsploraBlockchainConfig {
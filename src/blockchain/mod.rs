@@ -42,6 +42,8 @@ pub mod electrum;
 pub use self::electrum::ElectrumBlockchain;
 #[cfg(feature = "electrum")]
 pub use self::electrum::ElectrumBlockchainConfig;
+#[cfg(feature = "electrum")]
+pub use self::electrum::{ElectrumEvent, ElectrumSubscription};
 
 #[cfg(feature = "rpc")]
 pub mod rpc;
@@ -38,7 +38,7 @@ use crate::wallet::utils::SecpCtx;
 use crate::{ConfirmationTime, Error, FeeRate, KeychainKind, LocalUtxo, TransactionDetails};
 use bitcoincore_rpc::json::{
     GetAddressInfoResultLabel, ImportMultiOptions, ImportMultiRequest,
-    ImportMultiRequestScriptPubkey, ImportMultiRescanSince,
+    ImportMultiRequestScriptPubkey, ImportMultiRescanSince, WalletTxInfo,
 };
 use bitcoincore_rpc::jsonrpc::serde_json::Value;
 use bitcoincore_rpc::{Auth, Client, RpcApi};
@@ -191,8 +191,7 @@ impl Blockchain for RpcBlockchain {
             let txid = tx_result.info.txid;
             list_txs_ids.insert(txid);
             if let Some(mut known_tx) = known_txs.get_mut(&txid) {
-                let confirmation_time =
-                    ConfirmationTime::new(tx_result.info.blockheight, tx_result.info.blocktime);
+                let confirmation_time = confirmation_time(&tx_result.info);
                 if confirmation_time != known_tx.confirmation_time {
                     // reorg may change tx height
                     debug!(
@@ -228,14 +227,12 @@ impl Blockchain for RpcBlockchain {
                 let td = TransactionDetails {
                     transaction: Some(tx),
                     txid: tx_result.info.txid,
-                    confirmation_time: ConfirmationTime::new(
-                        tx_result.info.blockheight,
-                        tx_result.info.blocktime,
-                    ),
+                    confirmation_time: confirmation_time(&tx_result.info),
                     received,
                     sent,
                     fee: tx_result.fee.map(|f| f.as_sat().abs() as u64),
                     verified: true,
+                    ancestors_fee: None,
                 };
                 debug!(
                     "saving tx: {} tx_result.fee:{:?} td.fees:{:?}",
@@ -265,6 +262,10 @@ impl Blockchain for RpcBlockchain {
                         value: u.amount.as_sat(),
                         script_pubkey: u.script_pub_key,
                     },
+                    is_coinbase: db
+                        .get_raw_tx(&u.txid)?
+                        .map(|tx| tx.is_coin_base())
+                        .unwrap_or(false),
                 })
             })
             .collect::<Result<_, Error>>()?;
@@ -406,6 +407,14 @@ where
     Ok(wallet_name)
 }
 
+/// return the confirmation time of a wallet tx, including the hash of the block
+fn confirmation_time(info: &WalletTxInfo) -> Option<ConfirmationTime> {
+    ConfirmationTime::new(info.blockheight, info.blocktime).map(|c| ConfirmationTime {
+        block_hash: info.blockhash,
+        ..c
+    })
+}
+
 /// return the wallets available in default wallet directory
 //TODO use bitcoincore_rpc method when PR #179 lands
 fn list_wallet_dir(client: &Client) -> Result<Vec<String>, Error> {
@@ -512,7 +521,7 @@ mod test {
         assert_eq!(expected_address, address.to_string());
         send_to_address(&bitcoind, &address, 100_000);
         wallet.sync(noop_progress(), None).unwrap();
-        assert_eq!(wallet.get_balance().unwrap(), 100_000);
+        assert_eq!(wallet.get_balance().unwrap().get_total(), 100_000);
 
         let mut builder = wallet.build_tx();
         builder.add_recipient(node_address.script_pubkey(), 50_000);
@@ -523,7 +532,7 @@ mod test {
         wallet.broadcast(tx).unwrap();
         wallet.sync(noop_progress(), None).unwrap();
         assert_eq!(
-            wallet.get_balance().unwrap(),
+            wallet.get_balance().unwrap().get_total(),
             100_000 - 50_000 - details.fee.unwrap_or(0)
         );
         drop(wallet);
@@ -544,7 +553,7 @@ mod test {
         wallet_skip.sync(noop_progress(), None).unwrap();
         send_to_address(&bitcoind, &address, 100_000);
         wallet_skip.sync(noop_progress(), None).unwrap();
-        assert_eq!(wallet_skip.get_balance().unwrap(), 100_000);
+        assert_eq!(wallet_skip.get_balance().unwrap().get_total(), 100_000);
     }
 
     #[test]
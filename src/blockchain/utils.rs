@@ -16,6 +16,7 @@ use log::{debug, error, info, trace};
 use rand::seq::SliceRandom;
 use rand::thread_rng;
 
+use bitcoin::hashes::{sha256, Hash, HashEngine};
 use bitcoin::{BlockHeader, OutPoint, Script, Transaction, Txid};
 
 use super::*;
@@ -25,12 +26,47 @@ use crate::types::{ConfirmationTime, KeychainKind, LocalUtxo, TransactionDetails
 use crate::wallet::time::Instant;
 use crate::wallet::utils::ChunksIterator;
 
+/// Number of blocks after which a confirmed transaction is considered final: the block containing
+/// it is not checked against the current chain anymore to detect reorgs
+const REORG_CHECK_DEPTH: u32 = 100;
+
 #[derive(Debug)]
 pub struct ElsGetHistoryRes {
     pub height: i32,
     pub tx_hash: Txid,
 }
 
+/// Status of the history of a script
+#[derive(Debug)]
+pub struct ElsScriptStatus {
+    /// Hash that changes every time the history of the script changes, `None` if the history is
+    /// empty
+    pub status: Option<sha256::Hash>,
+    /// The history of the script, if it was downloaded to compute the status
+    pub history: Option<Vec<ElsGetHistoryRes>>,
+}
+
+impl ElsScriptStatus {
+    /// Compute the status from the full history, like Electrum servers do: the hash of the
+    /// concatenation of `txid:height:` for every transaction
+    pub fn from_history(history: Vec<ElsGetHistoryRes>) -> Self {
+        let status = if history.is_empty() {
+            None
+        } else {
+            let mut engine = sha256::Hash::engine();
+            for el in history.iter() {
+                engine.input(format!("{}:{}:", el.tx_hash, el.height).as_bytes());
+            }
+            Some(sha256::Hash::from_engine(engine))
+        };
+
+        ElsScriptStatus {
+            status,
+            history: Some(history),
+        }
+    }
+}
+
 /// Implements the synchronization logic for an Electrum-like client.
 #[maybe_async]
 pub trait ElectrumLikeSync {
@@ -49,8 +85,26 @@ pub trait ElectrumLikeSync {
         heights: I,
     ) -> Result<Vec<BlockHeader>, Error>;
 
+    fn els_get_height(&self) -> Result<u32, Error>;
+
     // Provided methods down here...
 
+    /// Return the status of the history of each script
+    ///
+    /// The default implementation downloads the full history to compute it, backends that can
+    /// cheaply query a status should override this method so that the history is only downloaded
+    /// for the scripts whose status changed.
+    fn els_batch_script_status<'s, I: IntoIterator<Item = &'s Script> + Clone>(
+        &self,
+        scripts: I,
+    ) -> Result<Vec<ElsScriptStatus>, Error> {
+        let histories = maybe_await!(self.els_batch_script_get_history(scripts))?;
+        Ok(histories
+            .into_iter()
+            .map(ElsScriptStatus::from_history)
+            .collect())
+    }
+
     fn electrum_like_setup<D: BatchDatabase, P: Progress>(
         &self,
         stop_gap: Option<usize>,
@@ -67,32 +121,48 @@ pub trait ElectrumLikeSync {
         let mut history_txs_id = HashSet::new();
         let mut txid_height = HashMap::new();
         let mut max_indexes = HashMap::new();
+        let mut changed_statuses = HashMap::new();
 
         let mut wallet_chains = vec![KeychainKind::Internal, KeychainKind::External];
         // shuffling improve privacy, the server doesn't know my first request is from my internal or external addresses
         wallet_chains.shuffle(&mut thread_rng());
-        // download history of our internal and external script_pubkeys
+        // download history of our internal and external script_pubkeys whose status changed since
+        // the last sync
         for keychain in wallet_chains.iter() {
             let script_iter = db.iter_script_pubkeys(Some(*keychain))?.into_iter();
 
             for (i, chunk) in ChunksIterator::new(script_iter, stop_gap).enumerate() {
                 // TODO if i == last, should create another chunk of addresses in db
-                let call_result: Vec<Vec<ElsGetHistoryRes>> =
-                    maybe_await!(self.els_batch_script_get_history(chunk.iter()))?;
+                let call_result: Vec<ElsScriptStatus> =
+                    maybe_await!(self.els_batch_script_status(chunk.iter()))?;
                 let max_index = call_result
                     .iter()
                     .enumerate()
-                    .filter_map(|(i, v)| v.first().map(|_| i as u32))
+                    .filter_map(|(i, v)| v.status.map(|_| i as u32))
                     .max();
                 if let Some(max) = max_index {
                     max_indexes.insert(keychain, max + (i * chunk_size) as u32);
                 }
-                let flattened: Vec<ElsGetHistoryRes> = call_result.into_iter().flatten().collect();
-                debug!("#{} of {:?} results:{}", i, keychain, flattened.len());
-                if flattened.is_empty() {
-                    // Didn't find anything in the last `stop_gap` script_pubkeys, breaking
-                    break;
+
+                let mut histories = vec![];
+                let mut to_download = vec![];
+                for (script, script_status) in chunk.iter().zip(call_result) {
+                    if db.get_script_status(script)? == script_status.status {
+                        continue;
+                    }
+
+                    changed_statuses.insert(script.clone(), script_status.status);
+                    match script_status.history {
+                        Some(history) => histories.push(history),
+                        None => to_download.push(script),
+                    }
                 }
+                if !to_download.is_empty() {
+                    histories.extend(maybe_await!(self.els_batch_script_get_history(to_download))?);
+                }
+
+                let flattened: Vec<ElsGetHistoryRes> = histories.into_iter().flatten().collect();
+                debug!("#{} of {:?} results:{}", i, keychain, flattened.len());
 
                 for el in flattened {
                     // el.height = -1 means unconfirmed with unconfirmed parents
@@ -105,6 +175,11 @@ pub trait ElectrumLikeSync {
                     }
                     history_txs_id.insert(el.tx_hash);
                 }
+
+                if max_index.is_none() {
+                    // Didn't find anything in the last `stop_gap` script_pubkeys, breaking
+                    break;
+                }
             }
         }
 
@@ -116,6 +191,13 @@ pub trait ElectrumLikeSync {
             }
         }
 
+        // even when no status changed the blocks of the most recent txs are checked below, since
+        // a reorg can confirm them again at the same height without changing the history
+        info!(
+            "{} scripts changed since the last sync",
+            changed_statuses.len()
+        );
+
         // get db status
         let txs_details_in_db: HashMap<Txid, TransactionDetails> = db
             .iter_txs(false)?
@@ -127,6 +209,25 @@ pub trait ElectrumLikeSync {
             .into_iter()
             .map(|tx| (tx.txid(), tx))
             .collect();
+
+        // txs that don't involve any of the changed scripts are still in the history of the
+        // unchanged ones, at the same height
+        for (txid, tx_details) in txs_details_in_db.iter() {
+            if history_txs_id.contains(txid) {
+                continue;
+            }
+            let involves_changed = txs_raw_in_db
+                .get(txid)
+                .map(|tx| involves_scripts(tx, &txs_raw_in_db, &changed_statuses))
+                .unwrap_or(false);
+            if !involves_changed {
+                history_txs_id.insert(*txid);
+                txid_height.insert(
+                    *txid,
+                    tx_details.confirmation_time.as_ref().map(|c| c.height),
+                );
+            }
+        }
         let utxos_deps = utxos_deps(db, &txs_raw_in_db)?;
 
         // download new txs and headers
@@ -136,7 +237,7 @@ pub trait ElectrumLikeSync {
             chunk_size,
             db
         ))?;
-        let new_timestamps = maybe_await!(self.download_needed_headers(
+        let new_headers = maybe_await!(self.download_needed_headers(
             &txid_height,
             &txs_details_in_db,
             chunk_size
@@ -144,15 +245,32 @@ pub trait ElectrumLikeSync {
 
         let mut batch = db.begin_batch();
 
-        // save any tx details not in db but in history_txs_id or with different height/timestamp
+        // save any tx details not in db but in history_txs_id or with a different confirmation
         for txid in history_txs_id.iter() {
-            let height = txid_height.get(txid).cloned().flatten();
-            let timestamp = new_timestamps.get(txid).cloned();
-            if let Some(tx_details) = txs_details_in_db.get(txid) {
-                // check if tx height matches, otherwise updates it. timestamp is not in the if clause
-                // because we are not asking headers for confirmed tx we know about
-                if tx_details.confirmation_time.as_ref().map(|c| c.height) != height {
-                    let confirmation_time = ConfirmationTime::new(height, timestamp);
+            let known_details = txs_details_in_db.get(txid);
+            let confirmation_time = match txid_height.get(txid).cloned().flatten() {
+                None => None,
+                Some(height) => match new_headers.get(&height) {
+                    Some(header) => Some(ConfirmationTime::from_header(height, header)),
+                    // we don't download the headers of old txs we already know about
+                    None => Some(
+                        known_details
+                            .and_then(|details| details.confirmation_time.clone())
+                            .ok_or_else(|| Error::Generic("header missing".to_string()))?,
+                    ),
+                },
+            };
+
+            if let Some(tx_details) = known_details {
+                if tx_details.confirmation_time != confirmation_time {
+                    if let Some(ConfirmationTime {
+                        block_hash: Some(block_hash),
+                        ..
+                    }) = &tx_details.confirmation_time
+                    {
+                        info!("tx {} was in orphaned block {}", txid, block_hash);
+                    }
+
                     let mut new_tx_details = tx_details.clone();
                     new_tx_details.confirmation_time = confirmation_time;
                     batch.set_tx(&new_tx_details)?;
@@ -161,18 +279,40 @@ pub trait ElectrumLikeSync {
                 save_transaction_details_and_utxos(
                     txid,
                     db,
-                    timestamp,
-                    height,
+                    confirmation_time,
                     &mut batch,
                     &utxos_deps,
                 )?;
             }
         }
 
-        // remove any tx details in db but not in history_txs_id
-        for txid in txs_details_in_db.keys() {
-            if !history_txs_id.contains(txid) {
+        // remove any tx details in db but not in history_txs_id, for example because they were
+        // double-spent after a reorg, and roll back their effects on our utxos
+        let removed_txids: Vec<_> = txs_details_in_db
+            .keys()
+            .filter(|txid| !history_txs_id.contains(*txid))
+            .collect();
+        if !removed_txids.is_empty() {
+            let spent_in_history: HashSet<OutPoint> = txs_raw_in_db
+                .values()
+                .chain(new_txs.iter())
+                .filter(|tx| history_txs_id.contains(&tx.txid()))
+                .flat_map(|tx| tx.input.iter().map(|input| input.previous_output))
+                .collect();
+
+            for txid in removed_txids {
+                debug!("removing tx {} not in history anymore", txid);
                 batch.del_tx(txid, false)?;
+
+                if let Some(tx) = txs_raw_in_db.get(txid) {
+                    rollback_transaction_utxos(
+                        tx,
+                        db,
+                        &history_txs_id,
+                        &spent_in_history,
+                        &mut batch,
+                    )?;
+                }
             }
         }
 
@@ -183,6 +323,16 @@ pub trait ElectrumLikeSync {
             }
         }
 
+        // store the new statuses, so that the next sync can skip the unchanged scripts
+        for (script, status) in changed_statuses.iter() {
+            match status {
+                Some(status) => batch.set_script_status(script, status)?,
+                None => {
+                    batch.del_script_status(script)?;
+                }
+            }
+        }
+
         db.commit_batch(batch)?;
         info!("finish setup, elapsed {:?}ms", start.elapsed().as_millis());
 
@@ -231,45 +381,49 @@ pub trait ElectrumLikeSync {
         Ok(txs_downloaded)
     }
 
-    /// download headers at heights in `txid_height` if tx details not already present, returns a map Txid -> timestamp
+    /// download headers at heights in `txid_height`, returns a map height -> header
+    ///
+    /// Headers are not downloaded for txs already in db at the same height, unless they are less
+    /// than [`REORG_CHECK_DEPTH`] blocks deep (compared to the chain tip) and their block may
+    /// still be orphaned, or the hash of their block is unknown.
     fn download_needed_headers(
         &self,
         txid_height: &HashMap<Txid, Option<u32>>,
         txs_details_in_db: &HashMap<Txid, TransactionDetails>,
         chunk_size: usize,
-    ) -> Result<HashMap<Txid, u64>, Error> {
-        let mut txid_timestamp = HashMap::new();
-        let txid_in_db_with_conf: HashSet<_> = txs_details_in_db
-            .values()
-            .filter_map(|details| details.confirmation_time.as_ref().map(|_| details.txid))
-            .collect();
-        let needed_txid_height: HashMap<&Txid, u32> = txid_height
+    ) -> Result<HashMap<u32, BlockHeader>, Error> {
+        let tip_height = maybe_await!(self.els_get_height())?;
+        let needed_heights: HashSet<u32> = txid_height
             .iter()
-            .filter(|(t, _)| !txid_in_db_with_conf.contains(*t))
-            .filter_map(|(t, o)| o.map(|h| (t, h)))
+            .filter_map(|(txid, height)| {
+                let height = (*height)?;
+                match txs_details_in_db
+                    .get(txid)
+                    .and_then(|details| details.confirmation_time.as_ref())
+                {
+                    Some(conf)
+                        if conf.height == height
+                            && conf.block_hash.is_some()
+                            && height + REORG_CHECK_DEPTH <= tip_height =>
+                    {
+                        None
+                    }
+                    _ => Some(height),
+                }
+            })
             .collect();
-        let needed_heights: HashSet<u32> = needed_txid_height.values().cloned().collect();
+
+        let mut height_header = HashMap::new();
         if !needed_heights.is_empty() {
-            info!("{} headers to download for timestamp", needed_heights.len());
-            let mut height_timestamp: HashMap<u32, u64> = HashMap::new();
+            info!("{} headers to download", needed_heights.len());
             for chunk in ChunksIterator::new(needed_heights.into_iter(), chunk_size) {
                 let call_result: Vec<BlockHeader> =
                     maybe_await!(self.els_batch_block_header(chunk.clone()))?;
-                height_timestamp.extend(
-                    chunk
-                        .into_iter()
-                        .zip(call_result.iter().map(|h| h.time as u64)),
-                );
-            }
-            for (txid, height) in needed_txid_height {
-                let timestamp = height_timestamp
-                    .get(&height)
-                    .ok_or_else(|| Error::Generic("timestamp missing".to_string()))?;
-                txid_timestamp.insert(*txid, *timestamp);
+                height_header.extend(chunk.into_iter().zip(call_result));
             }
         }
 
-        Ok(txid_timestamp)
+        Ok(height_header)
     }
 
     fn download_and_save_in_chunks<D: BatchDatabase>(
@@ -297,8 +451,7 @@ pub trait ElectrumLikeSync {
 fn save_transaction_details_and_utxos<D: BatchDatabase>(
     txid: &Txid,
     db: &mut D,
-    timestamp: Option<u64>,
-    height: Option<u32>,
+    confirmation_time: Option<ConfirmationTime>,
     updates: &mut dyn BatchOperations,
     utxo_deps: &HashMap<OutPoint, OutPoint>,
 ) -> Result<(), Error> {
@@ -349,6 +502,7 @@ fn save_transaction_details_and_utxos<D: BatchDatabase>(
                 outpoint: OutPoint::new(tx.txid(), i as u32),
                 txout: output.clone(),
                 keychain,
+                is_coinbase: tx.is_coin_base(),
             })?;
 
             incoming += output.value;
@@ -360,15 +514,82 @@ fn save_transaction_details_and_utxos<D: BatchDatabase>(
         transaction: Some(tx),
         received: incoming,
         sent: outgoing,
-        confirmation_time: ConfirmationTime::new(height, timestamp),
+        verified: confirmation_time.is_some(),
+        ancestors_fee: None,
+        confirmation_time,
         fee: Some(inputs_sum.saturating_sub(outputs_sum)), /* if the tx is a coinbase, fees would be negative */
-        verified: height.is_some(),
     };
     updates.set_tx(&tx_details)?;
 
     Ok(())
 }
 
+/// remove the utxos created by `tx`, which is not part of the history anymore, and restore the
+/// utxos it spent unless they are spent by another tx in history
+fn rollback_transaction_utxos<D: BatchDatabase>(
+    tx: &Transaction,
+    db: &D,
+    history_txs_id: &HashSet<Txid>,
+    spent_in_history: &HashSet<OutPoint>,
+    updates: &mut dyn BatchOperations,
+) -> Result<(), Error> {
+    let txid = tx.txid();
+    for vout in 0..tx.output.len() {
+        updates.del_utxo(&OutPoint::new(txid, vout as u32))?;
+    }
+
+    for input in tx.input.iter() {
+        let previous_output = input.previous_output;
+        if !history_txs_id.contains(&previous_output.txid)
+            || spent_in_history.contains(&previous_output)
+        {
+            continue;
+        }
+
+        let previous_tx = match db.get_raw_tx(&previous_output.txid)? {
+            Some(previous_tx) => previous_tx,
+            None => continue,
+        };
+        let txout = previous_tx
+            .output
+            .get(previous_output.vout as usize)
+            .ok_or(Error::InvalidOutpoint(previous_output))?;
+        if let Some((keychain, _)) = db.get_path_from_script_pubkey(&txout.script_pubkey)? {
+            debug!("restoring utxo {} spent by {}", previous_output, txid);
+            updates.set_utxo(&LocalUtxo {
+                outpoint: previous_output,
+                txout: txout.clone(),
+                keychain,
+                is_coinbase: previous_tx.is_coin_base(),
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// returns whether `tx` spends from or sends to any of `scripts`, looking up the previous outputs
+/// in `txs_raw_in_db`
+fn involves_scripts<V>(
+    tx: &Transaction,
+    txs_raw_in_db: &HashMap<Txid, Transaction>,
+    scripts: &HashMap<Script, V>,
+) -> bool {
+    let spends_from = tx.input.iter().any(|input| {
+        txs_raw_in_db
+            .get(&input.previous_output.txid)
+            .and_then(|prev_tx| prev_tx.output.get(input.previous_output.vout as usize))
+            .map(|prev_out| scripts.contains_key(&prev_out.script_pubkey))
+            .unwrap_or(false)
+    });
+
+    spends_from
+        || tx
+            .output
+            .iter()
+            .any(|output| scripts.contains_key(&output.script_pubkey))
+}
+
 /// returns utxo dependency as the inputs needed for the utxo to exist
 /// `tx_raw_in_db` must contains utxo's generating txs or errors witt [crate::Error::TransactionNotFound]
 fn utxos_deps<D: BatchDatabase>(
@@ -387,3 +608,194 @@ fn utxos_deps<D: BatchDatabase>(
     }
     Ok(utxos_deps)
 }
+
+#[cfg(all(test, not(feature = "async-interface")))]
+mod test {
+    use std::cell::{Cell, RefCell};
+
+    use bitcoin::{TxIn, TxOut};
+
+    use super::*;
+    use crate::database::{Database, MemoryDatabase};
+
+    // Serves a fixed history and counts the requests for the full history of the scripts
+    struct TestClient {
+        history: Vec<(Script, Txid, i32)>,
+        txs: HashMap<Txid, Transaction>,
+        headers: RefCell<HashMap<u32, BlockHeader>>,
+        tip: u32,
+        history_requests: Cell<usize>,
+    }
+
+    impl TestClient {
+        fn get_history(&self, script: &Script) -> Vec<ElsGetHistoryRes> {
+            self.history
+                .iter()
+                .filter(|(s, _, _)| s == script)
+                .map(|(_, txid, height)| ElsGetHistoryRes {
+                    height: *height,
+                    tx_hash: *txid,
+                })
+                .collect()
+        }
+    }
+
+    impl ElectrumLikeSync for TestClient {
+        fn els_batch_script_get_history<'s, I: IntoIterator<Item = &'s Script> + Clone>(
+            &self,
+            scripts: I,
+        ) -> Result<Vec<Vec<ElsGetHistoryRes>>, Error> {
+            Ok(scripts
+                .into_iter()
+                .map(|script| {
+                    self.history_requests.set(self.history_requests.get() + 1);
+                    self.get_history(script)
+                })
+                .collect())
+        }
+
+        fn els_batch_script_status<'s, I: IntoIterator<Item = &'s Script> + Clone>(
+            &self,
+            scripts: I,
+        ) -> Result<Vec<ElsScriptStatus>, Error> {
+            Ok(scripts
+                .into_iter()
+                .map(|script| ElsScriptStatus {
+                    history: None,
+                    ..ElsScriptStatus::from_history(self.get_history(script))
+                })
+                .collect())
+        }
+
+        fn els_batch_transaction_get<'s, I: IntoIterator<Item = &'s Txid> + Clone>(
+            &self,
+            txids: I,
+        ) -> Result<Vec<Transaction>, Error> {
+            Ok(txids
+                .into_iter()
+                .map(|txid| self.txs[txid].clone())
+                .collect())
+        }
+
+        fn els_batch_block_header<I: IntoIterator<Item = u32> + Clone>(
+            &self,
+            heights: I,
+        ) -> Result<Vec<BlockHeader>, Error> {
+            let headers = self.headers.borrow();
+            Ok(heights.into_iter().map(|height| headers[&height]).collect())
+        }
+
+        fn els_get_height(&self) -> Result<u32, Error> {
+            Ok(self.tip)
+        }
+    }
+
+    fn get_header(nonce: u32) -> BlockHeader {
+        BlockHeader {
+            version: 1,
+            prev_blockhash: Default::default(),
+            merkle_root: Default::default(),
+            time: 0,
+            bits: 0,
+            nonce,
+        }
+    }
+
+    // A database with a few scripts and a client that knows a tx paying to the first one,
+    // confirmed at height 100
+    fn setup(tip: u32) -> (MemoryDatabase, TestClient, Txid) {
+        let mut db = MemoryDatabase::new();
+        let scripts: Vec<Script> = (0..5u8).map(|i| Script::from(vec![0x51 + i])).collect();
+        for (i, script) in scripts.iter().enumerate() {
+            db.set_script_pubkey(script, KeychainKind::External, i as u32)
+                .unwrap();
+        }
+
+        let tx = Transaction {
+            version: 1,
+            lock_time: 0,
+            input: vec![TxIn::default()],
+            output: vec![TxOut {
+                value: 50_000,
+                script_pubkey: scripts[0].clone(),
+            }],
+        };
+        let txid = tx.txid();
+        let client = TestClient {
+            history: vec![(scripts[0].clone(), txid, 100)],
+            txs: vec![(txid, tx)].into_iter().collect(),
+            headers: RefCell::new(vec![(100, get_header(0))].into_iter().collect()),
+            tip,
+            history_requests: Cell::new(0),
+        };
+
+        (db, client, txid)
+    }
+
+    #[test]
+    fn test_sync_unchanged_statuses() {
+        let (mut db, client, txid) = setup(150);
+
+        client
+            .electrum_like_setup(None, &mut db, noop_progress())
+            .unwrap();
+        assert_eq!(client.history_requests.get(), 1);
+        assert!(db.get_tx(&txid, false).unwrap().is_some());
+        assert_eq!(db.iter_utxos().unwrap().len(), 1);
+
+        client.history_requests.set(0);
+        client
+            .electrum_like_setup(None, &mut db, noop_progress())
+            .unwrap();
+        assert_eq!(client.history_requests.get(), 0);
+        assert!(db.get_tx(&txid, false).unwrap().is_some());
+        assert_eq!(db.iter_utxos().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_sync_reorg_same_height_unchanged_statuses() {
+        let (mut db, client, txid) = setup(150);
+
+        client
+            .electrum_like_setup(None, &mut db, noop_progress())
+            .unwrap();
+        let confirmation_time = db.get_tx(&txid, false).unwrap().unwrap().confirmation_time;
+        assert_eq!(
+            confirmation_time.unwrap().block_hash,
+            Some(get_header(0).block_hash())
+        );
+
+        // the tx is confirmed again at the same height, its history and status don't change
+        client.headers.borrow_mut().insert(100, get_header(1));
+        client.history_requests.set(0);
+        client
+            .electrum_like_setup(None, &mut db, noop_progress())
+            .unwrap();
+        assert_eq!(client.history_requests.get(), 0);
+        let confirmation_time = db.get_tx(&txid, false).unwrap().unwrap().confirmation_time;
+        assert_eq!(
+            confirmation_time.unwrap().block_hash,
+            Some(get_header(1).block_hash())
+        );
+    }
+
+    #[test]
+    fn test_sync_skip_final_headers() {
+        let (mut db, client, txid) = setup(200);
+
+        client
+            .electrum_like_setup(None, &mut db, noop_progress())
+            .unwrap();
+
+        // the tx is deep enough that its block is not checked anymore
+        client.headers.borrow_mut().clear();
+        client
+            .electrum_like_setup(None, &mut db, noop_progress())
+            .unwrap();
+        let confirmation_time = db.get_tx(&txid, false).unwrap().unwrap().confirmation_time;
+        assert_eq!(
+            confirmation_time.unwrap().block_hash,
+            Some(get_header(0).block_hash())
+        );
+    }
+}
@@ -65,6 +65,8 @@ macro_rules! impl_inner_method {
             $enum_name::Memory(inner) => inner.$name( $($args, )* ),
             #[cfg(feature = "key-value-db")]
             $enum_name::Sled(inner) => inner.$name( $($args, )* ),
+            #[cfg(feature = "sqlite")]
+            $enum_name::Sqlite(inner) => inner.$name( $($args, )* ),
         }
     }
 }
@@ -82,10 +84,15 @@ pub enum AnyDatabase {
     #[cfg_attr(docsrs, doc(cfg(feature = "key-value-db")))]
     /// Simple key-value embedded database based on [`sled`]
     Sled(sled::Tree),
+    #[cfg(feature = "sqlite")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "sqlite")))]
+    /// Single-file embedded database based on [`rusqlite`]
+    Sqlite(sqlite::SqliteDatabase),
 }
 
 impl_from!(memory::MemoryDatabase, AnyDatabase, Memory,);
 impl_from!(sled::Tree, AnyDatabase, Sled, #[cfg(feature = "key-value-db")]);
+impl_from!(sqlite::SqliteDatabase, AnyDatabase, Sqlite, #[cfg(feature = "sqlite")]);
 
 /// Type that contains any of the [`BatchDatabase::Batch`] types defined by the library
 pub enum AnyBatch {
@@ -95,6 +102,10 @@ pub enum AnyBatch {
     #[cfg_attr(docsrs, doc(cfg(feature = "key-value-db")))]
     /// Simple key-value embedded database based on [`sled`]
     Sled(<sled::Tree as BatchDatabase>::Batch),
+    #[cfg(feature = "sqlite")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "sqlite")))]
+    /// Single-file embedded database based on [`rusqlite`]
+    Sqlite(<sqlite::SqliteDatabase as BatchDatabase>::Batch),
 }
 
 impl_from!(
@@ -103,6 +114,7 @@ impl_from!(
     Memory,
 );
 impl_from!(<sled::Tree as BatchDatabase>::Batch, AnyBatch, Sled, #[cfg(feature = "key-value-db")]);
+impl_from!(<sqlite::SqliteDatabase as BatchDatabase>::Batch, AnyBatch, Sqlite, #[cfg(feature = "sqlite")]);
 
 impl BatchOperations for AnyDatabase {
     fn set_script_pubkey(
@@ -132,6 +144,12 @@ impl BatchOperations for AnyDatabase {
     fn set_last_index(&mut self, keychain: KeychainKind, value: u32) -> Result<(), Error> {
         impl_inner_method!(AnyDatabase, self, set_last_index, keychain, value)
     }
+    fn set_label(&mut self, label: &Label) -> Result<(), Error> {
+        impl_inner_method!(AnyDatabase, self, set_label, label)
+    }
+    fn set_script_status(&mut self, script: &Script, status: &sha256::Hash) -> Result<(), Error> {
+        impl_inner_method!(AnyDatabase, self, set_script_status, script, status)
+    }
 
     fn del_script_pubkey_from_path(
         &mut self,
@@ -168,6 +186,16 @@ impl BatchOperations for AnyDatabase {
     fn del_last_index(&mut self, keychain: KeychainKind) -> Result<Option<u32>, Error> {
         impl_inner_method!(AnyDatabase, self, del_last_index, keychain)
     }
+    fn del_label(
+        &mut self,
+        label_type: LabelType,
+        reference: &str,
+    ) -> Result<Option<Label>, Error> {
+        impl_inner_method!(AnyDatabase, self, del_label, label_type, reference)
+    }
+    fn del_script_status(&mut self, script: &Script) -> Result<Option<sha256::Hash>, Error> {
+        impl_inner_method!(AnyDatabase, self, del_script_status, script)
+    }
 }
 
 impl Database for AnyDatabase {
@@ -197,6 +225,9 @@ impl Database for AnyDatabase {
     fn iter_txs(&self, include_raw: bool) -> Result<Vec<TransactionDetails>, Error> {
         impl_inner_method!(AnyDatabase, self, iter_txs, include_raw)
     }
+    fn iter_labels(&self) -> Result<Vec<Label>, Error> {
+        impl_inner_method!(AnyDatabase, self, iter_labels)
+    }
 
     fn get_script_pubkey_from_path(
         &self,
@@ -229,10 +260,23 @@ impl Database for AnyDatabase {
     fn get_last_index(&self, keychain: KeychainKind) -> Result<Option<u32>, Error> {
         impl_inner_method!(AnyDatabase, self, get_last_index, keychain)
     }
+    fn get_label(&self, label_type: LabelType, reference: &str) -> Result<Option<Label>, Error> {
+        impl_inner_method!(AnyDatabase, self, get_label, label_type, reference)
+    }
+    fn get_script_status(&self, script: &Script) -> Result<Option<sha256::Hash>, Error> {
+        impl_inner_method!(AnyDatabase, self, get_script_status, script)
+    }
 
     fn increment_last_index(&mut self, keychain: KeychainKind) -> Result<u32, Error> {
         impl_inner_method!(AnyDatabase, self, increment_last_index, keychain)
     }
+
+    fn get_format_version(&self) -> Result<Option<u32>, Error> {
+        impl_inner_method!(AnyDatabase, self, get_format_version)
+    }
+    fn migrate(&mut self) -> Result<(), Error> {
+        impl_inner_method!(AnyDatabase, self, migrate)
+    }
 }
 
 impl BatchOperations for AnyBatch {
@@ -256,6 +300,12 @@ impl BatchOperations for AnyBatch {
     fn set_last_index(&mut self, keychain: KeychainKind, value: u32) -> Result<(), Error> {
         impl_inner_method!(AnyBatch, self, set_last_index, keychain, value)
     }
+    fn set_label(&mut self, label: &Label) -> Result<(), Error> {
+        impl_inner_method!(AnyBatch, self, set_label, label)
+    }
+    fn set_script_status(&mut self, script: &Script, status: &sha256::Hash) -> Result<(), Error> {
+        impl_inner_method!(AnyBatch, self, set_script_status, script, status)
+    }
 
     fn del_script_pubkey_from_path(
         &mut self,
@@ -286,6 +336,16 @@ impl BatchOperations for AnyBatch {
     fn del_last_index(&mut self, keychain: KeychainKind) -> Result<Option<u32>, Error> {
         impl_inner_method!(AnyBatch, self, del_last_index, keychain)
     }
+    fn del_label(
+        &mut self,
+        label_type: LabelType,
+        reference: &str,
+    ) -> Result<Option<Label>, Error> {
+        impl_inner_method!(AnyBatch, self, del_label, label_type, reference)
+    }
+    fn del_script_status(&mut self, script: &Script) -> Result<Option<sha256::Hash>, Error> {
+        impl_inner_method!(AnyBatch, self, del_script_status, script)
+    }
 }
 
 impl BatchDatabase for AnyDatabase {
@@ -296,19 +356,26 @@ impl BatchDatabase for AnyDatabase {
             AnyDatabase::Memory(inner) => inner.begin_batch().into(),
             #[cfg(feature = "key-value-db")]
             AnyDatabase::Sled(inner) => inner.begin_batch().into(),
+            #[cfg(feature = "sqlite")]
+            AnyDatabase::Sqlite(inner) => inner.begin_batch().into(),
         }
     }
     fn commit_batch(&mut self, batch: Self::Batch) -> Result<(), Error> {
         match self {
             AnyDatabase::Memory(db) => match batch {
                 AnyBatch::Memory(batch) => db.commit_batch(batch),
-                #[cfg(feature = "key-value-db")]
-                _ => unimplemented!("Sled batch shouldn't be used with Memory db."),
+                #[cfg(any(feature = "key-value-db", feature = "sqlite"))]
+                _ => unimplemented!("Other batch shouldn't be used with Memory db."),
             },
             #[cfg(feature = "key-value-db")]
             AnyDatabase::Sled(db) => match batch {
                 AnyBatch::Sled(batch) => db.commit_batch(batch),
-                _ => unimplemented!("Memory batch shouldn't be used with Sled db."),
+                _ => unimplemented!("Other batch shouldn't be used with Sled db."),
+            },
+            #[cfg(feature = "sqlite")]
+            AnyDatabase::Sqlite(db) => match batch {
+                AnyBatch::Sqlite(batch) => db.commit_batch(batch),
+                _ => unimplemented!("Other batch shouldn't be used with Sqlite db."),
             },
         }
     }
@@ -346,6 +413,10 @@ pub enum AnyDatabaseConfig {
     #[cfg_attr(docsrs, doc(cfg(feature = "key-value-db")))]
     /// Simple key-value embedded database based on [`sled`]
     Sled(SledDbConfiguration),
+    #[cfg(feature = "sqlite")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "sqlite")))]
+    /// Single-file embedded database based on [`rusqlite`]
+    Sqlite(sqlite::SqliteDbConfiguration),
 }
 
 impl ConfigurableDatabase for AnyDatabase {
@@ -358,9 +429,14 @@ impl ConfigurableDatabase for AnyDatabase {
             }
             #[cfg(feature = "key-value-db")]
             AnyDatabaseConfig::Sled(inner) => AnyDatabase::Sled(sled::Tree::from_config(inner)?),
+            #[cfg(feature = "sqlite")]
+            AnyDatabaseConfig::Sqlite(inner) => {
+                AnyDatabase::Sqlite(sqlite::SqliteDatabase::from_config(inner)?)
+            }
         })
     }
 }
 
 impl_from!((), AnyDatabaseConfig, Memory,);
 impl_from!(SledDbConfiguration, AnyDatabaseConfig, Sled, #[cfg(feature = "key-value-db")]);
+impl_from!(sqlite::SqliteDbConfiguration, AnyDatabaseConfig, Sqlite, #[cfg(feature = "sqlite")]);
@@ -0,0 +1,757 @@
+// Bitcoin Dev Kit
+// Written in 2021 by the Bitcoin Dev Kit Developers
+//
+// Copyright (c) 2020-2021 Bitcoin Dev Kit Developers
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! Encrypted database
+//!
+//! This module defines [`EncryptedDatabase`], a wrapper that encrypts the data before handing it
+//! to any other [`Database`], so that the content stored on disk by the inner database doesn't
+//! reveal the addresses, transactions and balance of the wallet.
+//!
+//! Values are encrypted using ChaCha20-Poly1305 with a key derived from the one provided by the
+//! user. Since the inner database can only store the types defined in this library, encrypted
+//! values are carried inside fields that can hold arbitrary data:
+//!
+//! * script_pubkeys are stored as the encrypted bytes of the script, encrypted deterministically
+//!   so that they can still be looked up
+//! * utxos are stored with a blinded outpoint and the encrypted [`LocalUtxo`] in place of the
+//!   script_pubkey of the output
+//! * raw transactions and transaction metadata are stored as placeholder transactions that spend
+//!   a blinded outpoint and carry the encrypted data in the witness of their only input
+//! * labels are stored with a blinded reference and the encrypted [`Label`] in place of the text
+//!   of the label
+//! * descriptor checksums are replaced by a keyed hash of the checksum
+//! * script statuses are stored with the encrypted script and masked with a keystream derived
+//!   from the script
+//!
+//! The last derivation indexes, the keychain and child number of script_pubkeys and the type of
+//! labels are **not** encrypted, which means that the number of addresses generated by the wallet
+//! is visible to anyone with access to the inner database.
+//!
+//! ## Example
+//!
+//! ```
+//! # use bdk::database::{EncryptedDatabase, MemoryDatabase};
+//! # use bdk::bitcoin::Network;
+//! # use bdk::Wallet;
+//! let key = [0x42; 32];
+//! let database = EncryptedDatabase::new(MemoryDatabase::default(), key);
+//! let wallet = Wallet::new_offline("wpkh(tpubD6NzVbkrYhZ4XHndKkuB8FifXm8r5FQHwrN6oZuWCz13qb93rtgKvD4PQsqC4HP4yhV3tA2fqr2RbY5mNXfM7RxXUoeABoDtsFUq2zJq6YK/0/*)", None, Network::Testnet, database)?;
+//! # Ok::<(), bdk::Error>(())
+//! ```
+
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+use bitcoin::consensus::encode::{deserialize, serialize};
+use bitcoin::hash_types::Txid;
+use bitcoin::hashes::hex::{FromHex, ToHex};
+use bitcoin::hashes::hmac::{Hmac, HmacEngine};
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::{OutPoint, Script, Transaction, TxIn, TxOut};
+
+use crate::database::{migration, BatchDatabase, BatchOperations, Database};
+use crate::error::Error;
+use crate::types::*;
+
+const NONCE_LEN: usize = 12;
+
+// Domains used to separate the different types of data
+const DOMAIN_SCRIPT: &[u8] = b"script";
+const DOMAIN_UTXO: &[u8] = b"utxo";
+const DOMAIN_RAW_TX: &[u8] = b"raw_tx";
+const DOMAIN_TX: &[u8] = b"tx";
+const DOMAIN_CHECKSUM: &[u8] = b"checksum";
+const DOMAIN_LABEL: &[u8] = b"label";
+const DOMAIN_SCRIPT_STATUS: &[u8] = b"script_status";
+
+// `vout` of the blinded outpoint spent by placeholder transactions, used to tell raw
+// transactions apart from transaction metadata when iterating over the inner database
+const RAW_TX_VOUT: u32 = 0;
+const TX_VOUT: u32 = 1;
+
+fn hmac(key: &[u8], domain: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut engine = HmacEngine::<sha256::Hash>::new(key);
+    engine.input(domain);
+    engine.input(data);
+    Hmac::<sha256::Hash>::from_engine(engine).into_inner()
+}
+
+/// Keys derived from the user-provided key
+#[derive(Clone)]
+struct Keys {
+    cipher: ChaCha20Poly1305,
+    nonce: [u8; 32],
+    blind: [u8; 32],
+}
+
+impl std::fmt::Debug for Keys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Keys(..)")
+    }
+}
+
+impl Keys {
+    fn new(key: [u8; 32]) -> Self {
+        let encryption_key = hmac(&key, b"bdk/encrypted-db/encryption", &[]);
+
+        Keys {
+            cipher: ChaCha20Poly1305::new(&Key::from(encryption_key)),
+            nonce: hmac(&key, b"bdk/encrypted-db/nonce", &[]),
+            blind: hmac(&key, b"bdk/encrypted-db/blind", &[]),
+        }
+    }
+
+    /// Deterministically encrypt `data`, the same plaintext always produces the same ciphertext
+    fn encrypt(&self, domain: &[u8], data: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut nonce = [0; NONCE_LEN];
+        nonce.copy_from_slice(&hmac(&self.nonce, domain, data)[..NONCE_LEN]);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                &Nonce::from(nonce),
+                Payload {
+                    msg: data,
+                    aad: domain,
+                },
+            )
+            .map_err(|_| Error::Encryption)?;
+
+        Ok([&nonce[..], &ciphertext].concat())
+    }
+
+    fn decrypt(&self, domain: &[u8], data: &[u8]) -> Result<Vec<u8>, Error> {
+        if data.len() < NONCE_LEN {
+            return Err(Error::Encryption);
+        }
+
+        let (nonce_bytes, ciphertext) = data.split_at(NONCE_LEN);
+        let mut nonce = [0; NONCE_LEN];
+        nonce.copy_from_slice(nonce_bytes);
+        self.cipher
+            .decrypt(
+                &Nonce::from(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: domain,
+                },
+            )
+            .map_err(|_| Error::Encryption)
+    }
+
+    fn blind(&self, domain: &[u8], data: &[u8]) -> [u8; 32] {
+        hmac(&self.blind, domain, data)
+    }
+
+    fn encrypt_script(&self, script: &Script) -> Result<Script, Error> {
+        Ok(self.encrypt(DOMAIN_SCRIPT, script.as_bytes())?.into())
+    }
+
+    fn decrypt_script(&self, script: &Script) -> Result<Script, Error> {
+        Ok(self.decrypt(DOMAIN_SCRIPT, script.as_bytes())?.into())
+    }
+
+    fn blind_outpoint(&self, outpoint: &OutPoint) -> OutPoint {
+        let txid = self.blind(DOMAIN_UTXO, &serialize(outpoint));
+        OutPoint::new(Txid::from_inner(txid), 0)
+    }
+
+    fn decrypt_utxo(&self, utxo: &LocalUtxo) -> Result<LocalUtxo, Error> {
+        let plaintext = self.decrypt(DOMAIN_UTXO, utxo.txout.script_pubkey.as_bytes())?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    /// Build the placeholder transaction that carries the data of `txid`, without the encrypted
+    /// payload. Since the txid doesn't commit to the witness, it can be used as a lookup key.
+    fn placeholder_tx(&self, domain: &[u8], vout: u32, txid: &Txid) -> Transaction {
+        let blinded = self.blind(domain, txid.as_ref());
+
+        Transaction {
+            version: 1,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(Txid::from_inner(blinded), vout),
+                script_sig: Script::new(),
+                sequence: 0xFFFFFFFF,
+                witness: vec![],
+            }],
+            output: vec![],
+        }
+    }
+
+    fn placeholder_txid(&self, domain: &[u8], vout: u32, txid: &Txid) -> Txid {
+        self.placeholder_tx(domain, vout, txid).txid()
+    }
+
+    fn encrypt_tx(
+        &self,
+        domain: &[u8],
+        vout: u32,
+        txid: &Txid,
+        data: &[u8],
+    ) -> Result<Transaction, Error> {
+        let mut tx = self.placeholder_tx(domain, vout, txid);
+        tx.input[0].witness = vec![self.encrypt(domain, data)?];
+
+        Ok(tx)
+    }
+
+    fn decrypt_tx(&self, domain: &[u8], tx: &Transaction) -> Result<Vec<u8>, Error> {
+        let payload = tx
+            .input
+            .first()
+            .and_then(|input| input.witness.first())
+            .ok_or(Error::Encryption)?;
+
+        self.decrypt(domain, payload)
+    }
+
+    fn decrypt_raw_tx(&self, tx: &Transaction) -> Result<Transaction, Error> {
+        Ok(deserialize(&self.decrypt_tx(DOMAIN_RAW_TX, tx)?)?)
+    }
+
+    fn blind_label_reference(&self, label_type: LabelType, reference: &str) -> String {
+        let data = [label_type.as_str().as_bytes(), b":", reference.as_bytes()].concat();
+        self.blind(DOMAIN_LABEL, &data).to_hex()
+    }
+
+    fn encrypt_label(&self, label: &Label) -> Result<Label, Error> {
+        let payload = self.encrypt(DOMAIN_LABEL, &serde_json::to_vec(label)?)?;
+
+        Ok(Label::new(
+            label.label_type,
+            self.blind_label_reference(label.label_type, &label.reference),
+            payload.to_hex(),
+        ))
+    }
+
+    /// Mask a script status with a keystream derived from the script, since statuses are computed
+    /// from public data and could otherwise be used to identify the script. Masking is its own
+    /// inverse.
+    fn mask_script_status(&self, script: &Script, status: &sha256::Hash) -> sha256::Hash {
+        let mut masked = self.blind(DOMAIN_SCRIPT_STATUS, script.as_bytes());
+        for (m, s) in masked.iter_mut().zip(status.as_inner()) {
+            *m ^= s;
+        }
+
+        sha256::Hash::from_inner(masked)
+    }
+
+    fn decrypt_label(&self, label: &Label) -> Result<Label, Error> {
+        let payload = Vec::<u8>::from_hex(&label.label).map_err(|_| Error::Encryption)?;
+        Ok(serde_json::from_slice(
+            &self.decrypt(DOMAIN_LABEL, &payload)?,
+        )?)
+    }
+
+    fn decrypt_details(&self, details: &TransactionDetails) -> Result<TransactionDetails, Error> {
+        let tx = details.transaction.as_ref().ok_or(Error::Encryption)?;
+        Ok(serde_json::from_slice(&self.decrypt_tx(DOMAIN_TX, tx)?)?)
+    }
+}
+
+/// Database wrapper that encrypts the data before storing it in an inner database
+///
+/// See the [module documentation](crate::database::encrypted) for details on how the data is
+/// stored. Opening an existing database with the wrong key fails when the descriptor checksums
+/// are checked, or with [`Error::Encryption`] when reading encrypted data.
+#[derive(Debug)]
+pub struct EncryptedDatabase<D> {
+    inner: D,
+    keys: Keys,
+}
+
+impl<D> EncryptedDatabase<D> {
+    /// Wrap `inner`, using `key` to encrypt and decrypt the data
+    pub fn new(inner: D, key: [u8; 32]) -> Self {
+        EncryptedDatabase {
+            inner,
+            keys: Keys::new(key),
+        }
+    }
+
+    /// Return a reference to the inner database
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    /// Consume the wrapper and return the inner database
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+impl<D: BatchOperations> BatchOperations for EncryptedDatabase<D> {
+    fn set_script_pubkey(
+        &mut self,
+        script: &Script,
+        keychain: KeychainKind,
+        child: u32,
+    ) -> Result<(), Error> {
+        let script = self.keys.encrypt_script(script)?;
+        self.inner.set_script_pubkey(&script, keychain, child)
+    }
+
+    fn set_utxo(&mut self, utxo: &LocalUtxo) -> Result<(), Error> {
+        let payload = self.keys.encrypt(DOMAIN_UTXO, &serde_json::to_vec(utxo)?)?;
+        self.inner.set_utxo(&LocalUtxo {
+            outpoint: self.keys.blind_outpoint(&utxo.outpoint),
+            txout: TxOut {
+                value: 0,
+                script_pubkey: payload.into(),
+            },
+            keychain: KeychainKind::External,
+            is_coinbase: false,
+        })
+    }
+
+    fn set_raw_tx(&mut self, transaction: &Transaction) -> Result<(), Error> {
+        let tx = self.keys.encrypt_tx(
+            DOMAIN_RAW_TX,
+            RAW_TX_VOUT,
+            &transaction.txid(),
+            &serialize(transaction),
+        )?;
+        self.inner.set_raw_tx(&tx)
+    }
+
+    fn set_tx(&mut self, transaction: &TransactionDetails) -> Result<(), Error> {
+        // insert the raw_tx if present
+        if let Some(ref tx) = transaction.transaction {
+            self.set_raw_tx(tx)?;
+        }
+
+        // remove the raw tx from the serialized version
+        let mut details = transaction.clone();
+        details.transaction = None;
+
+        let tx = self.keys.encrypt_tx(
+            DOMAIN_TX,
+            TX_VOUT,
+            &transaction.txid,
+            &serde_json::to_vec(&details)?,
+        )?;
+        self.inner.set_tx(&TransactionDetails {
+            txid: tx.txid(),
+            transaction: Some(tx),
+            ..Default::default()
+        })
+    }
+
+    fn set_last_index(&mut self, keychain: KeychainKind, value: u32) -> Result<(), Error> {
+        self.inner.set_last_index(keychain, value)
+    }
+
+    fn set_label(&mut self, label: &Label) -> Result<(), Error> {
+        let label = self.keys.encrypt_label(label)?;
+        self.inner.set_label(&label)
+    }
+
+    fn set_script_status(&mut self, script: &Script, status: &sha256::Hash) -> Result<(), Error> {
+        let status = self.keys.mask_script_status(script, status);
+        let script = self.keys.encrypt_script(script)?;
+        self.inner.set_script_status(&script, &status)
+    }
+
+    fn del_script_pubkey_from_path(
+        &mut self,
+        keychain: KeychainKind,
+        child: u32,
+    ) -> Result<Option<Script>, Error> {
+        self.inner
+            .del_script_pubkey_from_path(keychain, child)?
+            .map(|script| self.keys.decrypt_script(&script))
+            .transpose()
+    }
+
+    fn del_path_from_script_pubkey(
+        &mut self,
+        script: &Script,
+    ) -> Result<Option<(KeychainKind, u32)>, Error> {
+        let script = self.keys.encrypt_script(script)?;
+        self.inner.del_path_from_script_pubkey(&script)
+    }
+
+    fn del_utxo(&mut self, outpoint: &OutPoint) -> Result<Option<LocalUtxo>, Error> {
+        let outpoint = self.keys.blind_outpoint(outpoint);
+        self.inner
+            .del_utxo(&outpoint)?
+            .map(|utxo| self.keys.decrypt_utxo(&utxo))
+            .transpose()
+    }
+
+    fn del_raw_tx(&mut self, txid: &Txid) -> Result<Option<Transaction>, Error> {
+        let txid = self.keys.placeholder_txid(DOMAIN_RAW_TX, RAW_TX_VOUT, txid);
+        self.inner
+            .del_raw_tx(&txid)?
+            .map(|tx| self.keys.decrypt_raw_tx(&tx))
+            .transpose()
+    }
+
+    fn del_tx(
+        &mut self,
+        txid: &Txid,
+        include_raw: bool,
+    ) -> Result<Option<TransactionDetails>, Error> {
+        let raw_tx = if include_raw {
+            self.del_raw_tx(txid)?
+        } else {
+            None
+        };
+
+        // the placeholder tx is always removed, since it carries the data
+        let placeholder_txid = self.keys.placeholder_txid(DOMAIN_TX, TX_VOUT, txid);
+        self.inner
+            .del_tx(&placeholder_txid, true)?
+            .map(|details| {
+                let mut details = self.keys.decrypt_details(&details)?;
+                details.transaction = raw_tx;
+
+                Ok(details)
+            })
+            .transpose()
+    }
+
+    fn del_last_index(&mut self, keychain: KeychainKind) -> Result<Option<u32>, Error> {
+        self.inner.del_last_index(keychain)
+    }
+
+    fn del_label(
+        &mut self,
+        label_type: LabelType,
+        reference: &str,
+    ) -> Result<Option<Label>, Error> {
+        let reference = self.keys.blind_label_reference(label_type, reference);
+        self.inner
+            .del_label(label_type, &reference)?
+            .map(|label| self.keys.decrypt_label(&label))
+            .transpose()
+    }
+
+    fn del_script_status(&mut self, script: &Script) -> Result<Option<sha256::Hash>, Error> {
+        let encrypted_script = self.keys.encrypt_script(script)?;
+        Ok(self
+            .inner
+            .del_script_status(&encrypted_script)?
+            .map(|status| self.keys.mask_script_status(script, &status)))
+    }
+}
+
+impl<D: Database> Database for EncryptedDatabase<D> {
+    fn check_descriptor_checksum<B: AsRef<[u8]>>(
+        &mut self,
+        keychain: KeychainKind,
+        bytes: B,
+    ) -> Result<(), Error> {
+        let bytes = self.keys.blind(DOMAIN_CHECKSUM, bytes.as_ref());
+        self.inner.check_descriptor_checksum(keychain, bytes)
+    }
+
+    fn iter_script_pubkeys(&self, keychain: Option<KeychainKind>) -> Result<Vec<Script>, Error> {
+        self.inner
+            .iter_script_pubkeys(keychain)?
+            .iter()
+            .map(|script| self.keys.decrypt_script(script))
+            .collect()
+    }
+
+    fn iter_utxos(&self) -> Result<Vec<LocalUtxo>, Error> {
+        self.inner
+            .iter_utxos()?
+            .iter()
+            .map(|utxo| self.keys.decrypt_utxo(utxo))
+            .collect()
+    }
+
+    fn iter_raw_txs(&self) -> Result<Vec<Transaction>, Error> {
+        self.inner
+            .iter_raw_txs()?
+            .iter()
+            .filter(|tx| {
+                tx.input.first().map(|input| input.previous_output.vout) == Some(RAW_TX_VOUT)
+            })
+            .map(|tx| self.keys.decrypt_raw_tx(tx))
+            .collect()
+    }
+
+    fn iter_txs(&self, include_raw: bool) -> Result<Vec<TransactionDetails>, Error> {
+        self.inner
+            .iter_txs(true)?
+            .iter()
+            .map(|details| {
+                let mut details = self.keys.decrypt_details(details)?;
+                if include_raw {
+                    details.transaction = self.get_raw_tx(&details.txid)?;
+                }
+
+                Ok(details)
+            })
+            .collect()
+    }
+
+    fn iter_labels(&self) -> Result<Vec<Label>, Error> {
+        self.inner
+            .iter_labels()?
+            .iter()
+            .map(|label| self.keys.decrypt_label(label))
+            .collect()
+    }
+
+    fn get_script_pubkey_from_path(
+        &self,
+        keychain: KeychainKind,
+        child: u32,
+    ) -> Result<Option<Script>, Error> {
+        self.inner
+            .get_script_pubkey_from_path(keychain, child)?
+            .map(|script| self.keys.decrypt_script(&script))
+            .transpose()
+    }
+
+    fn get_path_from_script_pubkey(
+        &self,
+        script: &Script,
+    ) -> Result<Option<(KeychainKind, u32)>, Error> {
+        let script = self.keys.encrypt_script(script)?;
+        self.inner.get_path_from_script_pubkey(&script)
+    }
+
+    fn get_utxo(&self, outpoint: &OutPoint) -> Result<Option<LocalUtxo>, Error> {
+        let outpoint = self.keys.blind_outpoint(outpoint);
+        self.inner
+            .get_utxo(&outpoint)?
+            .map(|utxo| self.keys.decrypt_utxo(&utxo))
+            .transpose()
+    }
+
+    fn get_raw_tx(&self, txid: &Txid) -> Result<Option<Transaction>, Error> {
+        let txid = self.keys.placeholder_txid(DOMAIN_RAW_TX, RAW_TX_VOUT, txid);
+        self.inner
+            .get_raw_tx(&txid)?
+            .map(|tx| self.keys.decrypt_raw_tx(&tx))
+            .transpose()
+    }
+
+    fn get_tx(&self, txid: &Txid, include_raw: bool) -> Result<Option<TransactionDetails>, Error> {
+        let placeholder_txid = self.keys.placeholder_txid(DOMAIN_TX, TX_VOUT, txid);
+        self.inner
+            .get_tx(&placeholder_txid, true)?
+            .map(|details| {
+                let mut details = self.keys.decrypt_details(&details)?;
+                if include_raw {
+                    details.transaction = self.get_raw_tx(txid)?;
+                }
+
+                Ok(details)
+            })
+            .transpose()
+    }
+
+    fn get_last_index(&self, keychain: KeychainKind) -> Result<Option<u32>, Error> {
+        self.inner.get_last_index(keychain)
+    }
+
+    fn get_label(&self, label_type: LabelType, reference: &str) -> Result<Option<Label>, Error> {
+        let reference = self.keys.blind_label_reference(label_type, reference);
+        self.inner
+            .get_label(label_type, &reference)?
+            .map(|label| self.keys.decrypt_label(&label))
+            .transpose()
+    }
+
+    fn get_script_status(&self, script: &Script) -> Result<Option<sha256::Hash>, Error> {
+        let encrypted_script = self.keys.encrypt_script(script)?;
+        Ok(self
+            .inner
+            .get_script_status(&encrypted_script)?
+            .map(|status| self.keys.mask_script_status(script, &status)))
+    }
+
+    fn increment_last_index(&mut self, keychain: KeychainKind) -> Result<u32, Error> {
+        self.inner.increment_last_index(keychain)
+    }
+
+    fn get_format_version(&self) -> Result<Option<u32>, Error> {
+        self.inner.get_format_version()
+    }
+
+    fn migrate(&mut self) -> Result<(), Error> {
+        // the steps of the inner database only see blinded data, so the ones changing the
+        // content are applied again on the decrypted data
+        let found = self.inner.get_format_version()?;
+        self.inner.migrate()?;
+        if self.inner.get_format_version()? != found {
+            migration::migrate_content(self)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<D: BatchDatabase> BatchDatabase for EncryptedDatabase<D> {
+    type Batch = EncryptedDatabase<D::Batch>;
+
+    fn begin_batch(&self) -> Self::Batch {
+        EncryptedDatabase {
+            inner: self.inner.begin_batch(),
+            keys: self.keys.clone(),
+        }
+    }
+
+    fn commit_batch(&mut self, batch: Self::Batch) -> Result<(), Error> {
+        self.inner.commit_batch(batch.inner)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use bitcoin::hashes::hex::FromHex;
+    use bitcoin::{OutPoint, Script, Transaction, TxIn, TxOut};
+
+    use super::EncryptedDatabase;
+    use crate::database::{BatchOperations, Database, MemoryDatabase};
+    use crate::types::{KeychainKind, LocalUtxo};
+
+    fn get_tree() -> EncryptedDatabase<MemoryDatabase> {
+        EncryptedDatabase::new(MemoryDatabase::new(), [0x42; 32])
+    }
+
+    #[test]
+    fn test_script_pubkey() {
+        crate::database::test::test_script_pubkey(get_tree());
+    }
+
+    #[test]
+    fn test_batch_script_pubkey() {
+        crate::database::test::test_batch_script_pubkey(get_tree());
+    }
+
+    #[test]
+    fn test_iter_script_pubkey() {
+        crate::database::test::test_iter_script_pubkey(get_tree());
+    }
+
+    #[test]
+    fn test_del_script_pubkey() {
+        crate::database::test::test_del_script_pubkey(get_tree());
+    }
+
+    #[test]
+    fn test_utxo() {
+        crate::database::test::test_utxo(get_tree());
+    }
+
+    #[test]
+    fn test_raw_tx() {
+        crate::database::test::test_raw_tx(get_tree());
+    }
+
+    #[test]
+    fn test_tx() {
+        crate::database::test::test_tx(get_tree());
+    }
+
+    #[test]
+    fn test_last_index() {
+        crate::database::test::test_last_index(get_tree());
+    }
+
+    #[test]
+    fn test_label() {
+        crate::database::test::test_label(get_tree());
+    }
+
+    #[test]
+    fn test_script_status() {
+        crate::database::test::test_script_status(get_tree());
+    }
+
+    #[test]
+    fn test_format_version() {
+        crate::database::test::test_format_version(get_tree());
+    }
+
+    #[test]
+    fn test_inner_is_encrypted() {
+        let mut tree = get_tree();
+
+        let script = Script::from(
+            Vec::<u8>::from_hex("76a91402306a7c23f3e8010de41e9e591348bb83f11daa88ac").unwrap(),
+        );
+        let outpoint = OutPoint::from_str(
+            "5df6e0e2761359d30a8275058e299fcc0381534545f55cf43e41983f5d4c9456:0",
+        )
+        .unwrap();
+        tree.set_script_pubkey(&script, KeychainKind::External, 0)
+            .unwrap();
+        tree.set_utxo(&LocalUtxo {
+            txout: TxOut {
+                value: 133742,
+                script_pubkey: script.clone(),
+            },
+            outpoint,
+            keychain: KeychainKind::External,
+            is_coinbase: false,
+        })
+        .unwrap();
+
+        let inner = tree.inner();
+        assert_ne!(inner.iter_script_pubkeys(None).unwrap(), vec![script]);
+        let inner_utxo = &inner.iter_utxos().unwrap()[0];
+        assert_ne!(inner_utxo.outpoint, outpoint);
+        assert_eq!(inner_utxo.txout.value, 0);
+    }
+
+    #[test]
+    fn test_migrate_decrypted_content() {
+        let mut tree = get_tree();
+        let coinbase = Transaction {
+            version: 1,
+            lock_time: 0,
+            input: vec![TxIn::default()],
+            output: vec![TxOut::default()],
+        };
+        tree.set_raw_tx(&coinbase).unwrap();
+        let utxo = LocalUtxo {
+            outpoint: OutPoint::new(coinbase.txid(), 0),
+            txout: TxOut::default(),
+            keychain: KeychainKind::External,
+            is_coinbase: false,
+        };
+        tree.set_utxo(&utxo).unwrap();
+        assert_eq!(tree.get_format_version().unwrap(), None);
+
+        tree.migrate().unwrap();
+
+        assert!(tree.get_format_version().unwrap().is_some());
+        assert!(tree.get_utxo(&utxo.outpoint).unwrap().unwrap().is_coinbase);
+    }
+
+    #[test]
+    fn test_wrong_key() {
+        let mut tree = get_tree();
+        tree.check_descriptor_checksum(KeychainKind::External, b"checksum")
+            .unwrap();
+        tree.set_script_pubkey(&Script::new(), KeychainKind::External, 0)
+            .unwrap();
+
+        let mut tree = EncryptedDatabase::new(tree.into_inner(), [0x21; 32]);
+        assert!(matches!(
+            tree.check_descriptor_checksum(KeychainKind::External, b"checksum"),
+            Err(crate::Error::ChecksumMismatch)
+        ));
+        assert!(matches!(
+            tree.get_script_pubkey_from_path(KeychainKind::External, 0),
+            Err(crate::Error::Encryption)
+        ));
+    }
+}
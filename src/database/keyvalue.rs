@@ -15,9 +15,11 @@ use sled::{Batch, Tree};
 
 use bitcoin::consensus::encode::{deserialize, serialize};
 use bitcoin::hash_types::Txid;
+use bitcoin::hashes::{sha256, Hash};
 use bitcoin::{OutPoint, Script, Transaction};
 
 use crate::database::memory::MapKey;
+use crate::database::migration::{self, Migrate, Migration};
 use crate::database::{BatchDatabase, BatchOperations, Database};
 use crate::error::Error;
 use crate::types::*;
@@ -43,6 +45,7 @@ macro_rules! impl_batch_operations {
             let value = json!({
                 "t": utxo.txout,
                 "i": utxo.keychain,
+                "c": utxo.is_coinbase,
             });
             self.insert(key, serde_json::to_vec(&value)?)$($after_insert)*;
 
@@ -82,6 +85,20 @@ macro_rules! impl_batch_operations {
             Ok(())
         }
 
+        fn set_label(&mut self, label: &Label) -> Result<(), Error> {
+            let key = MapKey::Label(Some((label.label_type, &label.reference))).as_map_key();
+            self.insert(key, serde_json::to_vec(label)?)$($after_insert)*;
+
+            Ok(())
+        }
+
+        fn set_script_status(&mut self, script: &Script, status: &sha256::Hash) -> Result<(), Error> {
+            let key = MapKey::ScriptStatus(Some(script)).as_map_key();
+            self.insert(key, status.as_inner())$($after_insert)*;
+
+            Ok(())
+        }
+
         fn del_script_pubkey_from_path(&mut self, keychain: KeychainKind, path: u32) -> Result<Option<Script>, Error> {
             let key = MapKey::Path((Some(keychain), Some(path))).as_map_key();
             let res = self.remove(key);
@@ -118,8 +135,9 @@ macro_rules! impl_batch_operations {
                     let mut val: serde_json::Value = serde_json::from_slice(&b)?;
                     let txout = serde_json::from_value(val["t"].take())?;
                     let keychain = serde_json::from_value(val["i"].take())?;
+                    let is_coinbase = val["c"].as_bool().unwrap_or(false);
 
-                    Ok(Some(LocalUtxo { outpoint: outpoint.clone(), txout, keychain }))
+                    Ok(Some(LocalUtxo { outpoint: outpoint.clone(), txout, keychain, is_coinbase }))
                 }
             }
         }
@@ -168,6 +186,22 @@ macro_rules! impl_batch_operations {
                 }
             }
         }
+
+        fn del_label(&mut self, label_type: LabelType, reference: &str) -> Result<Option<Label>, Error> {
+            let key = MapKey::Label(Some((label_type, reference))).as_map_key();
+            let res = self.remove(key);
+            let res = $process_delete!(res);
+
+            Ok(res.map_or(Ok(None), |x| Some(serde_json::from_slice(&x)).transpose())?)
+        }
+
+        fn del_script_status(&mut self, script: &Script) -> Result<Option<sha256::Hash>, Error> {
+            let key = MapKey::ScriptStatus(Some(script)).as_map_key();
+            let res = self.remove(key);
+            let res = $process_delete!(res);
+
+            res.map(|x| sha256::Hash::from_slice(&x).map_err(|e| Error::Generic(e.to_string()))).transpose()
+        }
     }
 }
 
@@ -231,11 +265,13 @@ impl Database for Tree {
                 let mut val: serde_json::Value = serde_json::from_slice(&v)?;
                 let txout = serde_json::from_value(val["t"].take())?;
                 let keychain = serde_json::from_value(val["i"].take())?;
+                let is_coinbase = val["c"].as_bool().unwrap_or(false);
 
                 Ok(LocalUtxo {
                     outpoint,
                     txout,
                     keychain,
+                    is_coinbase,
                 })
             })
             .collect()
@@ -267,6 +303,16 @@ impl Database for Tree {
             .collect()
     }
 
+    fn iter_labels(&self) -> Result<Vec<Label>, Error> {
+        let key = MapKey::Label(None).as_map_key();
+        self.scan_prefix(key)
+            .map(|x| -> Result<_, Error> {
+                let (_, v) = x?;
+                Ok(serde_json::from_slice(&v)?)
+            })
+            .collect()
+    }
+
     fn get_script_pubkey_from_path(
         &self,
         keychain: KeychainKind,
@@ -299,11 +345,13 @@ impl Database for Tree {
                 let mut val: serde_json::Value = serde_json::from_slice(&b)?;
                 let txout = serde_json::from_value(val["t"].take())?;
                 let keychain = serde_json::from_value(val["i"].take())?;
+                let is_coinbase = val["c"].as_bool().unwrap_or(false);
 
                 Ok(LocalUtxo {
                     outpoint: *outpoint,
                     txout,
                     keychain,
+                    is_coinbase,
                 })
             })
             .transpose()
@@ -342,6 +390,21 @@ impl Database for Tree {
             .transpose()
     }
 
+    fn get_label(&self, label_type: LabelType, reference: &str) -> Result<Option<Label>, Error> {
+        let key = MapKey::Label(Some((label_type, reference))).as_map_key();
+        Ok(self
+            .get(key)?
+            .map(|b| serde_json::from_slice(&b))
+            .transpose()?)
+    }
+
+    fn get_script_status(&self, script: &Script) -> Result<Option<sha256::Hash>, Error> {
+        let key = MapKey::ScriptStatus(Some(script)).as_map_key();
+        self.get(key)?
+            .map(|b| sha256::Hash::from_slice(&b).map_err(|e| Error::Generic(e.to_string())))
+            .transpose()
+    }
+
     // inserts 0 if not present
     fn increment_last_index(&mut self, keychain: KeychainKind) -> Result<u32, Error> {
         let key = MapKey::LastIndex(keychain).as_map_key();
@@ -367,6 +430,40 @@ impl Database for Tree {
             Ok(val)
         })
     }
+
+    fn get_format_version(&self) -> Result<Option<u32>, Error> {
+        let key = MapKey::FormatVersion.as_map_key();
+        self.get(key)?
+            .map(|b| -> Result<_, Error> {
+                let array: [u8; 4] = b
+                    .as_ref()
+                    .try_into()
+                    .map_err(|_| Error::InvalidU32Bytes(b.to_vec()))?;
+                let val = u32::from_be_bytes(array);
+                Ok(val)
+            })
+            .transpose()
+    }
+
+    fn migrate(&mut self) -> Result<(), Error> {
+        migration::migrate(self)
+    }
+}
+
+impl Migrate for Tree {
+    const MIGRATIONS: &'static [Migration<Self>] = &[
+        // v1: introduce the format version record, the content is unchanged
+        |_| Ok(()),
+        // v2: flag the utxos created by coinbase transactions
+        migration::flag_coinbase_utxos,
+    ];
+
+    fn set_format_version(&mut self, version: u32) -> Result<(), Error> {
+        let key = MapKey::FormatVersion.as_map_key();
+        self.insert(key, &version.to_be_bytes())?;
+
+        Ok(())
+    }
 }
 
 impl BatchDatabase for Tree {
@@ -386,6 +483,7 @@ mod test {
     use std::sync::{Arc, Condvar, Mutex, Once};
     use std::time::{SystemTime, UNIX_EPOCH};
 
+    use lazy_static::lazy_static;
     use sled::{Db, Tree};
 
     static mut COUNT: usize = 0;
@@ -465,4 +563,19 @@ mod test {
     fn test_last_index() {
         crate::database::test::test_last_index(get_tree());
     }
+
+    #[test]
+    fn test_label() {
+        crate::database::test::test_label(get_tree());
+    }
+
+    #[test]
+    fn test_script_status() {
+        crate::database::test::test_script_status(get_tree());
+    }
+
+    #[test]
+    fn test_format_version() {
+        crate::database::test::test_format_version(get_tree());
+    }
 }
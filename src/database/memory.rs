@@ -19,8 +19,10 @@ use std::ops::Bound::{Excluded, Included};
 
 use bitcoin::consensus::encode::{deserialize, serialize};
 use bitcoin::hash_types::Txid;
+use bitcoin::hashes::sha256;
 use bitcoin::{OutPoint, Script, Transaction};
 
+use crate::database::migration::{self, Migrate, Migration};
 use crate::database::{BatchDatabase, BatchOperations, ConfigurableDatabase, Database};
 use crate::error::Error;
 use crate::types::*;
@@ -32,6 +34,9 @@ use crate::types::*;
 // transactions         t<txid> -> tx details
 // deriv indexes        c{i,e} -> u32
 // descriptor checksum  d{i,e} -> vec<u8>
+// format version       v -> u32
+// labels               l<type>:<ref> -> label
+// script status        h<script> -> sha256
 
 pub(crate) enum MapKey<'a> {
     Path((Option<KeychainKind>, Option<u32>)),
@@ -41,6 +46,9 @@ pub(crate) enum MapKey<'a> {
     Transaction(Option<&'a Txid>),
     LastIndex(KeychainKind),
     DescriptorChecksum(KeychainKind),
+    FormatVersion,
+    Label(Option<(LabelType, &'a str)>),
+    ScriptStatus(Option<&'a Script>),
 }
 
 impl MapKey<'_> {
@@ -59,6 +67,9 @@ impl MapKey<'_> {
             MapKey::Transaction(_) => b"t".to_vec(),
             MapKey::LastIndex(st) => [b"c", st.as_ref()].concat(),
             MapKey::DescriptorChecksum(st) => [b"d", st.as_ref()].concat(),
+            MapKey::FormatVersion => b"v".to_vec(),
+            MapKey::Label(_) => b"l".to_vec(),
+            MapKey::ScriptStatus(_) => b"h".to_vec(),
         }
     }
 
@@ -66,9 +77,13 @@ impl MapKey<'_> {
         match self {
             MapKey::Path((_, Some(child))) => child.to_be_bytes().to_vec(),
             MapKey::Script(Some(s)) => serialize(*s),
+            MapKey::ScriptStatus(Some(s)) => serialize(*s),
             MapKey::Utxo(Some(s)) => serialize(*s),
             MapKey::RawTx(Some(s)) => serialize(*s),
             MapKey::Transaction(Some(s)) => serialize(*s),
+            MapKey::Label(Some((label_type, reference))) => {
+                [label_type.as_str().as_bytes(), b":", reference.as_bytes()].concat()
+            }
             _ => vec![],
         }
     }
@@ -146,8 +161,10 @@ impl BatchOperations for MemoryDatabase {
 
     fn set_utxo(&mut self, utxo: &LocalUtxo) -> Result<(), Error> {
         let key = MapKey::Utxo(Some(&utxo.outpoint)).as_map_key();
-        self.map
-            .insert(key, Box::new((utxo.txout.clone(), utxo.keychain)));
+        self.map.insert(
+            key,
+            Box::new((utxo.txout.clone(), utxo.keychain, utxo.is_coinbase)),
+        );
 
         Ok(())
     }
@@ -179,6 +196,18 @@ impl BatchOperations for MemoryDatabase {
 
         Ok(())
     }
+    fn set_label(&mut self, label: &Label) -> Result<(), Error> {
+        let key = MapKey::Label(Some((label.label_type, &label.reference))).as_map_key();
+        self.map.insert(key, Box::new(label.clone()));
+
+        Ok(())
+    }
+    fn set_script_status(&mut self, script: &Script, status: &sha256::Hash) -> Result<(), Error> {
+        let key = MapKey::ScriptStatus(Some(script)).as_map_key();
+        self.map.insert(key, Box::new(*status));
+
+        Ok(())
+    }
 
     fn del_script_pubkey_from_path(
         &mut self,
@@ -218,11 +247,12 @@ impl BatchOperations for MemoryDatabase {
         match res {
             None => Ok(None),
             Some(b) => {
-                let (txout, keychain) = b.downcast_ref().cloned().unwrap();
+                let (txout, keychain, is_coinbase) = b.downcast_ref().cloned().unwrap();
                 Ok(Some(LocalUtxo {
                     outpoint: *outpoint,
                     txout,
                     keychain,
+                    is_coinbase,
                 }))
             }
         }
@@ -269,6 +299,24 @@ impl BatchOperations for MemoryDatabase {
             Some(b) => Ok(Some(*b.downcast_ref().unwrap())),
         }
     }
+    fn del_label(
+        &mut self,
+        label_type: LabelType,
+        reference: &str,
+    ) -> Result<Option<Label>, Error> {
+        let key = MapKey::Label(Some((label_type, reference))).as_map_key();
+        let res = self.map.remove(&key);
+        self.deleted_keys.push(key);
+
+        Ok(res.map(|x| x.downcast_ref().cloned().unwrap()))
+    }
+    fn del_script_status(&mut self, script: &Script) -> Result<Option<sha256::Hash>, Error> {
+        let key = MapKey::ScriptStatus(Some(script)).as_map_key();
+        let res = self.map.remove(&key);
+        self.deleted_keys.push(key);
+
+        Ok(res.map(|x| *x.downcast_ref().unwrap()))
+    }
 }
 
 impl Database for MemoryDatabase {
@@ -309,11 +357,12 @@ impl Database for MemoryDatabase {
             .range::<Vec<u8>, _>((Included(&key), Excluded(&after(&key))))
             .map(|(k, v)| {
                 let outpoint = deserialize(&k[1..]).unwrap();
-                let (txout, keychain) = v.downcast_ref().cloned().unwrap();
+                let (txout, keychain, is_coinbase) = v.downcast_ref().cloned().unwrap();
                 Ok(LocalUtxo {
                     outpoint,
                     txout,
                     keychain,
+                    is_coinbase,
                 })
             })
             .collect()
@@ -343,6 +392,14 @@ impl Database for MemoryDatabase {
             .collect()
     }
 
+    fn iter_labels(&self) -> Result<Vec<Label>, Error> {
+        let key = MapKey::Label(None).as_map_key();
+        self.map
+            .range::<Vec<u8>, _>((Included(&key), Excluded(&after(&key))))
+            .map(|(_, v)| Ok(v.downcast_ref().cloned().unwrap()))
+            .collect()
+    }
+
     fn get_script_pubkey_from_path(
         &self,
         keychain: KeychainKind,
@@ -372,11 +429,12 @@ impl Database for MemoryDatabase {
     fn get_utxo(&self, outpoint: &OutPoint) -> Result<Option<LocalUtxo>, Error> {
         let key = MapKey::Utxo(Some(outpoint)).as_map_key();
         Ok(self.map.get(&key).map(|b| {
-            let (txout, keychain) = b.downcast_ref().cloned().unwrap();
+            let (txout, keychain, is_coinbase) = b.downcast_ref().cloned().unwrap();
             LocalUtxo {
                 outpoint: *outpoint,
                 txout,
                 keychain,
+                is_coinbase,
             }
         }))
     }
@@ -406,6 +464,19 @@ impl Database for MemoryDatabase {
         Ok(self.map.get(&key).map(|b| *b.downcast_ref().unwrap()))
     }
 
+    fn get_label(&self, label_type: LabelType, reference: &str) -> Result<Option<Label>, Error> {
+        let key = MapKey::Label(Some((label_type, reference))).as_map_key();
+        Ok(self
+            .map
+            .get(&key)
+            .map(|b| b.downcast_ref().cloned().unwrap()))
+    }
+
+    fn get_script_status(&self, script: &Script) -> Result<Option<sha256::Hash>, Error> {
+        let key = MapKey::ScriptStatus(Some(script)).as_map_key();
+        Ok(self.map.get(&key).map(|b| *b.downcast_ref().unwrap()))
+    }
+
     // inserts 0 if not present
     fn increment_last_index(&mut self, keychain: KeychainKind) -> Result<u32, Error> {
         let key = MapKey::LastIndex(keychain).as_map_key();
@@ -419,6 +490,31 @@ impl Database for MemoryDatabase {
 
         Ok(*value)
     }
+
+    fn get_format_version(&self) -> Result<Option<u32>, Error> {
+        let key = MapKey::FormatVersion.as_map_key();
+        Ok(self.map.get(&key).map(|b| *b.downcast_ref().unwrap()))
+    }
+
+    fn migrate(&mut self) -> Result<(), Error> {
+        migration::migrate(self)
+    }
+}
+
+impl Migrate for MemoryDatabase {
+    const MIGRATIONS: &'static [Migration<Self>] = &[
+        // v1: introduce the format version record, the content is unchanged
+        |_| Ok(()),
+        // v2: flag the utxos created by coinbase transactions
+        migration::flag_coinbase_utxos,
+    ];
+
+    fn set_format_version(&mut self, version: u32) -> Result<(), Error> {
+        let key = MapKey::FormatVersion.as_map_key();
+        self.map.insert(key, Box::new(version));
+
+        Ok(())
+    }
 }
 
 impl BatchDatabase for MemoryDatabase {
@@ -476,6 +572,7 @@ macro_rules! populate_test_db {
         let confirmation_time = tx_meta.min_confirmations.map(|conf| ConfirmationTime {
             height: current_height.unwrap().checked_sub(conf as u32).unwrap(),
             timestamp: 0,
+            block_hash: None,
         });
 
         let tx_details = TransactionDetails {
@@ -486,6 +583,7 @@ macro_rules! populate_test_db {
             sent: 0,
             confirmation_time,
             verified: current_height.is_some(),
+            ancestors_fee: None,
         };
 
         db.set_tx(&tx_details).unwrap();
@@ -497,6 +595,7 @@ macro_rules! populate_test_db {
                     vout: vout as u32,
                 },
                 keychain: KeychainKind::External,
+                is_coinbase: false,
             })
             .unwrap();
         }
@@ -582,4 +681,19 @@ mod test {
     fn test_last_index() {
         crate::database::test::test_last_index(get_tree());
     }
+
+    #[test]
+    fn test_label() {
+        crate::database::test::test_label(get_tree());
+    }
+
+    #[test]
+    fn test_script_status() {
+        crate::database::test::test_script_status(get_tree());
+    }
+
+    #[test]
+    fn test_format_version() {
+        crate::database::test::test_format_version(get_tree());
+    }
 }
@@ -0,0 +1,156 @@
+// Bitcoin Dev Kit
+// Written in 2021 by the Bitcoin Dev Kit Developers
+//
+// Copyright (c) 2020-2021 Bitcoin Dev Kit Developers
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! Database format migrations
+//!
+//! Every database defined in this library records the version of the format it uses to store
+//! its data. Each database type has its own ordered list of migration steps: the step at index
+//! `i` upgrades the data from version `i` to version `i + 1`, so the latest version supported is
+//! the number of steps in the list. Databases created before versioning was introduced don't
+//! have a version record and are treated as version `0`.
+//!
+//! When a database is opened by a [`Wallet`](crate::wallet::Wallet) the missing steps are applied
+//! in order, recording the new version after each of them. A database with a version greater than
+//! the latest supported fails with [`Error::DatabaseVersionTooNew`].
+//!
+//! Databases that wrap another one and transform its content, like the
+//! [`EncryptedDatabase`](crate::database::EncryptedDatabase), leave the version record to the
+//! inner database but apply the steps that change the content again, on the data they expose.
+
+#[allow(unused_imports)]
+use log::{debug, info};
+
+use crate::database::{BatchOperations, Database};
+use crate::error::Error;
+use crate::types::LocalUtxo;
+
+/// A single migration step, that upgrades the data stored in a database by one version
+pub(crate) type Migration<D> = fn(&mut D) -> Result<(), Error>;
+
+/// Trait implemented by the databases that support migrations
+pub(crate) trait Migrate: Database + Sized + 'static {
+    /// Ordered list of migration steps
+    const MIGRATIONS: &'static [Migration<Self>];
+
+    /// Record the version of the format used by the database
+    fn set_format_version(&mut self, version: u32) -> Result<(), Error>;
+}
+
+/// Apply the migration steps required to bring `db` to the latest version of its format
+pub(crate) fn migrate<D: Migrate>(db: &mut D) -> Result<(), Error> {
+    let supported = D::MIGRATIONS.len() as u32;
+    let found = db.get_format_version()?.unwrap_or(0);
+    if found > supported {
+        return Err(Error::DatabaseVersionTooNew { found, supported });
+    }
+
+    for (step, migration) in D::MIGRATIONS.iter().enumerate().skip(found as usize) {
+        let version = step as u32 + 1;
+        debug!("migrating database to version {}", version);
+
+        migration(db)?;
+        db.set_format_version(version)?;
+    }
+    if found < supported {
+        info!("database migrated from version {} to {}", found, supported);
+    }
+
+    Ok(())
+}
+
+/// Apply the steps that change the content of the data, in order
+///
+/// Used by the databases that wrap another one, whose own steps only see the transformed data.
+/// Every step must be idempotent, since they are all applied again whenever the inner database
+/// is migrated.
+#[cfg(feature = "encrypted-db")]
+pub(crate) fn migrate_content<D: BatchOperations + Database>(db: &mut D) -> Result<(), Error> {
+    flag_coinbase_utxos(db)
+}
+
+/// Mark the utxos created by coinbase transactions, which were not flagged before
+///
+/// Utxos whose transaction is not stored in the database are left untouched.
+pub(crate) fn flag_coinbase_utxos<D: BatchOperations + Database>(db: &mut D) -> Result<(), Error> {
+    for utxo in db.iter_utxos()? {
+        let is_coinbase = db
+            .get_raw_tx(&utxo.outpoint.txid)?
+            .map(|tx| tx.is_coin_base())
+            .unwrap_or(false);
+        if is_coinbase && !utxo.is_coinbase {
+            db.set_utxo(&LocalUtxo {
+                is_coinbase,
+                ..utxo
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::{OutPoint, Transaction, TxIn, TxOut};
+
+    use crate::database::MemoryDatabase;
+    use crate::types::KeychainKind;
+
+    #[test]
+    fn test_migrate_unversioned() {
+        let mut db = MemoryDatabase::new();
+        db.set_last_index(KeychainKind::External, 42).unwrap();
+        assert_eq!(db.get_format_version().unwrap(), None);
+
+        migrate(&mut db).unwrap();
+
+        assert_eq!(
+            db.get_format_version().unwrap(),
+            Some(MemoryDatabase::MIGRATIONS.len() as u32)
+        );
+        assert_eq!(db.get_last_index(KeychainKind::External).unwrap(), Some(42));
+    }
+
+    #[test]
+    fn test_migrate_too_new() {
+        let mut db = MemoryDatabase::new();
+        let supported = MemoryDatabase::MIGRATIONS.len() as u32;
+        db.set_format_version(supported + 1).unwrap();
+
+        assert!(matches!(
+            migrate(&mut db),
+            Err(Error::DatabaseVersionTooNew { found, supported: s }) if found == supported + 1 && s == supported
+        ));
+    }
+
+    #[test]
+    fn test_flag_coinbase_utxos() {
+        let mut db = MemoryDatabase::new();
+        let coinbase = Transaction {
+            version: 1,
+            lock_time: 0,
+            input: vec![TxIn::default()],
+            output: vec![TxOut::default()],
+        };
+        db.set_raw_tx(&coinbase).unwrap();
+        let utxo = LocalUtxo {
+            outpoint: OutPoint::new(coinbase.txid(), 0),
+            txout: TxOut::default(),
+            keychain: KeychainKind::External,
+            is_coinbase: false,
+        };
+        db.set_utxo(&utxo).unwrap();
+
+        flag_coinbase_utxos(&mut db).unwrap();
+
+        assert!(db.get_utxo(&utxo.outpoint).unwrap().unwrap().is_coinbase);
+    }
+}
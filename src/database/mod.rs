@@ -22,9 +22,16 @@
 //! database written in Rust. If the `key-value-db` feature is enabled (which by default is),
 //! this library automatically implements all the required traits for [`sled::Tree`].
 //!
+//! If the `sqlite` feature is enabled, [`SqliteDatabase`] stores the same data in a single SQLite
+//! file, which can also be queried with any external SQLite client.
+//!
+//! If the `encrypted-db` feature is enabled, any database can be wrapped in an
+//! [`EncryptedDatabase`] to encrypt the data it stores.
+//!
 //! [`Wallet`]: crate::wallet::Wallet
 
 use bitcoin::hash_types::Txid;
+use bitcoin::hashes::sha256;
 use bitcoin::{OutPoint, Script, Transaction, TxOut};
 
 use crate::error::Error;
@@ -36,9 +43,21 @@ pub use any::{AnyDatabase, AnyDatabaseConfig};
 #[cfg(feature = "key-value-db")]
 pub(crate) mod keyvalue;
 
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+#[cfg(feature = "sqlite")]
+pub use sqlite::{SqliteBatch, SqliteDatabase};
+
+#[cfg(feature = "encrypted-db")]
+pub mod encrypted;
+#[cfg(feature = "encrypted-db")]
+pub use encrypted::EncryptedDatabase;
+
 pub mod memory;
 pub use memory::MemoryDatabase;
 
+pub(crate) mod migration;
+
 /// Trait for operations that can be batched
 ///
 /// This trait defines the list of operations that must be implemented on the [`Database`] type and
@@ -59,6 +78,21 @@ pub trait BatchOperations {
     fn set_tx(&mut self, transaction: &TransactionDetails) -> Result<(), Error>;
     /// Store the last derivation index for a given keychain.
     fn set_last_index(&mut self, keychain: KeychainKind, value: u32) -> Result<(), Error>;
+    /// Store a [`Label`], replacing any previous label for the same object
+    ///
+    /// The default implementation returns an error, since the label would otherwise be lost.
+    fn set_label(&mut self, _label: &Label) -> Result<(), Error> {
+        Err(Error::Generic(
+            "Labels are not supported by this database".into(),
+        ))
+    }
+    /// Store the status of a script_pubkey's history, as last seen by the blockchain backend
+    ///
+    /// The default implementation doesn't store anything, which makes the Electrum-like
+    /// backends download the history of every script at each sync.
+    fn set_script_status(&mut self, _script: &Script, _status: &sha256::Hash) -> Result<(), Error> {
+        Ok(())
+    }
 
     /// Delete a script_pubkey given the keychain and its child number.
     fn del_script_pubkey_from_path(
@@ -84,6 +118,18 @@ pub trait BatchOperations {
     ) -> Result<Option<TransactionDetails>, Error>;
     /// Delete the last derivation index for a keychain.
     fn del_last_index(&mut self, keychain: KeychainKind) -> Result<Option<u32>, Error>;
+    /// Delete the [`Label`] of an object given its type and reference
+    fn del_label(
+        &mut self,
+        _label_type: LabelType,
+        _reference: &str,
+    ) -> Result<Option<Label>, Error> {
+        Ok(None)
+    }
+    /// Delete the status of a script_pubkey's history
+    fn del_script_status(&mut self, _script: &Script) -> Result<Option<sha256::Hash>, Error> {
+        Ok(None)
+    }
 }
 
 /// Trait for reading data from a database
@@ -109,6 +155,10 @@ pub trait Database: BatchOperations {
     fn iter_raw_txs(&self) -> Result<Vec<Transaction>, Error>;
     /// Return the list of transactions metadata
     fn iter_txs(&self, include_raw: bool) -> Result<Vec<TransactionDetails>, Error>;
+    /// Return the list of [`Label`]s
+    fn iter_labels(&self) -> Result<Vec<Label>, Error> {
+        Ok(vec![])
+    }
 
     /// Fetch a script_pubkey given the child number of a keychain.
     fn get_script_pubkey_from_path(
@@ -129,11 +179,37 @@ pub trait Database: BatchOperations {
     fn get_tx(&self, txid: &Txid, include_raw: bool) -> Result<Option<TransactionDetails>, Error>;
     /// Return the last defivation index for a keychain.
     fn get_last_index(&self, keychain: KeychainKind) -> Result<Option<u32>, Error>;
+    /// Fetch the [`Label`] of an object given its type and reference
+    fn get_label(&self, _label_type: LabelType, _reference: &str) -> Result<Option<Label>, Error> {
+        Ok(None)
+    }
+    /// Fetch the status of a script_pubkey's history, as last seen by the blockchain backend
+    fn get_script_status(&self, _script: &Script) -> Result<Option<sha256::Hash>, Error> {
+        Ok(None)
+    }
 
     /// Increment the last derivation index for a keychain and return it
     ///
     /// It should insert and return `0` if not present in the database
     fn increment_last_index(&mut self, keychain: KeychainKind) -> Result<u32, Error>;
+
+    /// Return the version of the format used to store the data, `None` if it was never recorded
+    ///
+    /// The default implementation always returns `None`.
+    fn get_format_version(&self) -> Result<Option<u32>, Error> {
+        Ok(None)
+    }
+    /// Upgrade the data stored to the latest format version supported by the library
+    ///
+    /// Migration steps are applied in order starting from the version currently recorded, or
+    /// from `0` if no version is recorded. Should return
+    /// [`Error::DatabaseVersionTooNew`](crate::error::Error::DatabaseVersionTooNew) if the
+    /// database was written using a more recent version of the format.
+    ///
+    /// The default implementation doesn't do anything.
+    fn migrate(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 /// Trait for a database that supports batch operations
@@ -195,6 +271,7 @@ pub mod test {
 
     use bitcoin::consensus::encode::deserialize;
     use bitcoin::hashes::hex::*;
+    use bitcoin::hashes::Hash;
     use bitcoin::*;
 
     use super::*;
@@ -289,6 +366,7 @@ pub mod test {
             txout,
             outpoint,
             keychain: KeychainKind::External,
+            is_coinbase: true,
         };
 
         tree.set_utxo(&utxo).unwrap();
@@ -320,8 +398,15 @@ pub mod test {
             confirmation_time: Some(ConfirmationTime {
                 timestamp: 123456,
                 height: 1000,
+                block_hash: Some(
+                    BlockHash::from_hex(
+                        "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f",
+                    )
+                    .unwrap(),
+                ),
             }),
             verified: true,
+            ancestors_fee: None,
         };
 
         tree.set_tx(&tx_details).unwrap();
@@ -369,5 +454,61 @@ pub mod test {
         );
     }
 
+    pub fn test_label<D: Database>(mut tree: D) {
+        let txid =
+            Txid::from_hex("5df6e0e2761359d30a8275058e299fcc0381534545f55cf43e41983f5d4c9456")
+                .unwrap();
+        let label = Label::tx(&txid, "Payment to Bob");
+
+        tree.set_label(&label).unwrap();
+        assert_eq!(
+            tree.get_label(LabelType::Tx, &txid.to_string()).unwrap(),
+            Some(label.clone())
+        );
+        assert_eq!(
+            tree.get_label(LabelType::Output, &txid.to_string())
+                .unwrap(),
+            None
+        );
+
+        // setting a label again replaces the previous one
+        let label = Label::tx(&txid, "Payment to Carol");
+        tree.set_label(&label).unwrap();
+        assert_eq!(tree.iter_labels().unwrap(), vec![label.clone()]);
+
+        assert_eq!(
+            tree.del_label(LabelType::Tx, &txid.to_string()).unwrap(),
+            Some(label)
+        );
+        assert_eq!(tree.iter_labels().unwrap(), vec![]);
+    }
+
+    pub fn test_script_status<D: Database>(mut tree: D) {
+        let script = Script::from(
+            Vec::<u8>::from_hex("76a91402306a7c23f3e8010de41e9e591348bb83f11daa88ac").unwrap(),
+        );
+        let status = sha256::Hash::hash(
+            b"5df6e0e2761359d30a8275058e299fcc0381534545f55cf43e41983f5d4c9456:1000:",
+        );
+
+        assert_eq!(tree.get_script_status(&script).unwrap(), None);
+
+        tree.set_script_status(&script, &status).unwrap();
+        assert_eq!(tree.get_script_status(&script).unwrap(), Some(status));
+
+        assert_eq!(tree.del_script_status(&script).unwrap(), Some(status));
+        assert_eq!(tree.get_script_status(&script).unwrap(), None);
+    }
+
+    pub fn test_format_version<D: Database>(mut tree: D) {
+        tree.migrate().unwrap();
+        let version = tree.get_format_version().unwrap();
+        assert!(version.is_some());
+
+        // migrating an up-to-date database doesn't change it
+        tree.migrate().unwrap();
+        assert_eq!(tree.get_format_version().unwrap(), version);
+    }
+
     // TODO: more tests...
 }
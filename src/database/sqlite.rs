@@ -0,0 +1,966 @@
+// Bitcoin Dev Kit
+// Written in 2021 by the Bitcoin Dev Kit Developers
+//
+// Copyright (c) 2020-2021 Bitcoin Dev Kit Developers
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! SQLite database
+//!
+//! This module defines [`SqliteDatabase`], a [`Database`] that stores its content in a single
+//! SQLite file. Unlike the [`sled`] database, the content can be inspected with any SQLite client,
+//! since every type of data is kept in its own table:
+//!
+//! | table                     | content                                                  |
+//! |---------------------------|----------------------------------------------------------|
+//! | `script_pubkeys`          | `keychain`, `child`, `script`                            |
+//! | `utxos`                   | `value`, `keychain`, `vout`, `txid`, `script`, `is_coinbase` |
+//! | `transactions`            | `txid`, `raw_tx`                                         |
+//! | `transaction_details`     | `txid`, `timestamp`, `received`, `sent`, `fee`, `height`, `verified`, `block_hash` |
+//! | `last_derivation_indices` | `keychain`, `value`                                      |
+//! | `checksums`               | `keychain`, `checksum`                                   |
+//! | `labels`                  | `type`, `ref`, `label`, `origin`, `spendable`            |
+//! | `script_statuses`         | `script`, `status`                                       |
+//!
+//! Keychains are stored as the strings `"External"` and `"Internal"`, txids and scripts as raw
+//! bytes.
+
+use std::fmt;
+
+use bitcoin::consensus::encode::{deserialize, serialize};
+use bitcoin::hash_types::{BlockHash, Txid};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::{OutPoint, Script, Transaction, TxOut};
+
+use rusqlite::{named_params, Connection, OptionalExtension, Row};
+
+use crate::database::migration::{self, Migrate, Migration};
+use crate::database::{BatchDatabase, BatchOperations, ConfigurableDatabase, Database};
+use crate::error::Error;
+use crate::types::*;
+
+/// Statements that create the tables of version 1 of the schema
+static SCHEMA_V1: &str = "
+    CREATE TABLE IF NOT EXISTS script_pubkeys (keychain TEXT NOT NULL, child INTEGER NOT NULL, script BLOB NOT NULL);
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_keychain_child ON script_pubkeys(keychain, child);
+    CREATE INDEX IF NOT EXISTS idx_script ON script_pubkeys(script);
+    CREATE TABLE IF NOT EXISTS utxos (value INTEGER NOT NULL, keychain TEXT NOT NULL, vout INTEGER NOT NULL, txid BLOB NOT NULL, script BLOB NOT NULL);
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_txid_vout ON utxos(txid, vout);
+    CREATE TABLE IF NOT EXISTS transactions (txid BLOB NOT NULL, raw_tx BLOB NOT NULL);
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_txid ON transactions(txid);
+    CREATE TABLE IF NOT EXISTS transaction_details (txid BLOB NOT NULL, timestamp INTEGER, received INTEGER NOT NULL, sent INTEGER NOT NULL, fee INTEGER, height INTEGER, verified INTEGER NOT NULL DEFAULT 0);
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_txdetails_txid ON transaction_details(txid);
+    CREATE TABLE IF NOT EXISTS last_derivation_indices (keychain TEXT NOT NULL, value INTEGER NOT NULL);
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_indices_keychain ON last_derivation_indices(keychain);
+    CREATE TABLE IF NOT EXISTS checksums (keychain TEXT NOT NULL, checksum BLOB NOT NULL);
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_checksums_keychain ON checksums(keychain);
+";
+
+/// Statements that add the `labels` table in version 2 of the schema
+static SCHEMA_V2: &str = "
+    CREATE TABLE IF NOT EXISTS labels (type TEXT NOT NULL, ref TEXT NOT NULL, label TEXT NOT NULL, origin TEXT, spendable INTEGER);
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_labels_type_ref ON labels(type, ref);
+";
+
+/// Statements that add the `is_coinbase` column to `utxos` in version 3 of the schema
+static SCHEMA_V3: &str = "
+    ALTER TABLE utxos ADD COLUMN is_coinbase INTEGER NOT NULL DEFAULT 0;
+";
+
+/// Statements that add the `block_hash` column to `transaction_details` in version 4 of the schema
+static SCHEMA_V4: &str = "
+    ALTER TABLE transaction_details ADD COLUMN block_hash BLOB;
+";
+
+/// Statements that add the `script_statuses` table in version 5 of the schema
+static SCHEMA_V5: &str = "
+    CREATE TABLE IF NOT EXISTS script_statuses (script BLOB NOT NULL, status BLOB NOT NULL);
+    CREATE UNIQUE INDEX IF NOT EXISTS idx_script_statuses_script ON script_statuses(script);
+";
+
+/// SQLite database
+///
+/// This database stores its content in a single file at `path`. The file is created the first
+/// time it's opened, and its schema is migrated to the latest version every time it's opened. The
+/// version of the schema is stored in the `user_version` pragma.
+///
+/// The operations of a [`SqliteBatch`] are recorded and only applied when the batch is committed,
+/// inside a single transaction on the connection of the database.
+#[derive(Debug)]
+pub struct SqliteDatabase {
+    /// Path to the database file
+    pub path: String,
+    /// Open connection to the database
+    pub connection: Connection,
+}
+
+/// Configuration type for a [`SqliteDatabase`]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct SqliteDbConfiguration {
+    /// Path of the database file
+    pub path: String,
+}
+
+fn keychain_to_str(keychain: KeychainKind) -> &'static str {
+    match keychain {
+        KeychainKind::External => "External",
+        KeychainKind::Internal => "Internal",
+    }
+}
+
+fn keychain_from_str(keychain: &str) -> Result<KeychainKind, Error> {
+    match keychain {
+        "External" => Ok(KeychainKind::External),
+        "Internal" => Ok(KeychainKind::Internal),
+        other => Err(Error::Generic(format!("Invalid keychain `{}`", other))),
+    }
+}
+
+fn txid_from_row(row: &Row, idx: &str) -> Result<Txid, Error> {
+    let bytes: Vec<u8> = row.get(idx)?;
+    Txid::from_slice(&bytes).map_err(|e| Error::Generic(e.to_string()))
+}
+
+fn utxo_from_row(row: &Row) -> Result<LocalUtxo, Error> {
+    let value: i64 = row.get("value")?;
+    let keychain: String = row.get("keychain")?;
+    let vout: u32 = row.get("vout")?;
+    let script: Vec<u8> = row.get("script")?;
+    let is_coinbase: bool = row.get("is_coinbase")?;
+
+    Ok(LocalUtxo {
+        outpoint: OutPoint::new(txid_from_row(row, "txid")?, vout),
+        txout: TxOut {
+            value: value as u64,
+            script_pubkey: script.into(),
+        },
+        keychain: keychain_from_str(&keychain)?,
+        is_coinbase,
+    })
+}
+
+fn label_from_row(row: &Row) -> Result<Label, Error> {
+    let label_type: String = row.get("type")?;
+
+    Ok(Label {
+        label_type: serde_json::from_value(serde_json::Value::String(label_type))?,
+        reference: row.get("ref")?,
+        label: row.get("label")?,
+        origin: row.get("origin")?,
+        spendable: row.get("spendable")?,
+    })
+}
+
+fn details_from_row(row: &Row) -> Result<TransactionDetails, Error> {
+    let timestamp: Option<i64> = row.get("timestamp")?;
+    let height: Option<u32> = row.get("height")?;
+    let received: i64 = row.get("received")?;
+    let sent: i64 = row.get("sent")?;
+    let fee: Option<i64> = row.get("fee")?;
+    let verified: bool = row.get("verified")?;
+    let block_hash: Option<Vec<u8>> = row.get("block_hash")?;
+    let block_hash = block_hash
+        .map(|bytes| BlockHash::from_slice(&bytes))
+        .transpose()
+        .map_err(|e| Error::Generic(e.to_string()))?;
+
+    Ok(TransactionDetails {
+        transaction: None,
+        txid: txid_from_row(row, "txid")?,
+        received: received as u64,
+        sent: sent as u64,
+        fee: fee.map(|f| f as u64),
+        confirmation_time: ConfirmationTime::new(height, timestamp.map(|t| t as u64))
+            .map(|c| ConfirmationTime { block_hash, ..c }),
+        verified,
+        ancestors_fee: None,
+    })
+}
+
+impl SqliteDatabase {
+    /// Open (or create) the database at `path` and bring its schema up to date
+    pub fn new(path: String) -> Result<Self, Error> {
+        let connection = Connection::open(&path)?;
+        let mut db = SqliteDatabase { path, connection };
+        db.migrate()?;
+
+        Ok(db)
+    }
+
+    fn select_tx_details(&self, txid: &Txid) -> Result<Option<TransactionDetails>, Error> {
+        let mut statement = self
+            .connection
+            .prepare_cached("SELECT * FROM transaction_details WHERE txid = :txid")?;
+        let mut rows = statement.query(named_params! { ":txid": txid.as_inner() })?;
+
+        rows.next()?.map(details_from_row).transpose()
+    }
+
+    fn delete_raw_tx(&self, txid: &Txid) -> Result<Option<Transaction>, Error> {
+        let raw_tx = self.get_raw_tx(txid)?;
+        self.connection.execute(
+            "DELETE FROM transactions WHERE txid = :txid",
+            named_params! { ":txid": txid.as_inner() },
+        )?;
+
+        Ok(raw_tx)
+    }
+}
+
+impl BatchOperations for SqliteDatabase {
+    fn set_script_pubkey(
+        &mut self,
+        script: &Script,
+        keychain: KeychainKind,
+        child: u32,
+    ) -> Result<(), Error> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO script_pubkeys (keychain, child, script) VALUES (:keychain, :child, :script)",
+            named_params! {
+                ":keychain": keychain_to_str(keychain),
+                ":child": child,
+                ":script": script.as_bytes(),
+            },
+        )?;
+
+        Ok(())
+    }
+
+    fn set_utxo(&mut self, utxo: &LocalUtxo) -> Result<(), Error> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO utxos (value, keychain, vout, txid, script, is_coinbase) VALUES (:value, :keychain, :vout, :txid, :script, :is_coinbase)",
+            named_params! {
+                ":value": utxo.txout.value as i64,
+                ":keychain": keychain_to_str(utxo.keychain),
+                ":vout": utxo.outpoint.vout,
+                ":txid": utxo.outpoint.txid.as_inner(),
+                ":script": utxo.txout.script_pubkey.as_bytes(),
+                ":is_coinbase": utxo.is_coinbase,
+            },
+        )?;
+
+        Ok(())
+    }
+
+    fn set_raw_tx(&mut self, transaction: &Transaction) -> Result<(), Error> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO transactions (txid, raw_tx) VALUES (:txid, :raw_tx)",
+            named_params! {
+                ":txid": transaction.txid().as_inner(),
+                ":raw_tx": serialize(transaction),
+            },
+        )?;
+
+        Ok(())
+    }
+
+    fn set_tx(&mut self, transaction: &TransactionDetails) -> Result<(), Error> {
+        let confirmation_time = transaction.confirmation_time.as_ref();
+        self.connection.execute(
+            "INSERT OR REPLACE INTO transaction_details (txid, timestamp, received, sent, fee, height, verified, block_hash) VALUES (:txid, :timestamp, :received, :sent, :fee, :height, :verified, :block_hash)",
+            named_params! {
+                ":txid": transaction.txid.as_inner(),
+                ":timestamp": confirmation_time.map(|c| c.timestamp as i64),
+                ":received": transaction.received as i64,
+                ":sent": transaction.sent as i64,
+                ":fee": transaction.fee.map(|f| f as i64),
+                ":height": confirmation_time.map(|c| c.height),
+                ":verified": transaction.verified,
+                ":block_hash": confirmation_time.and_then(|c| c.block_hash.as_ref().map(|h| h.as_inner().to_vec())),
+            },
+        )?;
+
+        // insert the raw_tx if present
+        if let Some(ref tx) = transaction.transaction {
+            self.set_raw_tx(tx)?;
+        }
+
+        Ok(())
+    }
+
+    fn set_label(&mut self, label: &Label) -> Result<(), Error> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO labels (type, ref, label, origin, spendable) VALUES (:type, :ref, :label, :origin, :spendable)",
+            named_params! {
+                ":type": label.label_type.as_str(),
+                ":ref": label.reference,
+                ":label": label.label,
+                ":origin": label.origin,
+                ":spendable": label.spendable,
+            },
+        )?;
+
+        Ok(())
+    }
+
+    fn set_script_status(&mut self, script: &Script, status: &sha256::Hash) -> Result<(), Error> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO script_statuses (script, status) VALUES (:script, :status)",
+            named_params! {
+                ":script": script.as_bytes(),
+                ":status": status.as_inner(),
+            },
+        )?;
+
+        Ok(())
+    }
+
+    fn set_last_index(&mut self, keychain: KeychainKind, value: u32) -> Result<(), Error> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO last_derivation_indices (keychain, value) VALUES (:keychain, :value)",
+            named_params! {
+                ":keychain": keychain_to_str(keychain),
+                ":value": value,
+            },
+        )?;
+
+        Ok(())
+    }
+
+    fn del_script_pubkey_from_path(
+        &mut self,
+        keychain: KeychainKind,
+        child: u32,
+    ) -> Result<Option<Script>, Error> {
+        let script = self.get_script_pubkey_from_path(keychain, child)?;
+        self.connection.execute(
+            "DELETE FROM script_pubkeys WHERE keychain = :keychain AND child = :child",
+            named_params! {
+                ":keychain": keychain_to_str(keychain),
+                ":child": child,
+            },
+        )?;
+
+        Ok(script)
+    }
+
+    fn del_path_from_script_pubkey(
+        &mut self,
+        script: &Script,
+    ) -> Result<Option<(KeychainKind, u32)>, Error> {
+        let path = self.get_path_from_script_pubkey(script)?;
+        self.connection.execute(
+            "DELETE FROM script_pubkeys WHERE script = :script",
+            named_params! { ":script": script.as_bytes() },
+        )?;
+
+        Ok(path)
+    }
+
+    fn del_utxo(&mut self, outpoint: &OutPoint) -> Result<Option<LocalUtxo>, Error> {
+        let utxo = self.get_utxo(outpoint)?;
+        self.connection.execute(
+            "DELETE FROM utxos WHERE txid = :txid AND vout = :vout",
+            named_params! {
+                ":txid": outpoint.txid.as_inner(),
+                ":vout": outpoint.vout,
+            },
+        )?;
+
+        Ok(utxo)
+    }
+
+    fn del_raw_tx(&mut self, txid: &Txid) -> Result<Option<Transaction>, Error> {
+        self.delete_raw_tx(txid)
+    }
+
+    fn del_tx(
+        &mut self,
+        txid: &Txid,
+        include_raw: bool,
+    ) -> Result<Option<TransactionDetails>, Error> {
+        let raw_tx = if include_raw {
+            self.delete_raw_tx(txid)?
+        } else {
+            None
+        };
+
+        let details = self.select_tx_details(txid)?;
+        self.connection.execute(
+            "DELETE FROM transaction_details WHERE txid = :txid",
+            named_params! { ":txid": txid.as_inner() },
+        )?;
+
+        Ok(details.map(|mut details| {
+            details.transaction = raw_tx;
+            details
+        }))
+    }
+
+    fn del_last_index(&mut self, keychain: KeychainKind) -> Result<Option<u32>, Error> {
+        let value = self.get_last_index(keychain)?;
+        self.connection.execute(
+            "DELETE FROM last_derivation_indices WHERE keychain = :keychain",
+            named_params! { ":keychain": keychain_to_str(keychain) },
+        )?;
+
+        Ok(value)
+    }
+
+    fn del_label(
+        &mut self,
+        label_type: LabelType,
+        reference: &str,
+    ) -> Result<Option<Label>, Error> {
+        let label = self.get_label(label_type, reference)?;
+        self.connection.execute(
+            "DELETE FROM labels WHERE type = :type AND ref = :ref",
+            named_params! {
+                ":type": label_type.as_str(),
+                ":ref": reference,
+            },
+        )?;
+
+        Ok(label)
+    }
+
+    fn del_script_status(&mut self, script: &Script) -> Result<Option<sha256::Hash>, Error> {
+        let status = self.get_script_status(script)?;
+        self.connection.execute(
+            "DELETE FROM script_statuses WHERE script = :script",
+            named_params! { ":script": script.as_bytes() },
+        )?;
+
+        Ok(status)
+    }
+}
+
+impl Database for SqliteDatabase {
+    fn check_descriptor_checksum<B: AsRef<[u8]>>(
+        &mut self,
+        keychain: KeychainKind,
+        bytes: B,
+    ) -> Result<(), Error> {
+        let prev: Option<Vec<u8>> = self
+            .connection
+            .query_row(
+                "SELECT checksum FROM checksums WHERE keychain = :keychain",
+                named_params! { ":keychain": keychain_to_str(keychain) },
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        if let Some(val) = prev {
+            if val == bytes.as_ref() {
+                Ok(())
+            } else {
+                Err(Error::ChecksumMismatch)
+            }
+        } else {
+            self.connection.execute(
+                "INSERT INTO checksums (keychain, checksum) VALUES (:keychain, :checksum)",
+                named_params! {
+                    ":keychain": keychain_to_str(keychain),
+                    ":checksum": bytes.as_ref(),
+                },
+            )?;
+            Ok(())
+        }
+    }
+
+    fn iter_script_pubkeys(&self, keychain: Option<KeychainKind>) -> Result<Vec<Script>, Error> {
+        let mut statement = match keychain {
+            Some(_) => self.connection.prepare_cached(
+                "SELECT script FROM script_pubkeys WHERE keychain = :keychain ORDER BY child",
+            )?,
+            None => self
+                .connection
+                .prepare_cached("SELECT script FROM script_pubkeys ORDER BY keychain, child")?,
+        };
+        let mut rows = match keychain {
+            Some(keychain) => {
+                statement.query(named_params! { ":keychain": keychain_to_str(keychain) })?
+            }
+            None => statement.query([])?,
+        };
+
+        let mut scripts = vec![];
+        while let Some(row) = rows.next()? {
+            let script: Vec<u8> = row.get(0)?;
+            scripts.push(script.into());
+        }
+
+        Ok(scripts)
+    }
+
+    fn iter_utxos(&self) -> Result<Vec<LocalUtxo>, Error> {
+        let mut statement = self.connection.prepare_cached("SELECT * FROM utxos")?;
+        let mut rows = statement.query([])?;
+
+        let mut utxos = vec![];
+        while let Some(row) = rows.next()? {
+            utxos.push(utxo_from_row(row)?);
+        }
+
+        Ok(utxos)
+    }
+
+    fn iter_raw_txs(&self) -> Result<Vec<Transaction>, Error> {
+        let mut statement = self
+            .connection
+            .prepare_cached("SELECT raw_tx FROM transactions")?;
+        let mut rows = statement.query([])?;
+
+        let mut txs = vec![];
+        while let Some(row) = rows.next()? {
+            let raw_tx: Vec<u8> = row.get(0)?;
+            txs.push(deserialize(&raw_tx)?);
+        }
+
+        Ok(txs)
+    }
+
+    fn iter_txs(&self, include_raw: bool) -> Result<Vec<TransactionDetails>, Error> {
+        let mut statement = self
+            .connection
+            .prepare_cached("SELECT * FROM transaction_details")?;
+        let mut rows = statement.query([])?;
+
+        let mut txs = vec![];
+        while let Some(row) = rows.next()? {
+            let mut txdetails = details_from_row(row)?;
+            if include_raw {
+                txdetails.transaction = self.get_raw_tx(&txdetails.txid)?;
+            }
+
+            txs.push(txdetails);
+        }
+
+        Ok(txs)
+    }
+
+    fn iter_labels(&self) -> Result<Vec<Label>, Error> {
+        let mut statement = self
+            .connection
+            .prepare_cached("SELECT * FROM labels ORDER BY type, ref")?;
+        let mut rows = statement.query([])?;
+
+        let mut labels = vec![];
+        while let Some(row) = rows.next()? {
+            labels.push(label_from_row(row)?);
+        }
+
+        Ok(labels)
+    }
+
+    fn get_script_pubkey_from_path(
+        &self,
+        keychain: KeychainKind,
+        child: u32,
+    ) -> Result<Option<Script>, Error> {
+        let script: Option<Vec<u8>> = self
+            .connection
+            .query_row(
+                "SELECT script FROM script_pubkeys WHERE keychain = :keychain AND child = :child",
+                named_params! {
+                    ":keychain": keychain_to_str(keychain),
+                    ":child": child,
+                },
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(script.map(Script::from))
+    }
+
+    fn get_path_from_script_pubkey(
+        &self,
+        script: &Script,
+    ) -> Result<Option<(KeychainKind, u32)>, Error> {
+        let path: Option<(String, u32)> = self
+            .connection
+            .query_row(
+                "SELECT keychain, child FROM script_pubkeys WHERE script = :script",
+                named_params! { ":script": script.as_bytes() },
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        path.map(|(keychain, child)| Ok((keychain_from_str(&keychain)?, child)))
+            .transpose()
+    }
+
+    fn get_utxo(&self, outpoint: &OutPoint) -> Result<Option<LocalUtxo>, Error> {
+        let mut statement = self
+            .connection
+            .prepare_cached("SELECT * FROM utxos WHERE txid = :txid AND vout = :vout")?;
+        let mut rows = statement.query(named_params! {
+            ":txid": outpoint.txid.as_inner(),
+            ":vout": outpoint.vout,
+        })?;
+
+        rows.next()?.map(utxo_from_row).transpose()
+    }
+
+    fn get_raw_tx(&self, txid: &Txid) -> Result<Option<Transaction>, Error> {
+        let raw_tx: Option<Vec<u8>> = self
+            .connection
+            .query_row(
+                "SELECT raw_tx FROM transactions WHERE txid = :txid",
+                named_params! { ":txid": txid.as_inner() },
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(raw_tx.map(|b| deserialize(&b)).transpose()?)
+    }
+
+    fn get_tx(&self, txid: &Txid, include_raw: bool) -> Result<Option<TransactionDetails>, Error> {
+        let mut txdetails = match self.select_tx_details(txid)? {
+            Some(txdetails) => txdetails,
+            None => return Ok(None),
+        };
+        if include_raw {
+            txdetails.transaction = self.get_raw_tx(txid)?;
+        }
+
+        Ok(Some(txdetails))
+    }
+
+    fn get_last_index(&self, keychain: KeychainKind) -> Result<Option<u32>, Error> {
+        Ok(self
+            .connection
+            .query_row(
+                "SELECT value FROM last_derivation_indices WHERE keychain = :keychain",
+                named_params! { ":keychain": keychain_to_str(keychain) },
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    fn get_label(&self, label_type: LabelType, reference: &str) -> Result<Option<Label>, Error> {
+        let mut statement = self
+            .connection
+            .prepare_cached("SELECT * FROM labels WHERE type = :type AND ref = :ref")?;
+        let mut rows = statement.query(named_params! {
+            ":type": label_type.as_str(),
+            ":ref": reference,
+        })?;
+
+        rows.next()?.map(label_from_row).transpose()
+    }
+
+    fn get_script_status(&self, script: &Script) -> Result<Option<sha256::Hash>, Error> {
+        let mut statement = self
+            .connection
+            .prepare_cached("SELECT status FROM script_statuses WHERE script = :script")?;
+        let mut rows = statement.query(named_params! { ":script": script.as_bytes() })?;
+
+        rows.next()?
+            .map(|row| -> Result<_, Error> {
+                let bytes: Vec<u8> = row.get("status")?;
+                sha256::Hash::from_slice(&bytes).map_err(|e| Error::Generic(e.to_string()))
+            })
+            .transpose()
+    }
+
+    // inserts 0 if not present
+    fn increment_last_index(&mut self, keychain: KeychainKind) -> Result<u32, Error> {
+        self.connection.execute(
+            "INSERT INTO last_derivation_indices (keychain, value) VALUES (:keychain, 0) ON CONFLICT(keychain) DO UPDATE SET value = value + 1",
+            named_params! { ":keychain": keychain_to_str(keychain) },
+        )?;
+
+        self.get_last_index(keychain)?
+            .ok_or_else(|| Error::Generic("Missing last index after increment".to_string()))
+    }
+
+    fn get_format_version(&self) -> Result<Option<u32>, Error> {
+        let version: u32 = self
+            .connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+        // a freshly created file has `user_version` set to 0
+        Ok(Some(version).filter(|v| *v > 0))
+    }
+
+    fn migrate(&mut self) -> Result<(), Error> {
+        migration::migrate(self)
+    }
+}
+
+impl Migrate for SqliteDatabase {
+    const MIGRATIONS: &'static [Migration<Self>] = &[
+        // v1: initial schema
+        |db| Ok(db.connection.execute_batch(SCHEMA_V1)?),
+        // v2: add labels
+        |db| Ok(db.connection.execute_batch(SCHEMA_V2)?),
+        // v3: track coinbase utxos
+        |db| {
+            db.connection.execute_batch(SCHEMA_V3)?;
+            migration::flag_coinbase_utxos(db)
+        },
+        // v4: store the hash of the confirmation block
+        |db| Ok(db.connection.execute_batch(SCHEMA_V4)?),
+        // v5: add script statuses
+        |db| Ok(db.connection.execute_batch(SCHEMA_V5)?),
+    ];
+
+    fn set_format_version(&mut self, version: u32) -> Result<(), Error> {
+        self.connection
+            .pragma_update(None, "user_version", version)?;
+
+        Ok(())
+    }
+}
+
+type BatchOperation = Box<dyn FnOnce(&mut SqliteDatabase) -> Result<(), Error> + Send>;
+
+/// Batch of operations on a [`SqliteDatabase`]
+///
+/// The operations are applied when the batch is committed, so the deletions always return `None`.
+#[derive(Default)]
+pub struct SqliteBatch {
+    operations: Vec<BatchOperation>,
+}
+
+impl fmt::Debug for SqliteBatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SqliteBatch")
+            .field("operations", &self.operations.len())
+            .finish()
+    }
+}
+
+impl SqliteBatch {
+    fn push<F>(&mut self, operation: F)
+    where
+        F: FnOnce(&mut SqliteDatabase) -> Result<(), Error> + Send + 'static,
+    {
+        self.operations.push(Box::new(operation));
+    }
+}
+
+impl BatchOperations for SqliteBatch {
+    fn set_script_pubkey(
+        &mut self,
+        script: &Script,
+        keychain: KeychainKind,
+        child: u32,
+    ) -> Result<(), Error> {
+        let script = script.clone();
+        self.push(move |db| db.set_script_pubkey(&script, keychain, child));
+        Ok(())
+    }
+
+    fn set_utxo(&mut self, utxo: &LocalUtxo) -> Result<(), Error> {
+        let utxo = utxo.clone();
+        self.push(move |db| db.set_utxo(&utxo));
+        Ok(())
+    }
+
+    fn set_raw_tx(&mut self, transaction: &Transaction) -> Result<(), Error> {
+        let transaction = transaction.clone();
+        self.push(move |db| db.set_raw_tx(&transaction));
+        Ok(())
+    }
+
+    fn set_tx(&mut self, transaction: &TransactionDetails) -> Result<(), Error> {
+        let transaction = transaction.clone();
+        self.push(move |db| db.set_tx(&transaction));
+        Ok(())
+    }
+
+    fn set_last_index(&mut self, keychain: KeychainKind, value: u32) -> Result<(), Error> {
+        self.push(move |db| db.set_last_index(keychain, value));
+        Ok(())
+    }
+
+    fn set_label(&mut self, label: &Label) -> Result<(), Error> {
+        let label = label.clone();
+        self.push(move |db| db.set_label(&label));
+        Ok(())
+    }
+
+    fn set_script_status(&mut self, script: &Script, status: &sha256::Hash) -> Result<(), Error> {
+        let (script, status) = (script.clone(), *status);
+        self.push(move |db| db.set_script_status(&script, &status));
+        Ok(())
+    }
+
+    fn del_script_pubkey_from_path(
+        &mut self,
+        keychain: KeychainKind,
+        child: u32,
+    ) -> Result<Option<Script>, Error> {
+        self.push(move |db| db.del_script_pubkey_from_path(keychain, child).map(|_| ()));
+        Ok(None)
+    }
+
+    fn del_path_from_script_pubkey(
+        &mut self,
+        script: &Script,
+    ) -> Result<Option<(KeychainKind, u32)>, Error> {
+        let script = script.clone();
+        self.push(move |db| db.del_path_from_script_pubkey(&script).map(|_| ()));
+        Ok(None)
+    }
+
+    fn del_utxo(&mut self, outpoint: &OutPoint) -> Result<Option<LocalUtxo>, Error> {
+        let outpoint = *outpoint;
+        self.push(move |db| db.del_utxo(&outpoint).map(|_| ()));
+        Ok(None)
+    }
+
+    fn del_raw_tx(&mut self, txid: &Txid) -> Result<Option<Transaction>, Error> {
+        let txid = *txid;
+        self.push(move |db| db.del_raw_tx(&txid).map(|_| ()));
+        Ok(None)
+    }
+
+    fn del_tx(
+        &mut self,
+        txid: &Txid,
+        include_raw: bool,
+    ) -> Result<Option<TransactionDetails>, Error> {
+        let txid = *txid;
+        self.push(move |db| db.del_tx(&txid, include_raw).map(|_| ()));
+        Ok(None)
+    }
+
+    fn del_last_index(&mut self, keychain: KeychainKind) -> Result<Option<u32>, Error> {
+        self.push(move |db| db.del_last_index(keychain).map(|_| ()));
+        Ok(None)
+    }
+
+    fn del_label(
+        &mut self,
+        label_type: LabelType,
+        reference: &str,
+    ) -> Result<Option<Label>, Error> {
+        let reference = reference.to_string();
+        self.push(move |db| db.del_label(label_type, &reference).map(|_| ()));
+        Ok(None)
+    }
+
+    fn del_script_status(&mut self, script: &Script) -> Result<Option<sha256::Hash>, Error> {
+        let script = script.clone();
+        self.push(move |db| db.del_script_status(&script).map(|_| ()));
+        Ok(None)
+    }
+}
+
+impl BatchDatabase for SqliteDatabase {
+    type Batch = SqliteBatch;
+
+    fn begin_batch(&self) -> Self::Batch {
+        SqliteBatch::default()
+    }
+
+    fn commit_batch(&mut self, batch: Self::Batch) -> Result<(), Error> {
+        self.connection.execute_batch("BEGIN TRANSACTION")?;
+        for operation in batch.operations {
+            if let Err(e) = operation(self) {
+                self.connection.execute_batch("ROLLBACK TRANSACTION")?;
+                return Err(e);
+            }
+        }
+        self.connection.execute_batch("COMMIT TRANSACTION")?;
+
+        Ok(())
+    }
+}
+
+impl ConfigurableDatabase for SqliteDatabase {
+    type Config = SqliteDbConfiguration;
+
+    fn from_config(config: &Self::Config) -> Result<Self, Error> {
+        SqliteDatabase::new(config.path.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::Script;
+
+    use super::SqliteDatabase;
+    use crate::database::{BatchDatabase, BatchOperations, Database};
+    use crate::types::KeychainKind;
+
+    fn get_database() -> SqliteDatabase {
+        SqliteDatabase::new(":memory:".to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_script_pubkey() {
+        crate::database::test::test_script_pubkey(get_database());
+    }
+
+    #[test]
+    fn test_batch_script_pubkey() {
+        crate::database::test::test_batch_script_pubkey(get_database());
+    }
+
+    #[test]
+    fn test_iter_script_pubkey() {
+        crate::database::test::test_iter_script_pubkey(get_database());
+    }
+
+    #[test]
+    fn test_del_script_pubkey() {
+        crate::database::test::test_del_script_pubkey(get_database());
+    }
+
+    #[test]
+    fn test_utxo() {
+        crate::database::test::test_utxo(get_database());
+    }
+
+    #[test]
+    fn test_raw_tx() {
+        crate::database::test::test_raw_tx(get_database());
+    }
+
+    #[test]
+    fn test_tx() {
+        crate::database::test::test_tx(get_database());
+    }
+
+    #[test]
+    fn test_last_index() {
+        crate::database::test::test_last_index(get_database());
+    }
+
+    #[test]
+    fn test_label() {
+        crate::database::test::test_label(get_database());
+    }
+
+    #[test]
+    fn test_script_status() {
+        crate::database::test::test_script_status(get_database());
+    }
+
+    #[test]
+    fn test_format_version() {
+        crate::database::test::test_format_version(get_database());
+    }
+
+    #[test]
+    fn test_batch_in_memory() {
+        let mut db = get_database();
+        let script = Script::from(vec![0x00, 0x14]);
+
+        let mut batch = db.begin_batch();
+        batch
+            .set_script_pubkey(&script, KeychainKind::External, 42)
+            .unwrap();
+        batch.set_last_index(KeychainKind::External, 42).unwrap();
+        assert_eq!(
+            db.get_path_from_script_pubkey(&script).unwrap(),
+            None,
+            "the batch must not be applied before it's committed"
+        );
+
+        db.commit_batch(batch).unwrap();
+        assert_eq!(
+            db.get_path_from_script_pubkey(&script).unwrap(),
+            Some((KeychainKind::External, 42))
+        );
+        assert_eq!(db.get_last_index(KeychainKind::External).unwrap(), Some(42));
+    }
+}
@@ -397,6 +397,386 @@ impl<K: DerivableKey<Segwitv0>> DescriptorTemplate for Bip84Public<K> {
     }
 }
 
+/// P2WSH sorted multisig template. Expands to a descriptor `wsh(sortedmulti(threshold, keys))`
+///
+/// ## Example
+///
+/// ```
+/// # use std::str::FromStr;
+/// # use bdk::bitcoin::{PublicKey, Network};
+/// # use bdk::{Wallet};
+/// # use bdk::database::MemoryDatabase;
+/// # use bdk::wallet::AddressIndex::New;
+/// use bdk::template::P2Wsh_SortedMulti;
+///
+/// let key_a = bitcoin::PublicKey::from_str("03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd")?;
+/// let key_b = bitcoin::PublicKey::from_str("02e6642fd69bd211f93f7f1f36ca51a26a5290eb2dd1b0d8279a87bb0d480c8443")?;
+/// let wallet = Wallet::new_offline(
+///     P2Wsh_SortedMulti(2, vec![key_a, key_b]),
+///     None,
+///     Network::Testnet,
+///     MemoryDatabase::default(),
+/// )?;
+///
+/// assert_eq!(
+///     wallet.get_address(New)?.to_string(),
+///     "tb1q44u7wlzems4dj90hz99xeq0a2fxx7fwpj4dhsrtn4cupjpmjvj9qy9zysn"
+/// );
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+#[allow(non_camel_case_types)]
+pub struct P2Wsh_SortedMulti<K: IntoDescriptorKey<Segwitv0>>(pub usize, pub Vec<K>);
+
+impl<K: IntoDescriptorKey<Segwitv0>> DescriptorTemplate for P2Wsh_SortedMulti<K> {
+    fn build(self) -> Result<DescriptorTemplateOut, DescriptorError> {
+        descriptor!(wsh(sortedmulti_vec(self.0, self.1)))
+    }
+}
+
+/// P2WSH-P2SH sorted multisig template. Expands to a descriptor
+/// `sh(wsh(sortedmulti(threshold, keys)))`
+///
+/// ## Example
+///
+/// ```
+/// # use std::str::FromStr;
+/// # use bdk::bitcoin::{PublicKey, Network};
+/// # use bdk::{Wallet};
+/// # use bdk::database::MemoryDatabase;
+/// # use bdk::wallet::AddressIndex::New;
+/// use bdk::template::P2Wsh_P2Sh_SortedMulti;
+///
+/// let key_a = bitcoin::PublicKey::from_str("03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd")?;
+/// let key_b = bitcoin::PublicKey::from_str("02e6642fd69bd211f93f7f1f36ca51a26a5290eb2dd1b0d8279a87bb0d480c8443")?;
+/// let wallet = Wallet::new_offline(
+///     P2Wsh_P2Sh_SortedMulti(2, vec![key_a, key_b]),
+///     None,
+///     Network::Testnet,
+///     MemoryDatabase::default(),
+/// )?;
+///
+/// assert_eq!(
+///     wallet.get_address(New)?.to_string(),
+///     "2N4ShiusuyTn2cJ6bZgmTCzNDkK3ekQzn2N"
+/// );
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+#[allow(non_camel_case_types)]
+pub struct P2Wsh_P2Sh_SortedMulti<K: IntoDescriptorKey<Segwitv0>>(pub usize, pub Vec<K>);
+
+impl<K: IntoDescriptorKey<Segwitv0>> DescriptorTemplate for P2Wsh_P2Sh_SortedMulti<K> {
+    fn build(self) -> Result<DescriptorTemplateOut, DescriptorError> {
+        descriptor!(sh(wsh(sortedmulti_vec(self.0, self.1))))
+    }
+}
+
+/// BIP48 P2WSH template. Expands to `wsh(sortedmulti(threshold, key/48'/0'/0'/2'/{0,1}/*, ...))`
+///
+/// Since there are hardened derivation steps, this template requires private derivable keys
+/// (generally `xprv`s/`tprv`s). Every cosigner uses this template with its own private key and
+/// the [`Bip48WshPublic`] keys of the others.
+///
+/// ## Example
+///
+/// ```
+/// # use std::str::FromStr;
+/// # use bdk::bitcoin::{PrivateKey, Network};
+/// # use bdk::{Wallet,  KeychainKind};
+/// # use bdk::database::MemoryDatabase;
+/// # use bdk::wallet::AddressIndex::New;
+/// use bdk::template::Bip48Wsh;
+///
+/// let key_a = bitcoin::util::bip32::ExtendedPrivKey::from_str("tprv8ZgxMBicQKsPeZRHk4rTG6orPS2CRNFX3njhUXx5vj9qGog5ZMH4uGReDWN5kCkY3jmWEtWause41CDvBRXD1shKknAMKxT99o9qUTRVC6m")?;
+/// let key_b = bitcoin::util::bip32::ExtendedPrivKey::from_str("tprv8ZgxMBicQKsPcx5nBGsR63Pe8KnRUqmbJNENAfGftF3yuXoMMoVJJcYeUw5eVkm9WBPjWYt6HMWYJNesB5HaNVBaFc1M6dRjWSYnmewUMYy")?;
+/// let wallet = Wallet::new_offline(
+///     Bip48Wsh(2, vec![key_a, key_b], KeychainKind::External),
+///     Some(Bip48Wsh(2, vec![key_a, key_b], KeychainKind::Internal)),
+///     Network::Testnet,
+///     MemoryDatabase::default()
+/// )?;
+///
+/// assert_eq!(wallet.get_address(New)?.to_string(), "tb1qz9xvqv63w29j37pwmhxag38vdmq5w7jjq07hnxjl52qgcuvfcwvqf8gndy");
+/// assert_eq!(wallet.public_descriptor(KeychainKind::External)?.unwrap().to_string(), "wsh(sortedmulti(2,[c55b303f/48\'/0\'/0\'/2\']tpubDFCUE3UPVoSVk89QRuDAtBiQQc6DuJYCfjssVYMfSWSonybyhBCUGCoXQ112axmHnq7XvFWNpgDyrUHmLm6ghZKVgQpzThnEAn4z11FD144/0/*,[34b00776/48\'/0\'/0\'/2\']tpubDFMH5o1PTzwMn2cMa4dmYugyc9xHHcShpqLW2n8Wt7vNxYsz4T8x8X4eSfp3KdjdUVeFLmPQN9LEbTkm63QH3mrwMTYvF9vhXh7cWTD3iAa/0/*))#dg7v0gyw");
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+pub struct Bip48Wsh<K: DerivableKey<Segwitv0>>(pub usize, pub Vec<K>, pub KeychainKind);
+
+impl<K: DerivableKey<Segwitv0>> DescriptorTemplate for Bip48Wsh<K> {
+    fn build(self) -> Result<DescriptorTemplateOut, DescriptorError> {
+        let keychain = self.2;
+        let keys = self
+            .1
+            .into_iter()
+            .map(|key| segwit_v0::make_account_private(&[48, 0, 0, 2], key, keychain))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        P2Wsh_SortedMulti(self.0, keys).build()
+    }
+}
+
+/// BIP48 P2WSH public template. Expands to `wsh(sortedmulti(threshold, key/{0,1}/*, ...))`
+///
+/// This assumes that the keys used have already been derived with `m/48'/0'/0'/2'`.
+///
+/// This template requires the parent fingerprint of every key to populate correctly the
+/// metadata of PSBTs.
+///
+/// See [`Bip48Wsh`] for a template that does the full derivation, but requires private data
+/// for the keys.
+///
+/// ## Example
+///
+/// ```
+/// # use std::str::FromStr;
+/// # use bdk::bitcoin::{PrivateKey, Network};
+/// # use bdk::{Wallet,  KeychainKind};
+/// # use bdk::database::MemoryDatabase;
+/// # use bdk::wallet::AddressIndex::New;
+/// use bdk::template::Bip48WshPublic;
+///
+/// let key_a = bitcoin::util::bip32::ExtendedPubKey::from_str("tpubDFCUE3UPVoSVk89QRuDAtBiQQc6DuJYCfjssVYMfSWSonybyhBCUGCoXQ112axmHnq7XvFWNpgDyrUHmLm6ghZKVgQpzThnEAn4z11FD144")?;
+/// let fingerprint_a = bitcoin::util::bip32::Fingerprint::from_str("c55b303f")?;
+/// let key_b = bitcoin::util::bip32::ExtendedPubKey::from_str("tpubDFMH5o1PTzwMn2cMa4dmYugyc9xHHcShpqLW2n8Wt7vNxYsz4T8x8X4eSfp3KdjdUVeFLmPQN9LEbTkm63QH3mrwMTYvF9vhXh7cWTD3iAa")?;
+/// let fingerprint_b = bitcoin::util::bip32::Fingerprint::from_str("34b00776")?;
+/// let keys = vec![(key_a, fingerprint_a), (key_b, fingerprint_b)];
+/// let wallet = Wallet::new_offline(
+///     Bip48WshPublic(2, keys.clone(), KeychainKind::External),
+///     Some(Bip48WshPublic(2, keys, KeychainKind::Internal)),
+///     Network::Testnet,
+///     MemoryDatabase::default()
+/// )?;
+///
+/// assert_eq!(wallet.get_address(New)?.to_string(), "tb1qz9xvqv63w29j37pwmhxag38vdmq5w7jjq07hnxjl52qgcuvfcwvqf8gndy");
+/// assert_eq!(wallet.public_descriptor(KeychainKind::External)?.unwrap().to_string(), "wsh(sortedmulti(2,[c55b303f/48\'/0\'/0\'/2\']tpubDFCUE3UPVoSVk89QRuDAtBiQQc6DuJYCfjssVYMfSWSonybyhBCUGCoXQ112axmHnq7XvFWNpgDyrUHmLm6ghZKVgQpzThnEAn4z11FD144/0/*,[34b00776/48\'/0\'/0\'/2\']tpubDFMH5o1PTzwMn2cMa4dmYugyc9xHHcShpqLW2n8Wt7vNxYsz4T8x8X4eSfp3KdjdUVeFLmPQN9LEbTkm63QH3mrwMTYvF9vhXh7cWTD3iAa/0/*))#dg7v0gyw");
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+pub struct Bip48WshPublic<K: DerivableKey<Segwitv0>>(
+    pub usize,
+    pub Vec<(K, bip32::Fingerprint)>,
+    pub KeychainKind,
+);
+
+impl<K: DerivableKey<Segwitv0>> DescriptorTemplate for Bip48WshPublic<K> {
+    fn build(self) -> Result<DescriptorTemplateOut, DescriptorError> {
+        let keychain = self.2;
+        let keys = self
+            .1
+            .into_iter()
+            .map(|(key, fingerprint)| {
+                segwit_v0::make_account_public(&[48, 0, 0, 2], key, fingerprint, keychain)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        P2Wsh_SortedMulti(self.0, keys).build()
+    }
+}
+
+/// BIP48 P2WSH-P2SH template. Expands to
+/// `sh(wsh(sortedmulti(threshold, key/48'/0'/0'/1'/{0,1}/*, ...)))`
+///
+/// Since there are hardened derivation steps, this template requires private derivable keys
+/// (generally `xprv`s/`tprv`s).
+///
+/// See [`Bip48ShWshPublic`] for a template that can work with `xpub`s/`tpub`s.
+///
+/// ## Example
+///
+/// ```
+/// # use std::str::FromStr;
+/// # use bdk::bitcoin::{PrivateKey, Network};
+/// # use bdk::{Wallet,  KeychainKind};
+/// # use bdk::database::MemoryDatabase;
+/// # use bdk::wallet::AddressIndex::New;
+/// use bdk::template::Bip48ShWsh;
+///
+/// let key_a = bitcoin::util::bip32::ExtendedPrivKey::from_str("tprv8ZgxMBicQKsPeZRHk4rTG6orPS2CRNFX3njhUXx5vj9qGog5ZMH4uGReDWN5kCkY3jmWEtWause41CDvBRXD1shKknAMKxT99o9qUTRVC6m")?;
+/// let key_b = bitcoin::util::bip32::ExtendedPrivKey::from_str("tprv8ZgxMBicQKsPcx5nBGsR63Pe8KnRUqmbJNENAfGftF3yuXoMMoVJJcYeUw5eVkm9WBPjWYt6HMWYJNesB5HaNVBaFc1M6dRjWSYnmewUMYy")?;
+/// let wallet = Wallet::new_offline(
+///     Bip48ShWsh(2, vec![key_a, key_b], KeychainKind::External),
+///     Some(Bip48ShWsh(2, vec![key_a, key_b], KeychainKind::Internal)),
+///     Network::Testnet,
+///     MemoryDatabase::default()
+/// )?;
+///
+/// assert_eq!(wallet.get_address(New)?.to_string(), "2NBMTcX4fzBD6TmVLznDddxLqRjvxfbbXRx");
+/// assert_eq!(wallet.public_descriptor(KeychainKind::External)?.unwrap().to_string(), "sh(wsh(sortedmulti(2,[c55b303f/48\'/0\'/0\'/1\']tpubDFCUE3UPVoSVfM9pChPyhVfGDEphG3aAME3tHdV45ijTZ2SrsBG5vaUNaNKm8oYHiVHAGd5RqKUgP5LnwyTNL5KdgGJwWQBRK8SvyRspEt9/0/*,[34b00776/48\'/0\'/0\'/1\']tpubDFMH5o1PTzwMiVw8mkfcTMDXhMH2CNHQkzWv2bJxKcjsZwJsVCVhEXWQepJUStqzBxx8xBL9c1qq5Vgj1TcVqwZJohgg1f4azNkmVkwGno2/0/*)))#xm88kcuf");
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+pub struct Bip48ShWsh<K: DerivableKey<Segwitv0>>(pub usize, pub Vec<K>, pub KeychainKind);
+
+impl<K: DerivableKey<Segwitv0>> DescriptorTemplate for Bip48ShWsh<K> {
+    fn build(self) -> Result<DescriptorTemplateOut, DescriptorError> {
+        let keychain = self.2;
+        let keys = self
+            .1
+            .into_iter()
+            .map(|key| segwit_v0::make_account_private(&[48, 0, 0, 1], key, keychain))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        P2Wsh_P2Sh_SortedMulti(self.0, keys).build()
+    }
+}
+
+/// BIP48 P2WSH-P2SH public template. Expands to `sh(wsh(sortedmulti(threshold, key/{0,1}/*, ...)))`
+///
+/// This assumes that the keys used have already been derived with `m/48'/0'/0'/1'`.
+///
+/// This template requires the parent fingerprint of every key to populate correctly the
+/// metadata of PSBTs.
+///
+/// See [`Bip48ShWsh`] for a template that does the full derivation, but requires private data
+/// for the keys.
+///
+/// ## Example
+///
+/// ```
+/// # use std::str::FromStr;
+/// # use bdk::bitcoin::{PrivateKey, Network};
+/// # use bdk::{Wallet,  KeychainKind};
+/// # use bdk::database::MemoryDatabase;
+/// # use bdk::wallet::AddressIndex::New;
+/// use bdk::template::Bip48ShWshPublic;
+///
+/// let key_a = bitcoin::util::bip32::ExtendedPubKey::from_str("tpubDFCUE3UPVoSVfM9pChPyhVfGDEphG3aAME3tHdV45ijTZ2SrsBG5vaUNaNKm8oYHiVHAGd5RqKUgP5LnwyTNL5KdgGJwWQBRK8SvyRspEt9")?;
+/// let fingerprint_a = bitcoin::util::bip32::Fingerprint::from_str("c55b303f")?;
+/// let key_b = bitcoin::util::bip32::ExtendedPubKey::from_str("tpubDFMH5o1PTzwMiVw8mkfcTMDXhMH2CNHQkzWv2bJxKcjsZwJsVCVhEXWQepJUStqzBxx8xBL9c1qq5Vgj1TcVqwZJohgg1f4azNkmVkwGno2")?;
+/// let fingerprint_b = bitcoin::util::bip32::Fingerprint::from_str("34b00776")?;
+/// let keys = vec![(key_a, fingerprint_a), (key_b, fingerprint_b)];
+/// let wallet = Wallet::new_offline(
+///     Bip48ShWshPublic(2, keys.clone(), KeychainKind::External),
+///     Some(Bip48ShWshPublic(2, keys, KeychainKind::Internal)),
+///     Network::Testnet,
+///     MemoryDatabase::default()
+/// )?;
+///
+/// assert_eq!(wallet.get_address(New)?.to_string(), "2NBMTcX4fzBD6TmVLznDddxLqRjvxfbbXRx");
+/// assert_eq!(wallet.public_descriptor(KeychainKind::External)?.unwrap().to_string(), "sh(wsh(sortedmulti(2,[c55b303f/48\'/0\'/0\'/1\']tpubDFCUE3UPVoSVfM9pChPyhVfGDEphG3aAME3tHdV45ijTZ2SrsBG5vaUNaNKm8oYHiVHAGd5RqKUgP5LnwyTNL5KdgGJwWQBRK8SvyRspEt9/0/*,[34b00776/48\'/0\'/0\'/1\']tpubDFMH5o1PTzwMiVw8mkfcTMDXhMH2CNHQkzWv2bJxKcjsZwJsVCVhEXWQepJUStqzBxx8xBL9c1qq5Vgj1TcVqwZJohgg1f4azNkmVkwGno2/0/*)))#xm88kcuf");
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+pub struct Bip48ShWshPublic<K: DerivableKey<Segwitv0>>(
+    pub usize,
+    pub Vec<(K, bip32::Fingerprint)>,
+    pub KeychainKind,
+);
+
+impl<K: DerivableKey<Segwitv0>> DescriptorTemplate for Bip48ShWshPublic<K> {
+    fn build(self) -> Result<DescriptorTemplateOut, DescriptorError> {
+        let keychain = self.2;
+        let keys = self
+            .1
+            .into_iter()
+            .map(|(key, fingerprint)| {
+                segwit_v0::make_account_public(&[48, 0, 0, 1], key, fingerprint, keychain)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        P2Wsh_P2Sh_SortedMulti(self.0, keys).build()
+    }
+}
+
+/// BIP87 template. Expands to `wsh(sortedmulti(threshold, key/87'/0'/0'/{0,1}/*, ...))`
+///
+/// BIP87 doesn't encode the script type in the derivation path, this template uses native
+/// segwit `wsh(sortedmulti())` like most of the wallets that implement it.
+///
+/// Since there are hardened derivation steps, this template requires private derivable keys
+/// (generally `xprv`s/`tprv`s).
+///
+/// See [`Bip87Public`] for a template that can work with `xpub`s/`tpub`s.
+///
+/// ## Example
+///
+/// ```
+/// # use std::str::FromStr;
+/// # use bdk::bitcoin::{PrivateKey, Network};
+/// # use bdk::{Wallet,  KeychainKind};
+/// # use bdk::database::MemoryDatabase;
+/// # use bdk::wallet::AddressIndex::New;
+/// use bdk::template::Bip87;
+///
+/// let key_a = bitcoin::util::bip32::ExtendedPrivKey::from_str("tprv8ZgxMBicQKsPeZRHk4rTG6orPS2CRNFX3njhUXx5vj9qGog5ZMH4uGReDWN5kCkY3jmWEtWause41CDvBRXD1shKknAMKxT99o9qUTRVC6m")?;
+/// let key_b = bitcoin::util::bip32::ExtendedPrivKey::from_str("tprv8ZgxMBicQKsPcx5nBGsR63Pe8KnRUqmbJNENAfGftF3yuXoMMoVJJcYeUw5eVkm9WBPjWYt6HMWYJNesB5HaNVBaFc1M6dRjWSYnmewUMYy")?;
+/// let wallet = Wallet::new_offline(
+///     Bip87(2, vec![key_a, key_b], KeychainKind::External),
+///     Some(Bip87(2, vec![key_a, key_b], KeychainKind::Internal)),
+///     Network::Testnet,
+///     MemoryDatabase::default()
+/// )?;
+///
+/// assert_eq!(wallet.get_address(New)?.to_string(), "tb1qj8xqcnv4jzdtpggn667884z99ky8qux5uwx2pq37nlmkqytxjpgsu4w762");
+/// assert_eq!(wallet.public_descriptor(KeychainKind::External)?.unwrap().to_string(), "wsh(sortedmulti(2,[c55b303f/87\'/0\'/0\']tpubDDQpexebcDnk5X3xDsFEJacHSs2Q17b1e3BBMTgh85A3w44S4hJyqVc81f7XAyXwtEjLZ8J5naa2wEgmqPkjdRsGW5S2TjKfKytJjzxfLHM/0/*,[34b00776/87\'/0\'/0\']tpubDDeF3eiUzBhXfuTC6aaU9meLTbywPCMaSMqGdE7TWLc8mxMFBETrqzxpnjGrsQzSZytGxgRrYTMQHmYBFcqJDKK5wiuQARPx3GLTqkho3zG/0/*))#64mwtl39");
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+pub struct Bip87<K: DerivableKey<Segwitv0>>(pub usize, pub Vec<K>, pub KeychainKind);
+
+impl<K: DerivableKey<Segwitv0>> DescriptorTemplate for Bip87<K> {
+    fn build(self) -> Result<DescriptorTemplateOut, DescriptorError> {
+        let keychain = self.2;
+        let keys = self
+            .1
+            .into_iter()
+            .map(|key| segwit_v0::make_bipxx_private(87, key, keychain))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        P2Wsh_SortedMulti(self.0, keys).build()
+    }
+}
+
+/// BIP87 public template. Expands to `wsh(sortedmulti(threshold, key/{0,1}/*, ...))`
+///
+/// This assumes that the keys used have already been derived with `m/87'/0'/0'`.
+///
+/// This template requires the parent fingerprint of every key to populate correctly the
+/// metadata of PSBTs.
+///
+/// See [`Bip87`] for a template that does the full derivation, but requires private data
+/// for the keys.
+///
+/// ## Example
+///
+/// ```
+/// # use std::str::FromStr;
+/// # use bdk::bitcoin::{PrivateKey, Network};
+/// # use bdk::{Wallet,  KeychainKind};
+/// # use bdk::database::MemoryDatabase;
+/// # use bdk::wallet::AddressIndex::New;
+/// use bdk::template::Bip87Public;
+///
+/// let key_a = bitcoin::util::bip32::ExtendedPubKey::from_str("tpubDDQpexebcDnk5X3xDsFEJacHSs2Q17b1e3BBMTgh85A3w44S4hJyqVc81f7XAyXwtEjLZ8J5naa2wEgmqPkjdRsGW5S2TjKfKytJjzxfLHM")?;
+/// let fingerprint_a = bitcoin::util::bip32::Fingerprint::from_str("c55b303f")?;
+/// let key_b = bitcoin::util::bip32::ExtendedPubKey::from_str("tpubDDeF3eiUzBhXfuTC6aaU9meLTbywPCMaSMqGdE7TWLc8mxMFBETrqzxpnjGrsQzSZytGxgRrYTMQHmYBFcqJDKK5wiuQARPx3GLTqkho3zG")?;
+/// let fingerprint_b = bitcoin::util::bip32::Fingerprint::from_str("34b00776")?;
+/// let keys = vec![(key_a, fingerprint_a), (key_b, fingerprint_b)];
+/// let wallet = Wallet::new_offline(
+///     Bip87Public(2, keys.clone(), KeychainKind::External),
+///     Some(Bip87Public(2, keys, KeychainKind::Internal)),
+///     Network::Testnet,
+///     MemoryDatabase::default()
+/// )?;
+///
+/// assert_eq!(wallet.get_address(New)?.to_string(), "tb1qj8xqcnv4jzdtpggn667884z99ky8qux5uwx2pq37nlmkqytxjpgsu4w762");
+/// assert_eq!(wallet.public_descriptor(KeychainKind::External)?.unwrap().to_string(), "wsh(sortedmulti(2,[c55b303f/87\'/0\'/0\']tpubDDQpexebcDnk5X3xDsFEJacHSs2Q17b1e3BBMTgh85A3w44S4hJyqVc81f7XAyXwtEjLZ8J5naa2wEgmqPkjdRsGW5S2TjKfKytJjzxfLHM/0/*,[34b00776/87\'/0\'/0\']tpubDDeF3eiUzBhXfuTC6aaU9meLTbywPCMaSMqGdE7TWLc8mxMFBETrqzxpnjGrsQzSZytGxgRrYTMQHmYBFcqJDKK5wiuQARPx3GLTqkho3zG/0/*))#64mwtl39");
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+pub struct Bip87Public<K: DerivableKey<Segwitv0>>(
+    pub usize,
+    pub Vec<(K, bip32::Fingerprint)>,
+    pub KeychainKind,
+);
+
+impl<K: DerivableKey<Segwitv0>> DescriptorTemplate for Bip87Public<K> {
+    fn build(self) -> Result<DescriptorTemplateOut, DescriptorError> {
+        let keychain = self.2;
+        let keys = self
+            .1
+            .into_iter()
+            .map(|(key, fingerprint)| segwit_v0::make_bipxx_public(87, key, fingerprint, keychain))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        P2Wsh_SortedMulti(self.0, keys).build()
+    }
+}
+
 macro_rules! expand_make_bipxx {
     ( $mod_name:ident, $ctx:ty ) => {
         mod $mod_name {
@@ -407,10 +787,27 @@ macro_rules! expand_make_bipxx {
                 key: K,
                 keychain: KeychainKind,
             ) -> Result<impl IntoDescriptorKey<$ctx>, DescriptorError> {
-                let mut derivation_path = Vec::with_capacity(4);
-                derivation_path.push(bip32::ChildNumber::from_hardened_idx(bip)?);
-                derivation_path.push(bip32::ChildNumber::from_hardened_idx(0)?);
-                derivation_path.push(bip32::ChildNumber::from_hardened_idx(0)?);
+                make_account_private(&[bip, 0, 0], key, keychain)
+            }
+            pub(super) fn make_bipxx_public<K: DerivableKey<$ctx>>(
+                bip: u32,
+                key: K,
+                parent_fingerprint: bip32::Fingerprint,
+                keychain: KeychainKind,
+            ) -> Result<impl IntoDescriptorKey<$ctx>, DescriptorError> {
+                make_account_public(&[bip, 0, 0], key, parent_fingerprint, keychain)
+            }
+
+            // `account` is the list of hardened steps that lead to the account key
+            pub(super) fn make_account_private<K: DerivableKey<$ctx>>(
+                account: &[u32],
+                key: K,
+                keychain: KeychainKind,
+            ) -> Result<impl IntoDescriptorKey<$ctx>, DescriptorError> {
+                let mut derivation_path = Vec::with_capacity(account.len() + 1);
+                for index in account {
+                    derivation_path.push(bip32::ChildNumber::from_hardened_idx(*index)?);
+                }
 
                 match keychain {
                     KeychainKind::External => {
@@ -425,8 +822,8 @@ macro_rules! expand_make_bipxx {
 
                 Ok((key, derivation_path))
             }
-            pub(super) fn make_bipxx_public<K: DerivableKey<$ctx>>(
-                bip: u32,
+            pub(super) fn make_account_public<K: DerivableKey<$ctx>>(
+                account: &[u32],
                 key: K,
                 parent_fingerprint: bip32::Fingerprint,
                 keychain: KeychainKind,
@@ -436,11 +833,11 @@ macro_rules! expand_make_bipxx {
                     KeychainKind::Internal => vec![bip32::ChildNumber::from_normal_idx(1)?].into(),
                 };
 
-                let source_path = bip32::DerivationPath::from(vec![
-                    bip32::ChildNumber::from_hardened_idx(bip)?,
-                    bip32::ChildNumber::from_hardened_idx(0)?,
-                    bip32::ChildNumber::from_hardened_idx(0)?,
-                ]);
+                let source_path = account
+                    .iter()
+                    .map(|index| bip32::ChildNumber::from_hardened_idx(*index))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let source_path = bip32::DerivationPath::from(source_path);
 
                 Ok((key, (parent_fingerprint, source_path), derivation_path))
             }
@@ -723,4 +1120,130 @@ mod test {
             ],
         );
     }
+
+    // P2WSH `wsh(sortedmulti(2,key_a,key_b))`
+    #[test]
+    fn test_p2wsh_sortedmulti_template() {
+        let key_a = bitcoin::PublicKey::from_str(
+            "03a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd",
+        )
+        .unwrap();
+        let key_b = bitcoin::PublicKey::from_str(
+            "02e6642fd69bd211f93f7f1f36ca51a26a5290eb2dd1b0d8279a87bb0d480c8443",
+        )
+        .unwrap();
+        check(
+            P2Wsh_SortedMulti(2, vec![key_a, key_b]).build(),
+            true,
+            true,
+            &["bcrt1q44u7wlzems4dj90hz99xeq0a2fxx7fwpj4dhsrtn4cupjpmjvj9qfugz9f"],
+        );
+        // the order of the keys doesn't matter
+        check(
+            P2Wsh_P2Sh_SortedMulti(2, vec![key_b, key_a]).build(),
+            true,
+            true,
+            &["2N4ShiusuyTn2cJ6bZgmTCzNDkK3ekQzn2N"],
+        );
+    }
+
+    // the private templates must expand to the same descriptors as the public ones
+    fn check_multisig_public<P, Q>(private: P, public: Q)
+    where
+        P: DescriptorTemplate,
+        Q: DescriptorTemplate,
+    {
+        let (private, _, _) = private.build().unwrap();
+        let (public, _, _) = public.build().unwrap();
+        assert_eq!(private.to_string(), public.to_string());
+    }
+
+    fn multisig_keys() -> Vec<bitcoin::util::bip32::ExtendedPrivKey> {
+        vec![
+            bitcoin::util::bip32::ExtendedPrivKey::from_str("tprv8ZgxMBicQKsPeZRHk4rTG6orPS2CRNFX3njhUXx5vj9qGog5ZMH4uGReDWN5kCkY3jmWEtWause41CDvBRXD1shKknAMKxT99o9qUTRVC6m").unwrap(),
+            bitcoin::util::bip32::ExtendedPrivKey::from_str("tprv8ZgxMBicQKsPcx5nBGsR63Pe8KnRUqmbJNENAfGftF3yuXoMMoVJJcYeUw5eVkm9WBPjWYt6HMWYJNesB5HaNVBaFc1M6dRjWSYnmewUMYy").unwrap(),
+        ]
+    }
+
+    fn multisig_public_keys(
+        account: &str,
+    ) -> Vec<(
+        bitcoin::util::bip32::ExtendedPubKey,
+        bitcoin::util::bip32::Fingerprint,
+    )> {
+        let secp = Secp256k1::new();
+        let account = bitcoin::util::bip32::DerivationPath::from_str(account).unwrap();
+        multisig_keys()
+            .into_iter()
+            .map(|key| {
+                let xprv = key.derive_priv(&secp, &account).unwrap();
+                (
+                    bitcoin::util::bip32::ExtendedPubKey::from_private(&secp, &xprv),
+                    key.fingerprint(&secp),
+                )
+            })
+            .collect()
+    }
+
+    // BIP48 `wsh(sortedmulti(2,key_a/48'/0'/0'/2'/{0,1}/*,key_b/48'/0'/0'/2'/{0,1}/*))`
+    #[test]
+    fn test_bip48_wsh_template() {
+        let (desc, _, _) = Bip48Wsh(2, multisig_keys(), KeychainKind::External)
+            .build()
+            .unwrap();
+        assert_eq!(desc.to_string(), "wsh(sortedmulti(2,[c55b303f/48'/0'/0'/2']tpubDFCUE3UPVoSVk89QRuDAtBiQQc6DuJYCfjssVYMfSWSonybyhBCUGCoXQ112axmHnq7XvFWNpgDyrUHmLm6ghZKVgQpzThnEAn4z11FD144/0/*,[34b00776/48'/0'/0'/2']tpubDFMH5o1PTzwMn2cMa4dmYugyc9xHHcShpqLW2n8Wt7vNxYsz4T8x8X4eSfp3KdjdUVeFLmPQN9LEbTkm63QH3mrwMTYvF9vhXh7cWTD3iAa/0/*))#dg7v0gyw");
+        check(
+            Bip48Wsh(2, multisig_keys(), KeychainKind::External).build(),
+            true,
+            false,
+            &["bcrt1qz9xvqv63w29j37pwmhxag38vdmq5w7jjq07hnxjl52qgcuvfcwvqy7z4c7"],
+        );
+
+        for keychain in &[KeychainKind::External, KeychainKind::Internal] {
+            check_multisig_public(
+                Bip48Wsh(2, multisig_keys(), *keychain),
+                Bip48WshPublic(2, multisig_public_keys("m/48'/0'/0'/2'"), *keychain),
+            );
+        }
+    }
+
+    // BIP48 `sh(wsh(sortedmulti(2,key_a/48'/0'/0'/1'/{0,1}/*,key_b/48'/0'/0'/1'/{0,1}/*)))`
+    #[test]
+    fn test_bip48_sh_wsh_template() {
+        let (desc, _, _) = Bip48ShWsh(2, multisig_keys(), KeychainKind::Internal)
+            .build()
+            .unwrap();
+        assert_eq!(desc.to_string(), "sh(wsh(sortedmulti(2,[c55b303f/48'/0'/0'/1']tpubDFCUE3UPVoSVfM9pChPyhVfGDEphG3aAME3tHdV45ijTZ2SrsBG5vaUNaNKm8oYHiVHAGd5RqKUgP5LnwyTNL5KdgGJwWQBRK8SvyRspEt9/1/*,[34b00776/48'/0'/0'/1']tpubDFMH5o1PTzwMiVw8mkfcTMDXhMH2CNHQkzWv2bJxKcjsZwJsVCVhEXWQepJUStqzBxx8xBL9c1qq5Vgj1TcVqwZJohgg1f4azNkmVkwGno2/1/*)))#8t7mchll");
+        check(
+            Bip48ShWsh(2, multisig_keys(), KeychainKind::External).build(),
+            true,
+            false,
+            &["2NBMTcX4fzBD6TmVLznDddxLqRjvxfbbXRx"],
+        );
+
+        for keychain in &[KeychainKind::External, KeychainKind::Internal] {
+            check_multisig_public(
+                Bip48ShWsh(2, multisig_keys(), *keychain),
+                Bip48ShWshPublic(2, multisig_public_keys("m/48'/0'/0'/1'"), *keychain),
+            );
+        }
+    }
+
+    // BIP87 `wsh(sortedmulti(2,key_a/87'/0'/0'/{0,1}/*,key_b/87'/0'/0'/{0,1}/*))`
+    #[test]
+    fn test_bip87_template() {
+        check(
+            Bip87(2, multisig_keys(), KeychainKind::External).build(),
+            true,
+            false,
+            &["bcrt1qj8xqcnv4jzdtpggn667884z99ky8qux5uwx2pq37nlmkqytxjpgs3vyc0s"],
+        );
+
+        for keychain in &[KeychainKind::External, KeychainKind::Internal] {
+            check_multisig_public(
+                Bip87(2, multisig_keys(), *keychain),
+                Bip87Public(2, multisig_public_keys("m/87'/0'/0'"), *keychain),
+            );
+        }
+    }
 }
@@ -77,6 +77,14 @@ pub enum Error {
     Key(crate::keys::KeyError),
     /// Descriptor checksum mismatch
     ChecksumMismatch,
+    /// The database was written using a format more recent than the one supported by this
+    /// version of the library
+    DatabaseVersionTooNew {
+        /// Version of the format found in the database
+        found: u32,
+        /// Latest version of the format supported
+        supported: u32,
+    },
     /// Spending policy is not compatible with this [`KeychainKind`](crate::types::KeychainKind)
     SpendingPolicyRequired(crate::types::KeychainKind),
     /// Error while extracting and manipulating policies
@@ -121,6 +129,12 @@ pub enum Error {
     Psbt(bitcoin::util::psbt::Error),
     /// Partially signed bitcoin transaction parseerror
     PsbtParse(bitcoin::util::psbt::PsbtParseError),
+    /// Error while combining PSBTs
+    PsbtCombine(crate::psbt::CombineError),
+    /// Multisig coordinator error
+    Coordinator(crate::wallet::coordinator::CoordinatorError),
+    /// Error while parsing or restoring a wallet backup
+    Backup(crate::wallet::backup::BackupError),
 
     //KeyMismatch(bitcoin::secp256k1::PublicKey, bitcoin::secp256k1::PublicKey),
     //MissingInputUTXO(usize),
@@ -141,9 +155,22 @@ pub enum Error {
     #[cfg(feature = "key-value-db")]
     /// Sled database error
     Sled(sled::Error),
+    #[cfg(feature = "sqlite")]
+    /// SQLite database error
+    Rusqlite(rusqlite::Error),
+    #[cfg(feature = "encrypted-db")]
+    /// Data stored in an [`EncryptedDatabase`](crate::database::EncryptedDatabase) can't be
+    /// decrypted, either because the key is wrong or because the data is corrupted
+    Encryption,
     #[cfg(feature = "rpc")]
     /// Rpc client error
     Rpc(bitcoincore_rpc::Error),
+    #[cfg(feature = "hwi")]
+    /// Hardware wallet error
+    Hwi(crate::wallet::hwi::HwiError),
+    #[cfg(feature = "payjoin")]
+    /// Payjoin error
+    Payjoin(crate::wallet::payjoin::PayjoinError),
 }
 
 impl fmt::Display for Error {
@@ -191,6 +218,9 @@ impl_error!(serde_json::Error, Json);
 impl_error!(bitcoin::hashes::hex::Error, Hex);
 impl_error!(bitcoin::util::psbt::Error, Psbt);
 impl_error!(bitcoin::util::psbt::PsbtParseError, PsbtParse);
+impl_error!(crate::psbt::CombineError, PsbtCombine);
+impl_error!(crate::wallet::coordinator::CoordinatorError, Coordinator);
+impl_error!(crate::wallet::backup::BackupError, Backup);
 
 #[cfg(feature = "electrum")]
 impl_error!(electrum_client::Error, Electrum);
@@ -198,8 +228,14 @@ impl_error!(electrum_client::Error, Electrum);
 impl_error!(crate::blockchain::esplora::EsploraError, Esplora);
 #[cfg(feature = "key-value-db")]
 impl_error!(sled::Error, Sled);
+#[cfg(feature = "sqlite")]
+impl_error!(rusqlite::Error, Rusqlite);
 #[cfg(feature = "rpc")]
 impl_error!(bitcoincore_rpc::Error, Rpc);
+#[cfg(feature = "hwi")]
+impl_error!(crate::wallet::hwi::HwiError, Hwi);
+#[cfg(feature = "payjoin")]
+impl_error!(crate::wallet::payjoin::PayjoinError, Payjoin);
 
 #[cfg(feature = "compact_filters")]
 impl From<crate::blockchain::compact_filters::CompactFiltersError> for Error {
@@ -187,6 +187,9 @@ fn main() -> Result<(), bdk::Error> {
 //! * `all-keys`: all features for working with bitcoin keys
 //! * `async-interface`: async functions in bdk traits
 //! * `keys-bip39`: [BIP-39](https://github.com/bitcoin/bips/blob/master/bip-0039.mediawiki) mnemonic codes for generating deterministic keys
+//! * `hwi`: [`hwi`](crate::wallet::hwi) signer and address validator for hardware wallets, through the [HWI](https://github.com/bitcoin-core/HWI) command line interface
+//! * `remote-signer`: [`remote_signer`](crate::wallet::remote_signer) that sends the PSBTs to a signing server over TCP or a Unix socket
+//! * `payjoin`: [BIP-78](https://github.com/bitcoin/bips/blob/master/bip-0078.mediawiki) [`payjoin`](crate::wallet::payjoin) sender and receiver
 //!
 //! ## Internal features
 //!
@@ -197,6 +200,8 @@ fn main() -> Result<(), bdk::Error> {
 //! * `electrum`: [`electrum`](crate::blockchain::electrum) client protocol for interacting with electrum servers
 //! * `esplora`: [`esplora`](crate::blockchain::esplora) client protocol for interacting with blockstream [electrs](https://github.com/Blockstream/electrs) servers
 //! * `key-value-db`: key value [`database`](crate::database) based on [`sled`](crate::sled) for caching blockchain data
+//! * `sqlite`: [`database`](crate::database) based on [`rusqlite`](crate::rusqlite) for caching blockchain data in a single SQLite file
+//! * `encrypted-db`: [`EncryptedDatabase`](crate::database::EncryptedDatabase) wrapper that encrypts the data stored by any other database
 
 pub extern crate bitcoin;
 extern crate log;
@@ -234,6 +239,9 @@ pub extern crate reqwest;
 #[cfg(feature = "key-value-db")]
 pub extern crate sled;
 
+#[cfg(feature = "sqlite")]
+pub extern crate rusqlite;
+
 #[allow(unused_imports)]
 #[cfg(test)]
 #[allow(unused_imports)]
@@ -249,7 +257,7 @@ pub mod descriptor;
 #[cfg(feature = "test-md-docs")]
 mod doctest;
 pub mod keys;
-pub(crate) mod psbt;
+pub mod psbt;
 pub(crate) mod types;
 pub mod wallet;
 
@@ -9,11 +9,68 @@
 // You may not use this file except in accordance with one or both of these
 // licenses.
 
+//! PSBT utilities
+//!
+//! This module contains the [`PsbtUtils`] trait, that extends [`Psbt`] with methods to compute
+//! its fee, combine the PSBTs returned by multiple cosigners and detect the fields they disagree
+//! on, and the types returned by [`Wallet::psbt_summary`](crate::Wallet::psbt_summary) and
+//! [`Wallet::psbt_missing_signatures`](crate::Wallet::psbt_missing_signatures).
+//!
+//! ## Example
+//!
+//! ```no_run
+//! # use std::str::FromStr;
+//! # use bdk::bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
+//! # use bdk::psbt::PsbtUtils;
+//! let mut psbt = Psbt::from_str("...")?;
+//! let cosigner_psbt = Psbt::from_str("...")?;
+//!
+//! psbt.combine(cosigner_psbt)?;
+//! println!("Fee: {:?} sat, fee rate: {:?}", psbt.fee_amount(), psbt.fee_rate());
+//! # Ok::<(), bdk::Error>(())
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+
 use bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
-use bitcoin::TxOut;
+use bitcoin::util::psbt::{self, Input, Output};
+use bitcoin::{Address, OutPoint, Script, TxOut};
 
+use crate::descriptor::policy::Satisfaction;
+use crate::descriptor::HdKeyPaths;
+use crate::types::{FeeRate, KeychainKind};
+
+/// Trait to add utility methods to [`Psbt`]
 pub trait PsbtUtils {
+    /// Return the previous output spent by the input at `input_index`, if it's available in the
+    /// `witness_utxo` or `non_witness_utxo` fields
     fn get_utxo_for(&self, input_index: usize) -> Option<TxOut>;
+
+    /// Return the fee paid by the transaction, in satoshi
+    ///
+    /// Returns `None` if the previous output of any input is missing.
+    fn fee_amount(&self) -> Option<u64>;
+
+    /// Return the fee rate of the transaction
+    ///
+    /// The size of the transaction is computed including the `scriptSig`s and witnesses of the
+    /// inputs that have already been finalized, so the value is exact only once every input is
+    /// finalized and is an overestimate before that.
+    ///
+    /// Returns `None` if the previous output of any input is missing.
+    fn fee_rate(&self) -> Option<FeeRate>;
+
+    /// Return the fields that have different values in `self` and `other`
+    ///
+    /// Both PSBTs are expected to spend the same unsigned transaction.
+    fn conflicts(&self, other: &Psbt) -> Vec<PsbtConflict>;
+
+    /// Combine `other` into `self`, as the "Combiner" role described in BIP 174
+    ///
+    /// Unlike [`Psbt::merge`], which picks arbitrarily when the two PSBTs contain different values
+    /// for the same key, this fails with [`CombineError::Conflicts`] and leaves `self` untouched.
+    fn combine(&mut self, other: Psbt) -> Result<(), CombineError>;
 }
 
 impl PsbtUtils for Psbt {
@@ -37,12 +94,361 @@ impl PsbtUtils for Psbt {
             None
         }
     }
+
+    fn fee_amount(&self) -> Option<u64> {
+        let tx = &self.global.unsigned_tx;
+        let input_amount = (0..tx.input.len())
+            .map(|n| self.get_utxo_for(n).map(|txout| txout.value))
+            .sum::<Option<u64>>()?;
+        let output_amount = tx.output.iter().map(|txout| txout.value).sum::<u64>();
+
+        input_amount.checked_sub(output_amount)
+    }
+
+    fn fee_rate(&self) -> Option<FeeRate> {
+        let fee_amount = self.fee_amount()?;
+
+        let mut tx = self.global.unsigned_tx.clone();
+        for (txin, input) in tx.input.iter_mut().zip(self.inputs.iter()) {
+            if let Some(script_sig) = &input.final_script_sig {
+                txin.script_sig = script_sig.clone();
+            }
+            if let Some(witness) = &input.final_script_witness {
+                txin.witness = witness.clone();
+            }
+        }
+        let vbytes = (tx.get_weight() as f32 / 4.0).ceil();
+
+        Some(FeeRate::from_sat_per_vb(fee_amount as f32 / vbytes))
+    }
+
+    fn conflicts(&self, other: &Psbt) -> Vec<PsbtConflict> {
+        let inputs = self
+            .inputs
+            .iter()
+            .zip(other.inputs.iter())
+            .enumerate()
+            .flat_map(|(index, (a, b))| {
+                input_conflicts(a, b)
+                    .into_iter()
+                    .map(move |field| PsbtConflict::Input { index, field })
+            });
+        let outputs = self
+            .outputs
+            .iter()
+            .zip(other.outputs.iter())
+            .enumerate()
+            .flat_map(|(index, (a, b))| {
+                output_conflicts(a, b)
+                    .into_iter()
+                    .map(move |field| PsbtConflict::Output { index, field })
+            });
+
+        inputs.chain(outputs).collect()
+    }
+
+    fn combine(&mut self, other: Psbt) -> Result<(), CombineError> {
+        if self.global.unsigned_tx != other.global.unsigned_tx {
+            return Err(CombineError::DifferentTransaction);
+        }
+
+        let conflicts = self.conflicts(&other);
+        if !conflicts.is_empty() {
+            return Err(CombineError::Conflicts(conflicts));
+        }
+
+        // `merge` can still fail on the global xpubs, so we work on a copy to avoid leaving
+        // `self` half-merged
+        let mut combined = self.clone();
+        combined.merge(other)?;
+        *self = combined;
+
+        Ok(())
+    }
+}
+
+fn option_conflict<T: PartialEq>(a: &Option<T>, b: &Option<T>) -> bool {
+    matches!((a, b), (Some(a), Some(b)) if a != b)
+}
+
+fn map_conflict<K: Ord, V: PartialEq>(a: &BTreeMap<K, V>, b: &BTreeMap<K, V>) -> bool {
+    b.iter()
+        .any(|(key, value)| matches!(a.get(key), Some(other) if other != value))
+}
+
+fn input_conflicts(a: &Input, b: &Input) -> Vec<&'static str> {
+    let checks = [
+        (
+            "non_witness_utxo",
+            option_conflict(&a.non_witness_utxo, &b.non_witness_utxo),
+        ),
+        (
+            "witness_utxo",
+            option_conflict(&a.witness_utxo, &b.witness_utxo),
+        ),
+        (
+            "partial_sigs",
+            map_conflict(&a.partial_sigs, &b.partial_sigs),
+        ),
+        (
+            "sighash_type",
+            option_conflict(&a.sighash_type, &b.sighash_type),
+        ),
+        (
+            "redeem_script",
+            option_conflict(&a.redeem_script, &b.redeem_script),
+        ),
+        (
+            "witness_script",
+            option_conflict(&a.witness_script, &b.witness_script),
+        ),
+        (
+            "bip32_derivation",
+            map_conflict(&a.bip32_derivation, &b.bip32_derivation),
+        ),
+        (
+            "final_script_sig",
+            option_conflict(&a.final_script_sig, &b.final_script_sig),
+        ),
+        (
+            "final_script_witness",
+            option_conflict(&a.final_script_witness, &b.final_script_witness),
+        ),
+    ];
+
+    checks
+        .iter()
+        .filter(|(_, conflict)| *conflict)
+        .map(|(field, _)| *field)
+        .collect()
+}
+
+fn output_conflicts(a: &Output, b: &Output) -> Vec<&'static str> {
+    let checks = [
+        (
+            "redeem_script",
+            option_conflict(&a.redeem_script, &b.redeem_script),
+        ),
+        (
+            "witness_script",
+            option_conflict(&a.witness_script, &b.witness_script),
+        ),
+        (
+            "bip32_derivation",
+            map_conflict(&a.bip32_derivation, &b.bip32_derivation),
+        ),
+    ];
+
+    checks
+        .iter()
+        .filter(|(_, conflict)| *conflict)
+        .map(|(field, _)| *field)
+        .collect()
+}
+
+/// A field that has different values in two PSBTs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PsbtConflict {
+    /// Conflict in an input map
+    Input {
+        /// Index of the input
+        index: usize,
+        /// Name of the field, as in [`psbt::Input`]
+        field: &'static str,
+    },
+    /// Conflict in an output map
+    Output {
+        /// Index of the output
+        index: usize,
+        /// Name of the field, as in [`psbt::Output`]
+        field: &'static str,
+    },
+}
+
+impl fmt::Display for PsbtConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PsbtConflict::Input { index, field } => write!(f, "input #{} {}", index, field),
+            PsbtConflict::Output { index, field } => write!(f, "output #{} {}", index, field),
+        }
+    }
+}
+
+/// Errors returned by [`PsbtUtils::combine`]
+#[derive(Debug)]
+pub enum CombineError {
+    /// The PSBTs spend different unsigned transactions
+    DifferentTransaction,
+    /// The PSBTs have different values for the same fields
+    Conflicts(Vec<PsbtConflict>),
+    /// Error while merging the PSBTs
+    Psbt(psbt::Error),
+}
+
+impl fmt::Display for CombineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CombineError::DifferentTransaction => {
+                write!(f, "The PSBTs spend different transactions")
+            }
+            CombineError::Conflicts(conflicts) => {
+                write!(f, "Conflicting fields: ")?;
+                for (i, conflict) in conflicts.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", conflict)?;
+                }
+                Ok(())
+            }
+            CombineError::Psbt(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for CombineError {}
+
+impl_error!(psbt::Error, Psbt, CombineError);
+
+/// Who owns an input or output of a PSBT, as returned by
+/// [`Wallet::psbt_summary`](crate::Wallet::psbt_summary)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ownership {
+    /// Owned by the external keychain of the wallet
+    Ours,
+    /// Owned by the internal keychain of the wallet
+    Change,
+    /// Not owned by the wallet
+    External,
+}
+
+impl fmt::Display for Ownership {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Ownership::Ours => write!(f, "ours"),
+            Ownership::Change => write!(f, "change"),
+            Ownership::External => write!(f, "external"),
+        }
+    }
+}
+
+impl From<Option<KeychainKind>> for Ownership {
+    fn from(keychain: Option<KeychainKind>) -> Self {
+        match keychain {
+            Some(KeychainKind::External) => Ownership::Ours,
+            Some(KeychainKind::Internal) => Ownership::Change,
+            None => Ownership::External,
+        }
+    }
+}
+
+/// An input in a [`PsbtSummary`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputSummary {
+    /// The output spent by the input
+    pub previous_output: OutPoint,
+    /// Value of the spent output, if available in the PSBT
+    pub value: Option<u64>,
+    /// Address of the spent output, if available in the PSBT and if it can be represented as an
+    /// address
+    pub address: Option<Address>,
+    /// Who owns the spent output
+    pub ownership: Ownership,
+    /// Whether the input has been finalized
+    pub is_final: bool,
+}
+
+/// An output in a [`PsbtSummary`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputSummary {
+    /// Value of the output
+    pub value: u64,
+    /// Script of the output
+    pub script_pubkey: Script,
+    /// Address of the output, if it can be represented as an address
+    pub address: Option<Address>,
+    /// Who owns the output
+    pub ownership: Ownership,
+}
+
+/// Summary of the inputs and outputs of a PSBT, returned by
+/// [`Wallet::psbt_summary`](crate::Wallet::psbt_summary)
+///
+/// Its [`Display`](fmt::Display) implementation prints a human-readable description of the
+/// transaction, that can be shown to the user before signing it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PsbtSummary {
+    /// Inputs of the transaction
+    pub inputs: Vec<InputSummary>,
+    /// Outputs of the transaction
+    pub outputs: Vec<OutputSummary>,
+    /// Fee paid by the transaction, see [`PsbtUtils::fee_amount`]
+    pub fee: Option<u64>,
+    /// Fee rate of the transaction, see [`PsbtUtils::fee_rate`]
+    pub fee_rate: Option<FeeRate>,
+}
+
+impl fmt::Display for PsbtSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Inputs:")?;
+        for input in &self.inputs {
+            write!(f, "  {} ", input.previous_output)?;
+            match input.value {
+                Some(value) => write!(f, "{} sat", value)?,
+                None => write!(f, "unknown value")?,
+            }
+            if let Some(address) = &input.address {
+                write!(f, " {}", address)?;
+            }
+            write!(f, " ({})", input.ownership)?;
+            if input.is_final {
+                write!(f, " [final]")?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f, "Outputs:")?;
+        for output in &self.outputs {
+            write!(f, "  {} sat ", output.value)?;
+            match &output.address {
+                Some(address) => write!(f, "{}", address)?,
+                None => write!(f, "{}", output.script_pubkey)?,
+            }
+            writeln!(f, " ({})", output.ownership)?;
+        }
+
+        match (self.fee, self.fee_rate) {
+            (Some(fee), Some(fee_rate)) => {
+                write!(f, "Fee: {} sat ({:.2} sat/vB)", fee, fee_rate.as_sat_vb())
+            }
+            _ => write!(f, "Fee: unknown"),
+        }
+    }
+}
+
+/// An input of a PSBT that still needs to be signed, as returned by
+/// [`Wallet::psbt_missing_signatures`](crate::Wallet::psbt_missing_signatures)
+#[derive(Debug, Clone)]
+pub struct MissingSignatures {
+    /// Index of the input
+    pub input_index: usize,
+    /// Keychain of the wallet that owns the input
+    pub keychain: KeychainKind,
+    /// Satisfaction of the spending policy given the signatures already in the input
+    pub satisfaction: Satisfaction,
+    /// Keys that haven't signed the input yet, with their fingerprint and derivation path
+    ///
+    /// Depending on the policy not all of them are required: for example in a 2-of-3 multisig
+    /// with one signature, any of the two remaining keys is enough.
+    pub missing_keys: HdKeyPaths,
 }
 
 #[cfg(test)]
 mod test {
-    use crate::bitcoin::TxIn;
-    use crate::psbt::Psbt;
+    use crate::bitcoin::{Address, TxIn};
+    use crate::descriptor::policy::Satisfaction;
+    use crate::psbt::*;
+    use crate::types::{FeeRate, KeychainKind};
     use crate::wallet::test::{get_funded_wallet, get_test_wpkh};
     use crate::wallet::AddressIndex;
     use crate::SignOptions;
@@ -119,4 +525,135 @@ mod test {
 
         let _ = wallet.sign(&mut psbt, SignOptions::default()).unwrap();
     }
+
+    #[test]
+    fn test_psbt_fee() {
+        let (wallet, _, _) = get_funded_wallet(get_test_wpkh());
+        let send_to = wallet.get_address(AddressIndex::New).unwrap();
+        let mut builder = wallet.build_tx();
+        builder
+            .add_recipient(send_to.script_pubkey(), 10_000)
+            .fee_rate(FeeRate::from_sat_per_vb(5.0));
+        let (mut psbt, details) = builder.finish().unwrap();
+
+        assert_eq!(psbt.fee_amount(), details.fee);
+        let unsigned_fee_rate = psbt.fee_rate().unwrap();
+        assert!(unsigned_fee_rate.as_sat_vb() > 5.0);
+
+        wallet.sign(&mut psbt, SignOptions::default()).unwrap();
+        let fee_rate = psbt.fee_rate().unwrap();
+        assert!(fee_rate < unsigned_fee_rate);
+        assert!((fee_rate.as_sat_vb() - 5.0).abs() < 0.1);
+
+        psbt.inputs[0].witness_utxo = None;
+        psbt.inputs[0].non_witness_utxo = None;
+        assert_eq!(psbt.fee_amount(), None);
+        assert_eq!(psbt.fee_rate(), None);
+    }
+
+    #[test]
+    fn test_psbt_combine() {
+        let (wallet, _, _) = get_funded_wallet("wsh(multi(2,tprv8ZgxMBicQKsPd3EupYiPRhaMooHKUHJxNsTfYuScep13go8QFfHdtkG9nRkFGb7busX4isf6X9dURGCoKgitaApQ6MupRhZMcELAxTBRJgS/*,tpubD6NzVbkrYhZ4Xferm7Pz4VnjdcDPFyjVu5K4iZXQ4pVN8Cks4pHVowTBXBKRhX64pkRyJZJN5xAKj4UDNnLPb5p2sSKXhewoYx5GbTdUFWq/*))");
+        let send_to = wallet.get_address(AddressIndex::New).unwrap();
+        let mut builder = wallet.build_tx();
+        builder.add_recipient(send_to.script_pubkey(), 10_000);
+        let (mut psbt, _) = builder.finish().unwrap();
+
+        let mut signed = psbt.clone();
+        let finalized = wallet.sign(&mut signed, SignOptions::default()).unwrap();
+        assert!(!finalized);
+        assert!(psbt.conflicts(&signed).is_empty());
+
+        psbt.combine(signed.clone()).unwrap();
+        assert_eq!(psbt.inputs[0].partial_sigs.len(), 1);
+
+        let mut conflicting = signed.clone();
+        for sig in conflicting.inputs[0].partial_sigs.values_mut() {
+            sig.push(0x00);
+        }
+        let expected = vec![PsbtConflict::Input {
+            index: 0,
+            field: "partial_sigs",
+        }];
+        assert_eq!(psbt.conflicts(&conflicting), expected);
+        let before = psbt.clone();
+        assert!(matches!(
+            psbt.combine(conflicting),
+            Err(CombineError::Conflicts(conflicts)) if conflicts == expected
+        ));
+        assert_eq!(psbt, before);
+
+        let mut different = signed;
+        different.global.unsigned_tx.lock_time += 1;
+        assert!(matches!(
+            psbt.combine(different),
+            Err(CombineError::DifferentTransaction)
+        ));
+    }
+
+    #[test]
+    fn test_psbt_summary() {
+        let (wallet, _, _) = get_funded_wallet("wpkh(tprv8ZgxMBicQKsPd3EupYiPRhaMooHKUHJxNsTfYuScep13go8QFfHdtkG9nRkFGb7busX4isf6X9dURGCoKgitaApQ6MupRhZMcELAxTBRJgS/*)");
+        let ours = wallet.get_address(AddressIndex::New).unwrap();
+        let external = Address::from_str("bcrt1q3qtze4ys45tgdvguj66zrk4fu6hq3a3v9pfly5").unwrap();
+        let mut builder = wallet.build_tx();
+        builder
+            .add_recipient(ours.script_pubkey(), 10_000)
+            .add_recipient(external.script_pubkey(), 20_000);
+        let (psbt, details) = builder.finish().unwrap();
+
+        let summary = wallet.psbt_summary(&psbt).unwrap();
+        assert_eq!(summary.inputs.len(), 1);
+        assert_eq!(summary.inputs[0].value, Some(50_000));
+        assert_eq!(summary.inputs[0].ownership, Ownership::Ours);
+        assert!(!summary.inputs[0].is_final);
+        assert_eq!(summary.fee, details.fee);
+
+        let output = |address: &Address| {
+            summary
+                .outputs
+                .iter()
+                .find(|o| o.address.as_ref() == Some(address))
+                .unwrap()
+        };
+        assert_eq!(output(&ours).ownership, Ownership::Ours);
+        assert_eq!(output(&ours).value, 10_000);
+        assert_eq!(output(&external).ownership, Ownership::External);
+        assert_eq!(output(&external).value, 20_000);
+
+        let text = summary.to_string();
+        assert!(text.contains(&format!("20000 sat {} (external)", external)));
+        assert!(text.contains(&format!("Fee: {} sat", details.fee.unwrap())));
+    }
+
+    #[test]
+    fn test_psbt_missing_signatures() {
+        let (wallet, _, _) = get_funded_wallet("wsh(multi(2,tprv8ZgxMBicQKsPd3EupYiPRhaMooHKUHJxNsTfYuScep13go8QFfHdtkG9nRkFGb7busX4isf6X9dURGCoKgitaApQ6MupRhZMcELAxTBRJgS/*,tpubD6NzVbkrYhZ4Xferm7Pz4VnjdcDPFyjVu5K4iZXQ4pVN8Cks4pHVowTBXBKRhX64pkRyJZJN5xAKj4UDNnLPb5p2sSKXhewoYx5GbTdUFWq/*))");
+        let send_to = wallet.get_address(AddressIndex::New).unwrap();
+        let mut builder = wallet.build_tx();
+        builder.add_recipient(send_to.script_pubkey(), 10_000);
+        let (mut psbt, _) = builder.finish().unwrap();
+
+        let missing = wallet.psbt_missing_signatures(&psbt).unwrap();
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].input_index, 0);
+        assert_eq!(missing[0].keychain, KeychainKind::External);
+        assert_eq!(missing[0].missing_keys.len(), 2);
+
+        wallet.sign(&mut psbt, SignOptions::default()).unwrap();
+        let missing = wallet.psbt_missing_signatures(&psbt).unwrap();
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].missing_keys.len(), 1);
+        assert!(matches!(
+            &missing[0].satisfaction,
+            Satisfaction::Partial { n: 2, m: 2, items, .. } if items == &vec![0]
+        ));
+
+        // the other cosigner signs
+        let input = &mut psbt.inputs[0];
+        let (pubkey, _) = missing[0].missing_keys.iter().next().unwrap();
+        let sig = input.partial_sigs.values().next().unwrap().clone();
+        input.partial_sigs.insert(*pubkey, sig);
+        assert!(wallet.psbt_missing_signatures(&psbt).unwrap().is_empty());
+    }
 }
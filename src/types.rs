@@ -12,7 +12,8 @@
 use std::convert::AsRef;
 
 use bitcoin::blockdata::transaction::{OutPoint, Transaction, TxOut};
-use bitcoin::{hash_types::Txid, util::psbt};
+use bitcoin::util::bip32::ExtendedPubKey;
+use bitcoin::{hash_types::Txid, util::psbt, Address, BlockHash, BlockHeader};
 
 use serde::{Deserialize, Serialize};
 
@@ -88,6 +89,10 @@ pub struct LocalUtxo {
     pub txout: TxOut,
     /// Type of keychain
     pub keychain: KeychainKind,
+    /// Whether this output was created by a coinbase transaction, and thus can only be spent
+    /// once it reaches maturity
+    #[serde(default)]
+    pub is_coinbase: bool,
 }
 
 /// A [`Utxo`] with its `satisfaction_weight`.
@@ -174,6 +179,14 @@ pub struct TransactionDetails {
     /// The check is only perfomed when the `verify` feature is enabled.
     #[serde(default = "bool::default")] // default to `false` if not specified
     pub verified: bool,
+    /// Part of the fee paid to bring the unconfirmed ancestors of the transaction up to its fee
+    /// rate
+    ///
+    /// Only set on the details returned when creating a transaction with
+    /// [`TxBuilder::pay_for_unconfirmed_ancestors`](crate::wallet::tx_builder::TxBuilder::pay_for_unconfirmed_ancestors)
+    /// or [`Wallet::build_cpfp`](crate::Wallet::build_cpfp).
+    #[serde(default)]
+    pub ancestors_fee: Option<u64>,
 }
 
 /// Block height and timestamp of the block containing the confirmed transaction
@@ -183,16 +196,183 @@ pub struct ConfirmationTime {
     pub height: u32,
     /// confirmation block timestamp
     pub timestamp: u64,
+    /// confirmation block hash, if known
+    ///
+    /// Used to detect when the block containing the transaction is orphaned by a reorg.
+    #[serde(default)]
+    pub block_hash: Option<BlockHash>,
 }
 
 impl ConfirmationTime {
     /// Returns `Some` `ConfirmationTime` if both `height` and `timestamp` are `Some`
     pub fn new(height: Option<u32>, timestamp: Option<u64>) -> Option<Self> {
         match (height, timestamp) {
-            (Some(height), Some(timestamp)) => Some(ConfirmationTime { height, timestamp }),
+            (Some(height), Some(timestamp)) => Some(ConfirmationTime {
+                height,
+                timestamp,
+                block_hash: None,
+            }),
             _ => None,
         }
     }
+
+    /// Returns the `ConfirmationTime` of a transaction confirmed in the block with header `header`
+    /// at height `height`
+    pub fn from_header(height: u32, header: &BlockHeader) -> Self {
+        ConfirmationTime {
+            height,
+            timestamp: header.time as u64,
+            block_hash: Some(header.block_hash()),
+        }
+    }
+}
+
+/// Balance of a wallet, broken down by the state of the outputs
+///
+/// Only the [`confirmed`](Balance::confirmed) and [`trusted_pending`](Balance::trusted_pending)
+/// parts can be safely considered spendable.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Balance {
+    /// Value of coinbase outputs that haven't reached maturity yet
+    pub immature: u64,
+    /// Value of unconfirmed outputs received from transactions spending only our own outputs,
+    /// usually change
+    pub trusted_pending: u64,
+    /// Value of unconfirmed outputs received from external transactions
+    pub untrusted_pending: u64,
+    /// Value of confirmed outputs
+    pub confirmed: u64,
+}
+
+impl Balance {
+    /// Return the value that can be spent without relying on external unconfirmed transactions
+    ///
+    /// This is the sum of [`confirmed`](Balance::confirmed) and
+    /// [`trusted_pending`](Balance::trusted_pending).
+    pub fn get_spendable(&self) -> u64 {
+        self.confirmed + self.trusted_pending
+    }
+
+    /// Return the total value, including immature and untrusted outputs
+    pub fn get_total(&self) -> u64 {
+        self.confirmed + self.trusted_pending + self.untrusted_pending + self.immature
+    }
+}
+
+impl std::fmt::Display for Balance {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{{ immature: {}, trusted_pending: {}, untrusted_pending: {}, confirmed: {} }}",
+            self.immature, self.trusted_pending, self.untrusted_pending, self.confirmed
+        )
+    }
+}
+
+impl std::ops::Add for Balance {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Balance {
+            immature: self.immature + other.immature,
+            trusted_pending: self.trusted_pending + other.trusted_pending,
+            untrusted_pending: self.untrusted_pending + other.untrusted_pending,
+            confirmed: self.confirmed + other.confirmed,
+        }
+    }
+}
+
+/// Type of the object a [`Label`] refers to
+///
+/// The types are the ones defined in [BIP-329](https://github.com/bitcoin/bips/blob/master/bip-0329.mediawiki).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelType {
+    /// A transaction, referenced by its txid
+    Tx,
+    /// An address
+    Addr,
+    /// A public key, referenced by its hex encoding
+    Pubkey,
+    /// A transaction input, referenced by the outpoint it spends
+    Input,
+    /// A transaction output, referenced by its outpoint
+    Output,
+    /// An extended public key
+    Xpub,
+}
+
+impl LabelType {
+    /// Return the [`LabelType`] as the string used in BIP-329 records
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LabelType::Tx => "tx",
+            LabelType::Addr => "addr",
+            LabelType::Pubkey => "pubkey",
+            LabelType::Input => "input",
+            LabelType::Output => "output",
+            LabelType::Xpub => "xpub",
+        }
+    }
+}
+
+/// A user-defined label attached to a transaction, address, output or key
+///
+/// This struct serializes to a [BIP-329](https://github.com/bitcoin/bips/blob/master/bip-0329.mediawiki)
+/// record. Labels are uniquely identified by their `label_type` and `reference`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Label {
+    /// Type of the object labeled
+    #[serde(rename = "type")]
+    pub label_type: LabelType,
+    /// Reference to the object labeled, like a txid, an address or an outpoint
+    #[serde(rename = "ref")]
+    pub reference: String,
+    /// The label
+    pub label: String,
+    /// Key origin of the descriptor the object belongs to, if known
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub origin: Option<String>,
+    /// Whether the output can be spent, only meaningful for [`LabelType::Output`]
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub spendable: Option<bool>,
+}
+
+impl Label {
+    /// Create a new label given the type and reference of the object labeled
+    pub fn new<R: ToString, L: Into<String>>(
+        label_type: LabelType,
+        reference: R,
+        label: L,
+    ) -> Self {
+        Label {
+            label_type,
+            reference: reference.to_string(),
+            label: label.into(),
+            origin: None,
+            spendable: None,
+        }
+    }
+
+    /// Create a new label for a transaction
+    pub fn tx<L: Into<String>>(txid: &Txid, label: L) -> Self {
+        Label::new(LabelType::Tx, txid, label)
+    }
+
+    /// Create a new label for an address
+    pub fn address<L: Into<String>>(address: &Address, label: L) -> Self {
+        Label::new(LabelType::Addr, address, label)
+    }
+
+    /// Create a new label for a transaction output
+    pub fn output<L: Into<String>>(outpoint: &OutPoint, label: L) -> Self {
+        Label::new(LabelType::Output, outpoint, label)
+    }
+
+    /// Create a new label for an extended public key
+    pub fn xpub<L: Into<String>>(xpub: &ExtendedPubKey, label: L) -> Self {
+        Label::new(LabelType::Xpub, xpub, label)
+    }
 }
 
 #[cfg(test)]
@@ -204,4 +384,29 @@ mod tests {
         const _MY_RATE: FeeRate = FeeRate::from_sat_per_vb(10.0);
         const _MIN_RELAY: FeeRate = FeeRate::default_min_relay_fee();
     }
+
+    #[test]
+    fn test_label_bip329_serialization() {
+        let label = Label::new(
+            LabelType::Output,
+            "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd:0",
+            "Change",
+        );
+        assert_eq!(
+            serde_json::to_string(&label).unwrap(),
+            r#"{"type":"output","ref":"f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd:0","label":"Change"}"#
+        );
+
+        let parsed: Label = serde_json::from_str(
+            r#"{ "type": "output", "ref": "f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd:0", "label": "Change", "spendable": false }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            parsed,
+            Label {
+                spendable: Some(false),
+                ..label
+            }
+        );
+    }
 }
@@ -24,6 +24,9 @@
 //! address) all the attached validators will be polled, in sequence. All of them must complete
 //! successfully to continue.
 //!
+//! With the `hwi` feature enabled, the `HwiAddressValidator` defined in the `wallet::hwi` module
+//! shows the addresses on a hardware wallet through HWI.
+//!
 //! ## Example
 //!
 //! ```
@@ -0,0 +1,322 @@
+// Bitcoin Dev Kit
+// Written in 2021 by the Bitcoin Dev Kit Developers
+//
+// Copyright (c) 2020-2021 Bitcoin Dev Kit Developers
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! Wallet backups
+//!
+//! This module contains the [`WalletBackup`], a full backup of a [`Wallet`](crate::Wallet):
+//! unlike the [`WalletExport`](crate::wallet::export::WalletExport), that only contains the
+//! descriptor, it also stores the last derivation index of each keychain, the labels and
+//! optionally the transaction history, so that a restored wallet doesn't need a full rescan with
+//! a large stop gap to find its addresses again.
+//!
+//! The backup is serialized as a versioned JSON object, with a checksum that is verified when it's
+//! parsed. **The backup contains the private keys of the wallet, if any.**
+//!
+//! ## Example
+//!
+//! ```
+//! # use std::str::FromStr;
+//! # use bitcoin::*;
+//! # use bdk::database::*;
+//! # use bdk::wallet::backup::WalletBackup;
+//! # use bdk::*;
+//! # let wallet = doctest_wallet!();
+//! let backup = wallet.backup(true)?.to_string();
+//!
+//! // ... later, on a different device
+//! let backup = WalletBackup::from_str(&backup)?;
+//! let restored = Wallet::new_offline(
+//!     &backup.descriptor,
+//!     backup.change_descriptor.as_ref(),
+//!     backup.network,
+//!     MemoryDatabase::default(),
+//! )?;
+//! restored.restore_from_backup(&backup)?;
+//! # Ok::<_, Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::Network;
+
+use crate::types::{KeychainKind, Label, TransactionDetails};
+
+/// Version of the backup format written by this version of the library
+pub const BACKUP_VERSION: u32 = 1;
+
+/// Errors while parsing or restoring a [`WalletBackup`]
+#[derive(Debug)]
+pub enum BackupError {
+    /// The backup was written with a newer, unsupported version of the format
+    UnsupportedVersion(u32),
+    /// The checksum of the backup is missing or doesn't match its content
+    InvalidChecksum,
+    /// The descriptor of a keychain in the backup is not the one of the wallet being restored
+    DescriptorMismatch(KeychainKind),
+    /// The backup is for a different network than the wallet being restored
+    NetworkMismatch {
+        /// Network of the backup
+        backup: Network,
+        /// Network of the wallet
+        wallet: Network,
+    },
+    /// Error while parsing or serializing JSON
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BackupError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported backup version {}", version)
+            }
+            BackupError::InvalidChecksum => write!(f, "Invalid backup checksum"),
+            BackupError::DescriptorMismatch(keychain) => {
+                write!(f, "The {:?} descriptor doesn't match the backup", keychain)
+            }
+            BackupError::NetworkMismatch { backup, wallet } => write!(
+                f,
+                "The backup is for {} but the wallet is for {}",
+                backup, wallet
+            ),
+            BackupError::Json(e) => write!(f, "JSON error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+impl_error!(serde_json::Error, Json, BackupError);
+
+/// A full backup of a [`Wallet`](crate::Wallet)
+///
+/// A backup is created with [`Wallet::backup`](crate::Wallet::backup) and restored with
+/// [`Wallet::restore_from_backup`](crate::Wallet::restore_from_backup). For a usage example see
+/// [this module](crate::wallet::backup)'s documentation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalletBackup {
+    /// Version of the backup format
+    pub version: u32,
+    /// Network of the wallet
+    pub network: Network,
+    /// External descriptor, including the private keys if the wallet has them
+    pub descriptor: String,
+    /// Internal descriptor, including the private keys if the wallet has them
+    pub change_descriptor: Option<String>,
+    /// Last derivation index used in the external keychain
+    pub last_external_index: Option<u32>,
+    /// Last derivation index used in the internal keychain
+    pub last_internal_index: Option<u32>,
+    /// Labels stored in the wallet
+    pub labels: Vec<Label>,
+    /// Transaction history, including the raw transactions, if it was included in the backup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transactions: Option<Vec<TransactionDetails>>,
+}
+
+impl WalletBackup {
+    /// Return the last derivation index used in a keychain
+    pub fn last_index(&self, keychain: KeychainKind) -> Option<u32> {
+        match keychain {
+            KeychainKind::External => self.last_external_index,
+            KeychainKind::Internal => self.last_internal_index,
+        }
+    }
+}
+
+// Hash of the canonical serialization of the backup, without the checksum itself
+fn checksum(content: &Value) -> String {
+    sha256::Hash::hash(content.to_string().as_bytes()).to_string()
+}
+
+impl fmt::Display for WalletBackup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut content = serde_json::to_value(self).map_err(|_| fmt::Error)?;
+        let checksum = checksum(&content);
+        content["checksum"] = checksum.into();
+
+        write!(f, "{}", content)
+    }
+}
+
+impl FromStr for WalletBackup {
+    type Err = BackupError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut content: Value = serde_json::from_str(s)?;
+
+        // check the version first, newer versions may compute the checksum differently
+        let version = content
+            .get("version")
+            .and_then(Value::as_u64)
+            .unwrap_or_default() as u32;
+        if version == 0 || version > BACKUP_VERSION {
+            return Err(BackupError::UnsupportedVersion(version));
+        }
+
+        let expected = content
+            .as_object_mut()
+            .and_then(|map| map.remove("checksum"))
+            .ok_or(BackupError::InvalidChecksum)?;
+        if expected.as_str() != Some(&checksum(&content)) {
+            return Err(BackupError::InvalidChecksum);
+        }
+
+        Ok(serde_json::from_value(content)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::database::{BatchOperations, Database, MemoryDatabase};
+    use crate::wallet::test::get_funded_wallet;
+    use crate::wallet::{AddressIndex, Wallet};
+    use crate::Error;
+
+    const DESCRIPTOR: &str = "wpkh(tprv8ZgxMBicQKsPd3EupYiPRhaMooHKUHJxNsTfYuScep13go8QFfHdtkG9nRkFGb7busX4isf6X9dURGCoKgitaApQ6MupRhZMcELAxTBRJgS/0/*)";
+
+    fn restored_wallet(backup: &WalletBackup) -> Wallet<(), MemoryDatabase> {
+        Wallet::new_offline(
+            &backup.descriptor,
+            backup.change_descriptor.as_ref(),
+            backup.network,
+            MemoryDatabase::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_backup_roundtrip() {
+        let (wallet, _, txid) = get_funded_wallet(DESCRIPTOR);
+        wallet.get_address(AddressIndex::New).unwrap();
+        wallet.get_address(AddressIndex::New).unwrap();
+        wallet.set_label(Label::tx(&txid, "Salary")).unwrap();
+
+        let backup = wallet.backup(true).unwrap();
+        assert_eq!(backup.version, BACKUP_VERSION);
+        assert!(backup.descriptor.starts_with(&format!("{}#", DESCRIPTOR)));
+        assert_eq!(backup.last_index(KeychainKind::External), Some(2));
+        assert_eq!(backup.transactions.as_ref().unwrap().len(), 1);
+
+        let parsed = WalletBackup::from_str(&backup.to_string()).unwrap();
+        assert_eq!(parsed, backup);
+
+        let restored = restored_wallet(&parsed);
+        restored.restore_from_backup(&parsed).unwrap();
+        assert_eq!(
+            restored.get_address(AddressIndex::New).unwrap().index,
+            wallet.get_address(AddressIndex::New).unwrap().index
+        );
+        assert_eq!(
+            restored.list_labels().unwrap(),
+            wallet.list_labels().unwrap()
+        );
+        assert_eq!(
+            restored.list_transactions(true).unwrap(),
+            wallet.list_transactions(true).unwrap()
+        );
+        assert_eq!(
+            restored.list_unspent().unwrap(),
+            wallet.list_unspent().unwrap()
+        );
+        assert_eq!(restored.get_balance().unwrap().confirmed, 50_000);
+    }
+
+    #[test]
+    fn test_backup_without_transactions() {
+        let (wallet, _, _) = get_funded_wallet(DESCRIPTOR);
+
+        let backup = wallet.backup(false).unwrap();
+        assert_eq!(backup.transactions, None);
+        assert!(!backup.to_string().contains("transactions"));
+
+        let restored = restored_wallet(&backup);
+        restored.restore_from_backup(&backup).unwrap();
+        assert!(restored.list_transactions(false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_backup_keeps_higher_index() {
+        let (wallet, _, _) = get_funded_wallet(DESCRIPTOR);
+        let backup = wallet.backup(false).unwrap();
+
+        let restored = restored_wallet(&backup);
+        restored
+            .database
+            .borrow_mut()
+            .set_last_index(KeychainKind::External, 10)
+            .unwrap();
+        restored.restore_from_backup(&backup).unwrap();
+        assert_eq!(
+            restored
+                .database
+                .borrow()
+                .get_last_index(KeychainKind::External)
+                .unwrap(),
+            Some(10)
+        );
+    }
+
+    #[test]
+    fn test_backup_invalid_checksum() {
+        let (wallet, _, _) = get_funded_wallet(DESCRIPTOR);
+        let backup = wallet.backup(false).unwrap().to_string();
+
+        let tampered = backup.replace("\"labels\":[]", "\"labels\":[{\"type\":\"tx\",\"ref\":\"f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd\",\"label\":\"x\"}]");
+        assert_ne!(tampered, backup);
+        assert!(matches!(
+            WalletBackup::from_str(&tampered),
+            Err(BackupError::InvalidChecksum)
+        ));
+
+        let mut content: Value = serde_json::from_str(&backup).unwrap();
+        content.as_object_mut().unwrap().remove("checksum");
+        assert!(matches!(
+            WalletBackup::from_str(&content.to_string()),
+            Err(BackupError::InvalidChecksum)
+        ));
+    }
+
+    #[test]
+    fn test_backup_unsupported_version() {
+        let (wallet, _, _) = get_funded_wallet(DESCRIPTOR);
+        let mut content: Value =
+            serde_json::from_str(&wallet.backup(false).unwrap().to_string()).unwrap();
+        content["version"] = (BACKUP_VERSION + 1).into();
+
+        assert!(matches!(
+            WalletBackup::from_str(&content.to_string()),
+            Err(BackupError::UnsupportedVersion(v)) if v == BACKUP_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn test_restore_different_wallet() {
+        let (wallet, _, _) = get_funded_wallet(DESCRIPTOR);
+        let backup = wallet.backup(false).unwrap();
+
+        let (other, _, _) = get_funded_wallet(crate::wallet::test::get_test_wpkh());
+        assert!(matches!(
+            other.restore_from_backup(&backup),
+            Err(Error::Backup(BackupError::DescriptorMismatch(
+                KeychainKind::External
+            )))
+        ));
+    }
+}
@@ -90,14 +90,18 @@
 //! ```
 
 use crate::types::FeeRate;
+use crate::wallet::utils::IsDust;
 use crate::{database::Database, WeightedUtxo};
 use crate::{error::Error, Utxo};
 
+use bitcoin::OutPoint;
+
 use rand::seq::SliceRandom;
 #[cfg(not(test))]
 use rand::thread_rng;
 #[cfg(test)]
 use rand::{rngs::StdRng, SeedableRng};
+use std::collections::HashMap;
 use std::convert::TryInto;
 
 /// Default coin selection algorithm used by [`TxBuilder`](super::tx_builder::TxBuilder) if not
@@ -183,10 +187,8 @@ impl<D: Database> CoinSelectionAlgorithm<D> for LargestFirstCoinSelection {
         mut optional_utxos: Vec<WeightedUtxo>,
         fee_rate: FeeRate,
         amount_needed: u64,
-        mut fee_amount: f32,
+        fee_amount: f32,
     ) -> Result<CoinSelectionResult, Error> {
-        let calc_fee_bytes = |wu| (wu as f32) * fee_rate.as_sat_vb() / 4.0;
-
         log::debug!(
             "amount_needed = `{}`, fee_amount = `{}`, fee_rate = `{:?}`",
             amount_needed,
@@ -204,47 +206,102 @@ impl<D: Database> CoinSelectionAlgorithm<D> for LargestFirstCoinSelection {
                 .chain(optional_utxos.into_iter().rev().map(|utxo| (false, utxo)))
         };
 
-        // Keep including inputs until we've got enough.
-        // Store the total input value in selected_amount and the total fee being paid in fee_amount
-        let mut selected_amount = 0;
-        let selected = utxos
-            .scan(
-                (&mut selected_amount, &mut fee_amount),
-                |(selected_amount, fee_amount), (must_use, weighted_utxo)| {
-                    if must_use || **selected_amount < amount_needed + (fee_amount.ceil() as u64) {
-                        **fee_amount +=
-                            calc_fee_bytes(TXIN_BASE_WEIGHT + weighted_utxo.satisfaction_weight);
-                        **selected_amount += weighted_utxo.utxo.txout().value;
-
-                        log::debug!(
-                            "Selected {}, updated fee_amount = `{}`",
-                            weighted_utxo.utxo.outpoint(),
-                            fee_amount
-                        );
-
-                        Some(weighted_utxo.utxo)
-                    } else {
-                        None
-                    }
-                },
-            )
-            .collect::<Vec<_>>();
+        select_sorted_utxos(utxos, fee_rate, amount_needed, fee_amount)
+    }
+}
 
-        let amount_needed_with_fees = amount_needed + (fee_amount.ceil() as u64);
-        if selected_amount < amount_needed_with_fees {
-            return Err(Error::InsufficientFunds {
-                needed: amount_needed_with_fees,
-                available: selected_amount,
-            });
-        }
+/// OldestFirstCoinSelection always picks the utxo with the smallest blockheight to add to the
+/// selected coins next
+///
+/// The confirmation height of the utxos is looked up in the `database`, unconfirmed utxos and
+/// utxos whose transaction is unknown are picked last. This is useful to consolidate old coins,
+/// or to spend the coins in the order they were received.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OldestFirstCoinSelection;
 
-        Ok(CoinSelectionResult {
-            selected,
-            fee_amount,
-        })
+impl<D: Database> CoinSelectionAlgorithm<D> for OldestFirstCoinSelection {
+    fn coin_select(
+        &self,
+        database: &D,
+        required_utxos: Vec<WeightedUtxo>,
+        optional_utxos: Vec<WeightedUtxo>,
+        fee_rate: FeeRate,
+        amount_needed: u64,
+        fee_amount: f32,
+    ) -> Result<CoinSelectionResult, Error> {
+        let mut optional_utxos = optional_utxos
+            .into_iter()
+            .map(|wu| {
+                let height = database
+                    .get_tx(&wu.utxo.outpoint().txid, false)?
+                    .and_then(|details| details.confirmation_time)
+                    .map(|time| time.height)
+                    .unwrap_or(u32::MAX);
+
+                Ok((height, wu))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        optional_utxos.sort_by_key(|(height, _)| *height);
+
+        let utxos = required_utxos
+            .into_iter()
+            .map(|utxo| (true, utxo))
+            .chain(optional_utxos.into_iter().map(|(_, utxo)| (false, utxo)));
+
+        select_sorted_utxos(utxos, fee_rate, amount_needed, fee_amount)
     }
 }
 
+// Select the utxos in the order they are returned by `utxos`, until `amount_needed` and the fees
+// are covered. The utxos flagged as required are always selected.
+fn select_sorted_utxos(
+    utxos: impl Iterator<Item = (bool, WeightedUtxo)>,
+    fee_rate: FeeRate,
+    amount_needed: u64,
+    mut fee_amount: f32,
+) -> Result<CoinSelectionResult, Error> {
+    let calc_fee_bytes = |wu| (wu as f32) * fee_rate.as_sat_vb() / 4.0;
+
+    // Keep including inputs until we've got enough.
+    // Store the total input value in selected_amount and the total fee being paid in fee_amount
+    let mut selected_amount = 0;
+    let selected = utxos
+        .scan(
+            (&mut selected_amount, &mut fee_amount),
+            |(selected_amount, fee_amount), (must_use, weighted_utxo)| {
+                if must_use || **selected_amount < amount_needed + (fee_amount.ceil() as u64) {
+                    **fee_amount +=
+                        calc_fee_bytes(TXIN_BASE_WEIGHT + weighted_utxo.satisfaction_weight);
+                    **selected_amount += weighted_utxo.utxo.txout().value;
+
+                    log::debug!(
+                        "Selected {}, updated fee_amount = `{}`",
+                        weighted_utxo.utxo.outpoint(),
+                        fee_amount
+                    );
+
+                    Some(weighted_utxo.utxo)
+                } else {
+                    None
+                }
+            },
+        )
+        .collect::<Vec<_>>();
+
+    let amount_needed_with_fees = amount_needed + (fee_amount.ceil() as u64);
+    if selected_amount < amount_needed_with_fees {
+        return Err(Error::InsufficientFunds {
+            needed: amount_needed_with_fees,
+            available: selected_amount,
+        });
+    }
+
+    Ok(CoinSelectionResult {
+        selected,
+        fee_amount,
+    })
+}
+
 #[derive(Debug, Clone)]
 // Adds fee information to an UTXO.
 struct OutputGroup {
@@ -482,33 +539,18 @@ impl BranchAndBoundCoinSelection {
     fn single_random_draw(
         &self,
         required_utxos: Vec<OutputGroup>,
-        mut optional_utxos: Vec<OutputGroup>,
+        optional_utxos: Vec<OutputGroup>,
         curr_value: i64,
         actual_target: i64,
         fee_amount: f32,
     ) -> CoinSelectionResult {
-        #[cfg(not(test))]
-        optional_utxos.shuffle(&mut thread_rng());
-        #[cfg(test)]
-        {
-            let seed = [0; 32];
-            let mut rng: StdRng = SeedableRng::from_seed(seed);
-            optional_utxos.shuffle(&mut rng);
-        }
-
-        let selected_utxos = optional_utxos
-            .into_iter()
-            .scan(curr_value, |curr_value, utxo| {
-                if *curr_value >= actual_target {
-                    None
-                } else {
-                    *curr_value += utxo.effective_value;
-                    Some(utxo)
-                }
-            })
-            .collect::<Vec<_>>();
-
-        BranchAndBoundCoinSelection::calculate_cs_result(selected_utxos, required_utxos, fee_amount)
+        single_random_draw(
+            required_utxos,
+            optional_utxos,
+            curr_value,
+            actual_target,
+            fee_amount,
+        )
     }
 
     fn calculate_cs_result(
@@ -530,6 +572,267 @@ impl BranchAndBoundCoinSelection {
     }
 }
 
+// Shuffle the optional utxos and select them until the target is reached
+fn single_random_draw(
+    required_utxos: Vec<OutputGroup>,
+    mut optional_utxos: Vec<OutputGroup>,
+    curr_value: i64,
+    actual_target: i64,
+    fee_amount: f32,
+) -> CoinSelectionResult {
+    #[cfg(not(test))]
+    optional_utxos.shuffle(&mut thread_rng());
+    #[cfg(test)]
+    {
+        let seed = [0; 32];
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+        optional_utxos.shuffle(&mut rng);
+    }
+
+    let selected_utxos = optional_utxos
+        .into_iter()
+        .scan(curr_value, |curr_value, utxo| {
+            if *curr_value >= actual_target {
+                None
+            } else {
+                *curr_value += utxo.effective_value;
+                Some(utxo)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    BranchAndBoundCoinSelection::calculate_cs_result(selected_utxos, required_utxos, fee_amount)
+}
+
+/// Single random draw coin selection
+///
+/// This coin selection algorithm shuffles the available UTXOs and picks them in random order
+/// until the required amount is reached. It's the fallback used by
+/// [`BranchAndBoundCoinSelection`] when no exact match is found, but it can also be used on its
+/// own to avoid the fingerprint of a deterministic selection. UTXOs that cost more in fees than
+/// their value are never selected, unless they are required.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SingleRandomDrawCoinSelection;
+
+impl<D: Database> CoinSelectionAlgorithm<D> for SingleRandomDrawCoinSelection {
+    fn coin_select(
+        &self,
+        _database: &D,
+        required_utxos: Vec<WeightedUtxo>,
+        optional_utxos: Vec<WeightedUtxo>,
+        fee_rate: FeeRate,
+        amount_needed: u64,
+        fee_amount: f32,
+    ) -> Result<CoinSelectionResult, Error> {
+        let required_utxos: Vec<OutputGroup> = required_utxos
+            .into_iter()
+            .map(|u| OutputGroup::new(u, fee_rate))
+            .collect();
+        let optional_utxos: Vec<OutputGroup> = optional_utxos
+            .into_iter()
+            .map(|u| OutputGroup::new(u, fee_rate))
+            .filter(|u| u.effective_value > 0)
+            .collect();
+
+        let curr_value: i64 = required_utxos.iter().map(|u| u.effective_value).sum();
+        let available_value: i64 = optional_utxos.iter().map(|u| u.effective_value).sum();
+        let actual_target = fee_amount.ceil() as u64 + amount_needed;
+
+        let available = (curr_value + available_value).max(0) as u64;
+        if available < actual_target {
+            return Err(Error::InsufficientFunds {
+                needed: actual_target,
+                available,
+            });
+        }
+
+        Ok(single_random_draw(
+            required_utxos,
+            optional_utxos,
+            curr_value,
+            actual_target as i64,
+            fee_amount,
+        ))
+    }
+}
+
+/// Coin selection that runs several algorithms and picks the result with the lowest waste
+///
+/// The waste of a selection measures how much it costs compared to an ideal one, given the
+/// `long_term_fee_rate` that the wallet expects to pay on average in the future:
+///
+/// - every input adds the difference between its fee at the current fee rate and its fee at the
+///   long term fee rate. This is negative when the current fee rate is low, favouring the
+///   selections that consolidate many utxos, and positive when it's high, favouring the ones
+///   with fewer inputs;
+/// - if the selection creates a change output, the cost of creating it now and spending it
+///   later at the long term fee rate is added, otherwise the excess value given to the miners is
+///   added.
+///
+/// By default [`BranchAndBoundCoinSelection`], [`LargestFirstCoinSelection`],
+/// [`OldestFirstCoinSelection`] and [`SingleRandomDrawCoinSelection`] are compared, assuming a
+/// P2WPKH change output.
+pub struct WasteMetricCoinSelection<D> {
+    algorithms: Vec<Box<dyn CoinSelectionAlgorithm<D>>>,
+    long_term_fee_rate: FeeRate,
+    size_of_change: u64,
+    change_satisfaction_weight: usize,
+}
+
+impl<D> std::fmt::Debug for WasteMetricCoinSelection<D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasteMetricCoinSelection")
+            .field("algorithms", &self.algorithms)
+            .field("long_term_fee_rate", &self.long_term_fee_rate)
+            .field("size_of_change", &self.size_of_change)
+            .field(
+                "change_satisfaction_weight",
+                &self.change_satisfaction_weight,
+            )
+            .finish()
+    }
+}
+
+impl<D: Database> WasteMetricCoinSelection<D> {
+    /// Create a new instance comparing the default algorithms
+    pub fn new(long_term_fee_rate: FeeRate) -> Self {
+        Self::with_algorithms(
+            long_term_fee_rate,
+            vec![
+                Box::new(BranchAndBoundCoinSelection::default()),
+                Box::new(LargestFirstCoinSelection),
+                Box::new(OldestFirstCoinSelection),
+                Box::new(SingleRandomDrawCoinSelection),
+            ],
+        )
+    }
+
+    /// Create a new instance comparing the given algorithms
+    ///
+    /// When more algorithms have the same waste, the one that comes first is preferred.
+    pub fn with_algorithms(
+        long_term_fee_rate: FeeRate,
+        algorithms: Vec<Box<dyn CoinSelectionAlgorithm<D>>>,
+    ) -> Self {
+        WasteMetricCoinSelection {
+            algorithms,
+            long_term_fee_rate,
+            // P2WPKH change output: value (8 bytes) + script len (1 bytes) + script (22 bytes)
+            size_of_change: 8 + 1 + 22,
+            // P2WPKH witness: signature (73 bytes) + pubkey (33 bytes) + lengths (2 bytes)
+            change_satisfaction_weight: 73 + 33 + 2,
+        }
+    }
+
+    /// Set the size in bytes of the change output and the weight of its satisfaction
+    pub fn change(mut self, size_of_change: u64, change_satisfaction_weight: usize) -> Self {
+        self.size_of_change = size_of_change;
+        self.change_satisfaction_weight = change_satisfaction_weight;
+        self
+    }
+
+    /// Compute the waste of a selection of utxos
+    ///
+    /// `amount_needed` and `fee_amount` are the same arguments passed to
+    /// [`CoinSelectionAlgorithm::coin_select`].
+    pub fn waste(
+        &self,
+        selected: &[WeightedUtxo],
+        fee_rate: FeeRate,
+        amount_needed: u64,
+        fee_amount: f32,
+    ) -> f32 {
+        let inputs_weight = selected
+            .iter()
+            .map(|wu| TXIN_BASE_WEIGHT + wu.satisfaction_weight)
+            .sum::<usize>() as f32;
+        let inputs_fee = inputs_weight / 4.0 * fee_rate.as_sat_vb();
+        let timing_cost = inputs_fee - inputs_weight / 4.0 * self.long_term_fee_rate.as_sat_vb();
+
+        let selected_amount: u64 = selected.iter().map(|wu| wu.utxo.txout().value).sum();
+        let excess = selected_amount as f32 - amount_needed as f32 - fee_amount - inputs_fee;
+        let change_fee = self.size_of_change as f32 * fee_rate.as_sat_vb();
+        let change_value = (excess - change_fee).floor();
+
+        if change_value > 0.0 && !(change_value as u64).is_dust() {
+            let spend_change_fee = (TXIN_BASE_WEIGHT + self.change_satisfaction_weight) as f32
+                / 4.0
+                * self.long_term_fee_rate.as_sat_vb();
+            timing_cost + change_fee + spend_change_fee
+        } else {
+            timing_cost + excess
+        }
+    }
+}
+
+impl<D: Database> CoinSelectionAlgorithm<D> for WasteMetricCoinSelection<D> {
+    fn coin_select(
+        &self,
+        database: &D,
+        required_utxos: Vec<WeightedUtxo>,
+        optional_utxos: Vec<WeightedUtxo>,
+        fee_rate: FeeRate,
+        amount_needed: u64,
+        fee_amount: f32,
+    ) -> Result<CoinSelectionResult, Error> {
+        let weighted_utxos: HashMap<OutPoint, &WeightedUtxo> = required_utxos
+            .iter()
+            .chain(optional_utxos.iter())
+            .map(|wu| (wu.utxo.outpoint(), wu))
+            .collect();
+
+        let mut best: Option<(f32, CoinSelectionResult)> = None;
+        let mut first_error = None;
+        for algorithm in &self.algorithms {
+            let result = match algorithm.coin_select(
+                database,
+                required_utxos.clone(),
+                optional_utxos.clone(),
+                fee_rate,
+                amount_needed,
+                fee_amount,
+            ) {
+                Ok(result) => result,
+                Err(e) => {
+                    log::debug!("{:?} failed: {}", algorithm, e);
+                    first_error.get_or_insert(e);
+                    continue;
+                }
+            };
+
+            let selected = result
+                .selected
+                .iter()
+                .filter_map(|utxo| weighted_utxos.get(&utxo.outpoint()))
+                .map(|wu| (*wu).clone())
+                .collect::<Vec<_>>();
+            let waste = self.waste(&selected, fee_rate, amount_needed, fee_amount);
+            log::debug!(
+                "{:?} selected {} utxos, waste = `{}`",
+                algorithm,
+                selected.len(),
+                waste
+            );
+
+            let is_better = match &best {
+                Some((best_waste, _)) => waste < *best_waste,
+                None => true,
+            };
+            if is_better {
+                best = Some((waste, result));
+            }
+        }
+
+        match (best, first_error) {
+            (Some((_, result)), _) => Ok(result),
+            (None, Some(e)) => Err(e),
+            (None, None) => Err(Error::Generic(
+                "No coin selection algorithm to compare".to_string(),
+            )),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::str::FromStr;
@@ -537,8 +840,9 @@ mod test {
     use bitcoin::{OutPoint, Script, TxOut};
 
     use super::*;
-    use crate::database::MemoryDatabase;
+    use crate::database::{BatchOperations, MemoryDatabase};
     use crate::types::*;
+    use crate::ConfirmationTime;
 
     use rand::rngs::StdRng;
     use rand::seq::SliceRandom;
@@ -562,6 +866,7 @@ mod test {
                         script_pubkey: Script::new(),
                     },
                     keychain: KeychainKind::External,
+                    is_coinbase: false,
                 }),
             },
             WeightedUtxo {
@@ -576,6 +881,7 @@ mod test {
                         script_pubkey: Script::new(),
                     },
                     keychain: KeychainKind::External,
+                    is_coinbase: false,
                 }),
             },
             WeightedUtxo {
@@ -590,6 +896,7 @@ mod test {
                         script_pubkey: Script::new(),
                     },
                     keychain: KeychainKind::Internal,
+                    is_coinbase: false,
                 }),
             },
         ]
@@ -610,6 +917,7 @@ mod test {
                         script_pubkey: Script::new(),
                     },
                     keychain: KeychainKind::External,
+                    is_coinbase: false,
                 }),
             });
         }
@@ -629,6 +937,7 @@ mod test {
                     script_pubkey: Script::new(),
                 },
                 keychain: KeychainKind::External,
+                is_coinbase: false,
             }),
         };
         vec![utxo; utxos_number]
@@ -1042,4 +1351,264 @@ mod test {
             (result.fee_amount - (50.0 + result.selected.len() as f32 * 68.0)).abs() < f32::EPSILON
         );
     }
+
+    fn get_oldest_first_test_db() -> MemoryDatabase {
+        let mut database = MemoryDatabase::default();
+        for (txid, height) in &[
+            (
+                "0000000000000000000000000000000000000000000000000000000000000000",
+                200,
+            ),
+            (
+                "0000000000000000000000000000000000000000000000000000000000000002",
+                100,
+            ),
+        ] {
+            database
+                .set_tx(&TransactionDetails {
+                    txid: bitcoin::Txid::from_str(txid).unwrap(),
+                    confirmation_time: Some(ConfirmationTime {
+                        height: *height,
+                        timestamp: 0,
+                        block_hash: None,
+                    }),
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+
+        database
+    }
+
+    #[test]
+    fn test_oldest_first_coin_selection_success() {
+        let utxos = get_test_utxos();
+        let database = get_oldest_first_test_db();
+
+        let result = OldestFirstCoinSelection::default()
+            .coin_select(
+                &database,
+                vec![],
+                utxos,
+                FeeRate::from_sat_per_vb(1.0),
+                150_000,
+                FEE_AMOUNT,
+            )
+            .unwrap();
+
+        // the 200k utxo confirmed at height 100 is enough
+        assert_eq!(result.selected.len(), 1);
+        assert_eq!(result.selected_amount(), 200_000);
+        assert!((result.fee_amount - 118.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn test_oldest_first_coin_selection_unconfirmed_last() {
+        let utxos = get_test_utxos();
+        let database = get_oldest_first_test_db();
+
+        let result = OldestFirstCoinSelection::default()
+            .coin_select(
+                &database,
+                vec![],
+                utxos,
+                FeeRate::from_sat_per_vb(1.0),
+                250_000,
+                FEE_AMOUNT,
+            )
+            .unwrap();
+
+        // the utxo whose tx is unknown is not needed
+        assert_eq!(result.selected.len(), 2);
+        assert_eq!(result.selected_amount(), 300_000);
+        assert!(result
+            .selected
+            .iter()
+            .all(|u| u.outpoint().txid.to_string() != format!("{:064}", 1)));
+    }
+
+    #[test]
+    fn test_oldest_first_coin_selection_use_required() {
+        let mut utxos = get_test_utxos();
+        let database = get_oldest_first_test_db();
+        let required = vec![utxos.remove(1)];
+
+        let result = OldestFirstCoinSelection::default()
+            .coin_select(
+                &database,
+                required,
+                utxos,
+                FeeRate::from_sat_per_vb(1.0),
+                150_000,
+                FEE_AMOUNT,
+            )
+            .unwrap();
+
+        assert_eq!(result.selected.len(), 2);
+        assert_eq!(result.selected_amount(), 200_010);
+    }
+
+    #[test]
+    #[should_panic(expected = "InsufficientFunds")]
+    fn test_oldest_first_coin_selection_insufficient_funds() {
+        let utxos = get_test_utxos();
+        let database = get_oldest_first_test_db();
+
+        OldestFirstCoinSelection::default()
+            .coin_select(
+                &database,
+                vec![],
+                utxos,
+                FeeRate::from_sat_per_vb(1.0),
+                500_000,
+                FEE_AMOUNT,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_single_random_draw_coin_selection_success() {
+        let seed = [0; 32];
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+        let mut utxos = generate_random_utxos(&mut rng, 300);
+        let target_amount = sum_random_utxos(&mut rng, &mut utxos);
+        let database = MemoryDatabase::default();
+
+        let result = SingleRandomDrawCoinSelection::default()
+            .coin_select(
+                &database,
+                vec![],
+                utxos,
+                FeeRate::from_sat_per_vb(1.0),
+                target_amount,
+                FEE_AMOUNT,
+            )
+            .unwrap();
+
+        assert!(result.selected_amount() >= target_amount + result.fee_amount.ceil() as u64);
+    }
+
+    #[test]
+    fn test_single_random_draw_coin_selection_skips_uneconomical() {
+        let utxos = get_test_utxos();
+        let database = MemoryDatabase::default();
+
+        // the utxo of 10 sats costs more in fees than its value, and should never be selected
+        let result = SingleRandomDrawCoinSelection::default()
+            .coin_select(
+                &database,
+                vec![],
+                utxos,
+                FeeRate::from_sat_per_vb(1.0),
+                250_000,
+                FEE_AMOUNT,
+            )
+            .unwrap();
+
+        assert_eq!(result.selected.len(), 2);
+        assert_eq!(result.selected_amount(), 300_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "InsufficientFunds")]
+    fn test_single_random_draw_coin_selection_insufficient_funds() {
+        let utxos = get_test_utxos();
+        let database = MemoryDatabase::default();
+
+        SingleRandomDrawCoinSelection::default()
+            .coin_select(
+                &database,
+                vec![],
+                utxos,
+                FeeRate::from_sat_per_vb(1.0),
+                300_000,
+                FEE_AMOUNT,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_waste_metric() {
+        let utxos = get_test_utxos();
+        let selection =
+            WasteMetricCoinSelection::<MemoryDatabase>::new(FeeRate::from_sat_per_vb(10.0));
+
+        // with a low fee rate every input has a negative timing cost: 68 vbytes * (1 - 10)
+        let waste = selection.waste(&utxos[2..], FeeRate::from_sat_per_vb(1.0), 150_000, 50.0);
+        // the change is worth creating: 31 vbytes now + 68 vbytes at the long term fee rate
+        assert!((waste - (-612.0 + 31.0 + 680.0)).abs() < f32::EPSILON);
+
+        // without change the excess is wasted
+        let waste = selection.waste(
+            &utxos[2..],
+            FeeRate::from_sat_per_vb(1.0),
+            200_000 - 50 - 68 - 100,
+            50.0,
+        );
+        assert!((waste - (-612.0 + 100.0)).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn test_waste_metric_coin_selection_prefers_lowest_waste() {
+        let utxos = get_test_utxos();
+        let mut database = MemoryDatabase::default();
+        database
+            .set_tx(&TransactionDetails {
+                txid: utxos[0].utxo.outpoint().txid,
+                confirmation_time: Some(ConfirmationTime {
+                    height: 100,
+                    timestamp: 0,
+                    block_hash: None,
+                }),
+                ..Default::default()
+            })
+            .unwrap();
+
+        // `OldestFirstCoinSelection` spends the 100k utxo and then the 200k one, while
+        // `LargestFirstCoinSelection` only spends the 200k one
+        for (fee_rate, long_term_fee_rate, expected_inputs) in &[
+            // fees are low compared to the long term: consolidate the coins
+            (1.0, 50.0, 2),
+            // fees are high compared to the long term: spend as few coins as possible
+            (50.0, 1.0, 1),
+        ] {
+            let selection = WasteMetricCoinSelection::with_algorithms(
+                FeeRate::from_sat_per_vb(*long_term_fee_rate),
+                vec![
+                    Box::new(LargestFirstCoinSelection),
+                    Box::new(OldestFirstCoinSelection),
+                ],
+            );
+            let result = selection
+                .coin_select(
+                    &database,
+                    vec![],
+                    vec![utxos[0].clone(), utxos[2].clone()],
+                    FeeRate::from_sat_per_vb(*fee_rate),
+                    150_000,
+                    FEE_AMOUNT,
+                )
+                .unwrap();
+
+            assert_eq!(result.selected.len(), *expected_inputs);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "InsufficientFunds")]
+    fn test_waste_metric_coin_selection_insufficient_funds() {
+        let utxos = get_test_utxos();
+        let database = MemoryDatabase::default();
+
+        WasteMetricCoinSelection::new(FeeRate::from_sat_per_vb(10.0))
+            .coin_select(
+                &database,
+                vec![],
+                utxos,
+                FeeRate::from_sat_per_vb(1.0),
+                500_000,
+                FEE_AMOUNT,
+            )
+            .unwrap();
+    }
 }
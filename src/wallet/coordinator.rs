@@ -0,0 +1,437 @@
+// Bitcoin Dev Kit
+// Written in 2021 by the Bitcoin Dev Kit Developers
+//
+// Copyright (c) 2020-2021 Bitcoin Dev Kit Developers
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! Multisig coordinator
+//!
+//! This module contains the [`Coordinator`], that drives a multi-party signing round for a
+//! multisig [`Wallet`]: the transactions created by the wallet are registered as proposals, the
+//! partially signed copies returned by each cosigner are combined into them, and once the
+//! spending policy of every input is satisfied the proposal is finalized.
+//!
+//! ## Example
+//!
+//! ```no_run
+//! # use std::str::FromStr;
+//! # use bitcoin::*;
+//! # use bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
+//! # use bdk::database::*;
+//! # use bdk::wallet::coordinator::Coordinator;
+//! # use bdk::*;
+//! # let descriptor = "wsh(multi(2,tpubD6NzVbkrYhZ4Xferm7Pz4VnjdcDPFyjVu5K4iZXQ4pVN8Cks4pHVowTBXBKRhX64pkRyJZJN5xAKj4UDNnLPb5p2sSKXhewoYx5GbTdUFWq/*,tpubD6NzVbkrYhZ4WQdzxL7NmJN7b85ePo4p6RSj9QQHF7te2RR9iUeVSGgnGkoUsB9LBRosgvNbjRv9bcsJgzgBd7QKuxDm23ZewkTRzNSLEDr/*))";
+//! # let wallet = Wallet::new_offline(descriptor, None, Network::Testnet, MemoryDatabase::default())?;
+//! # let to_address = Address::from_str("2N4eQYCbKUHCCTUjBJeHcJp9ok6J2GZsTDt").unwrap();
+//! let mut coordinator = Coordinator::new(&wallet);
+//!
+//! let (psbt, _) = {
+//!     let mut builder = wallet.build_tx();
+//!     builder.add_recipient(to_address.script_pubkey(), 50_000);
+//!     builder.finish()?
+//! };
+//! let txid = coordinator.add_proposal(psbt)?;
+//!
+//! // send the proposal to the cosigners and collect their signed copies
+//! for signed in vec![Psbt::from_str("...")?, Psbt::from_str("...")?] {
+//!     let status = coordinator.add_signed_psbt(signed)?;
+//!     println!("{} signatures missing", status.missing_signatures());
+//! }
+//!
+//! let finalized = coordinator.finalize(&txid, SignOptions::default())?;
+//! # Ok::<(), bdk::Error>(())
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use bitcoin::util::bip32::Fingerprint;
+use bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
+use bitcoin::Txid;
+
+use crate::database::BatchDatabase;
+use crate::descriptor::policy::{Condition, Policy, Satisfaction, SatisfiableItem};
+use crate::error::Error;
+use crate::psbt::PsbtUtils;
+use crate::signer::SignOptions;
+use crate::types::KeychainKind;
+use crate::wallet::Wallet;
+
+/// Errors returned by the [`Coordinator`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CoordinatorError {
+    /// There's no proposal for the transaction with this id
+    UnknownProposal(Txid),
+    /// The proposal can't be finalized yet, either because some signatures are still missing or
+    /// because a timelock hasn't expired
+    NotFinalized(Txid),
+}
+
+impl fmt::Display for CoordinatorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CoordinatorError::UnknownProposal(txid) => write!(f, "Unknown proposal {}", txid),
+            CoordinatorError::NotFinalized(txid) => {
+                write!(f, "Proposal {} can't be finalized yet", txid)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CoordinatorError {}
+
+/// Signing state of a branch of the spending policy of an input
+///
+/// The branches are the items of the root node of the policy, or the root node itself if it's not
+/// a threshold: for example the policy `or(multi(2,A,B),and(pk(C),older(144)))` has two branches,
+/// the multisig and the timelocked key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchStatus {
+    /// Id of the policy node of the branch
+    pub id: String,
+    /// Number of signatures still required to satisfy the branch
+    pub missing_signatures: usize,
+    /// Timelocks that must expire before the branch can be used, as returned by
+    /// [`Policy::get_condition`]
+    ///
+    /// This is `None` if the branch contains nested choices that are not unambiguous.
+    pub condition: Option<Condition>,
+}
+
+/// Signing state of an input of a proposal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputStatus {
+    /// Index of the input
+    pub input_index: usize,
+    /// Keychain of the wallet that owns the input
+    pub keychain: KeychainKind,
+    /// Fingerprints of the keys that already signed the input
+    pub signed_by: BTreeSet<Fingerprint>,
+    /// State of each branch of the policy
+    pub branches: Vec<BranchStatus>,
+    /// Number of signatures still required to satisfy the policy through its cheapest branch
+    pub missing_signatures: usize,
+}
+
+/// Signing state of a proposal, returned by [`Coordinator::status`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProposalStatus {
+    /// Id of the transaction
+    pub txid: Txid,
+    /// State of the inputs that belong to the wallet and haven't been finalized yet
+    pub inputs: Vec<InputStatus>,
+}
+
+impl ProposalStatus {
+    /// Number of signatures still required across all the inputs
+    pub fn missing_signatures(&self) -> usize {
+        self.inputs
+            .iter()
+            .map(|input| input.missing_signatures)
+            .sum()
+    }
+
+    /// Return whether every input has enough signatures to be finalized
+    ///
+    /// Finalizing can still fail if a timelock required by the policy hasn't expired.
+    pub fn is_satisfied(&self) -> bool {
+        self.missing_signatures() == 0
+    }
+}
+
+/// Coordinator of the signing rounds of a multisig [`Wallet`]
+///
+/// For a usage example see [this module](crate::wallet::coordinator)'s documentation.
+#[derive(Debug)]
+pub struct Coordinator<'w, B, D> {
+    wallet: &'w Wallet<B, D>,
+    proposals: BTreeMap<Txid, Psbt>,
+}
+
+impl<'w, B, D: BatchDatabase> Coordinator<'w, B, D> {
+    /// Create a new coordinator for `wallet`
+    pub fn new(wallet: &'w Wallet<B, D>) -> Self {
+        Coordinator {
+            wallet,
+            proposals: BTreeMap::new(),
+        }
+    }
+
+    /// Register a PSBT as a new proposal, and return the id of its transaction
+    ///
+    /// If a proposal for the same transaction exists already the PSBT is combined into it.
+    pub fn add_proposal(&mut self, psbt: Psbt) -> Result<Txid, Error> {
+        let txid = psbt.global.unsigned_tx.txid();
+        match self.proposals.get_mut(&txid) {
+            Some(proposal) => proposal.combine(psbt)?,
+            None => {
+                self.proposals.insert(txid, psbt);
+            }
+        }
+
+        Ok(txid)
+    }
+
+    /// Combine a copy of a proposal signed by a cosigner into the proposal, and return its new
+    /// status
+    pub fn add_signed_psbt(&mut self, psbt: Psbt) -> Result<ProposalStatus, Error> {
+        let txid = psbt.global.unsigned_tx.txid();
+        self.proposals
+            .get_mut(&txid)
+            .ok_or(CoordinatorError::UnknownProposal(txid))?
+            .combine(psbt)?;
+
+        self.status(&txid)
+    }
+
+    /// Return the PSBT of a proposal, including all the signatures collected so far
+    pub fn get_proposal(&self, txid: &Txid) -> Option<&Psbt> {
+        self.proposals.get(txid)
+    }
+
+    /// Return the ids of the outstanding proposals
+    pub fn list_proposals(&self) -> Vec<Txid> {
+        self.proposals.keys().cloned().collect()
+    }
+
+    /// Stop tracking a proposal and return its PSBT
+    pub fn remove_proposal(&mut self, txid: &Txid) -> Option<Psbt> {
+        self.proposals.remove(txid)
+    }
+
+    /// Return how many signatures are still required by each input of a proposal
+    pub fn status(&self, txid: &Txid) -> Result<ProposalStatus, Error> {
+        let psbt = self
+            .proposals
+            .get(txid)
+            .ok_or(CoordinatorError::UnknownProposal(*txid))?;
+
+        let mut inputs = vec![];
+        for (n, input) in psbt.inputs.iter().enumerate() {
+            if input.final_script_sig.is_some() || input.final_script_witness.is_some() {
+                continue;
+            }
+            let (keychain, _, policy) = match self.wallet.psbt_input_policy(psbt, n)? {
+                Some(input_policy) => input_policy,
+                None => continue,
+            };
+
+            let branches = match &policy.item {
+                SatisfiableItem::Thresh { items, .. } => items.iter().collect(),
+                _ => vec![&policy],
+            }
+            .into_iter()
+            .map(|branch| BranchStatus {
+                id: branch.id.clone(),
+                missing_signatures: missing_signatures(branch),
+                condition: branch.get_condition(&BTreeMap::new()).ok(),
+            })
+            .collect();
+            let signed_by = input
+                .partial_sigs
+                .keys()
+                .filter_map(|pubkey| input.bip32_derivation.get(pubkey))
+                .map(|(fingerprint, _)| *fingerprint)
+                .collect();
+
+            inputs.push(InputStatus {
+                input_index: n,
+                keychain,
+                signed_by,
+                branches,
+                missing_signatures: missing_signatures(&policy),
+            });
+        }
+
+        Ok(ProposalStatus {
+            txid: *txid,
+            inputs,
+        })
+    }
+
+    /// Finalize a proposal and stop tracking it
+    ///
+    /// If the proposal can't be finalized yet it's left untouched and
+    /// [`CoordinatorError::NotFinalized`] is returned.
+    pub fn finalize(&mut self, txid: &Txid, sign_options: SignOptions) -> Result<Psbt, Error> {
+        let mut psbt = self
+            .proposals
+            .get(txid)
+            .ok_or(CoordinatorError::UnknownProposal(*txid))?
+            .clone();
+
+        if !self.wallet.finalize_psbt(&mut psbt, sign_options)? {
+            return Err(CoordinatorError::NotFinalized(*txid).into());
+        }
+        self.proposals.remove(txid);
+
+        Ok(psbt)
+    }
+}
+
+/// Number of signatures still required to satisfy a policy node, taking its cheapest branches
+///
+/// Timelocks and hash preimages don't require signatures, so they are not counted.
+fn missing_signatures(policy: &Policy) -> usize {
+    if let Satisfaction::Complete { .. } | Satisfaction::PartialComplete { .. } =
+        policy.satisfaction
+    {
+        return 0;
+    }
+
+    match &policy.item {
+        SatisfiableItem::Signature(_) | SatisfiableItem::SignatureKey(_) => 1,
+        SatisfiableItem::Multisig { threshold, .. } => match &policy.satisfaction {
+            Satisfaction::Partial { items, .. } => threshold.saturating_sub(items.len()),
+            _ => *threshold,
+        },
+        SatisfiableItem::Thresh { items, threshold } => {
+            let mut missing = items.iter().map(missing_signatures).collect::<Vec<_>>();
+            missing.sort_unstable();
+            missing.into_iter().take(*threshold).sum()
+        }
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::secp256k1::Secp256k1;
+    use bitcoin::util::bip32::{ExtendedPrivKey, ExtendedPubKey};
+    use bitcoin::Network;
+
+    use super::*;
+    use crate::database::MemoryDatabase;
+    use crate::wallet::test::get_funded_wallet;
+    use crate::wallet::AddressIndex;
+
+    fn keys(seed: u8) -> (String, String) {
+        let secp = Secp256k1::new();
+        let xprv = ExtendedPrivKey::new_master(Network::Testnet, &[seed; 32]).unwrap();
+        let xpub = ExtendedPubKey::from_private(&secp, &xprv);
+        (xprv.to_string(), xpub.to_string())
+    }
+
+    fn proposal<B, D: BatchDatabase>(wallet: &Wallet<B, D>) -> Psbt {
+        let send_to = wallet.get_address(AddressIndex::New).unwrap();
+        let mut builder = wallet.build_tx();
+        builder.add_recipient(send_to.script_pubkey(), 10_000);
+        builder.finish().unwrap().0
+    }
+
+    #[test]
+    fn test_coordinator_multisig_round() {
+        let (xprv_a, xpub_a) = keys(1);
+        let (xprv_b, xpub_b) = keys(2);
+        let (wallet, _, _) = get_funded_wallet(&format!("wsh(multi(2,{}/*,{}/*))", xprv_a, xpub_b));
+        let cosigner = Wallet::new_offline(
+            &format!("wsh(multi(2,{}/*,{}/*))", xpub_a, xprv_b),
+            None,
+            Network::Regtest,
+            MemoryDatabase::new(),
+        )
+        .unwrap();
+
+        let mut coordinator = Coordinator::new(&wallet);
+        let psbt = proposal(&wallet);
+        let txid = coordinator.add_proposal(psbt.clone()).unwrap();
+        assert_eq!(coordinator.list_proposals(), vec![txid]);
+
+        let status = coordinator.status(&txid).unwrap();
+        assert_eq!(status.inputs.len(), 1);
+        assert_eq!(status.inputs[0].branches.len(), 1);
+        assert_eq!(status.inputs[0].branches[0].missing_signatures, 2);
+        assert_eq!(status.missing_signatures(), 2);
+        assert!(matches!(
+            coordinator
+                .finalize(&txid, SignOptions::default())
+                .unwrap_err(),
+            Error::Coordinator(CoordinatorError::NotFinalized(_))
+        ));
+
+        let mut signed_a = psbt.clone();
+        wallet.sign(&mut signed_a, SignOptions::default()).unwrap();
+        let status = coordinator.add_signed_psbt(signed_a).unwrap();
+        assert_eq!(status.missing_signatures(), 1);
+        assert_eq!(status.inputs[0].signed_by.len(), 1);
+
+        let mut signed_b = psbt;
+        cosigner
+            .sign(&mut signed_b, SignOptions::default())
+            .unwrap();
+        let status = coordinator.add_signed_psbt(signed_b).unwrap();
+        assert!(status.is_satisfied());
+        assert_eq!(status.inputs[0].signed_by.len(), 2);
+
+        let finalized = coordinator.finalize(&txid, SignOptions::default()).unwrap();
+        assert!(finalized.inputs[0].final_script_witness.is_some());
+        assert!(coordinator.list_proposals().is_empty());
+    }
+
+    #[test]
+    fn test_coordinator_branches() {
+        let (xprv_a, _) = keys(1);
+        let (_, xpub_b) = keys(2);
+        let (_, xpub_c) = keys(3);
+        let (wallet, _, _) = get_funded_wallet(&format!(
+            "wsh(or_d(multi(2,{}/*,{}/*),and_v(v:pk({}/*),older(144))))",
+            xprv_a, xpub_b, xpub_c
+        ));
+
+        let mut coordinator = Coordinator::new(&wallet);
+        // spend through the multisig branch
+        let root_id = wallet.policies(KeychainKind::External).unwrap().unwrap().id;
+        let path = vec![(root_id, vec![0])].into_iter().collect();
+        let send_to = wallet.get_address(AddressIndex::New).unwrap();
+        let mut builder = wallet.build_tx();
+        builder
+            .add_recipient(send_to.script_pubkey(), 10_000)
+            .policy_path(path, KeychainKind::External);
+        let (mut psbt, _) = builder.finish().unwrap();
+        let txid = coordinator.add_proposal(psbt.clone()).unwrap();
+
+        let status = coordinator.status(&txid).unwrap();
+        let branches = &status.inputs[0].branches;
+        assert_eq!(branches.len(), 2);
+        assert_eq!(branches[0].missing_signatures, 2);
+        assert_eq!(branches[0].condition, Some(Condition::default()));
+        assert_eq!(branches[1].missing_signatures, 1);
+        assert_eq!(
+            branches[1].condition,
+            Some(Condition {
+                csv: Some(144),
+                timelock: None
+            })
+        );
+        assert_eq!(status.inputs[0].missing_signatures, 1);
+
+        wallet.sign(&mut psbt, SignOptions::default()).unwrap();
+        let status = coordinator.add_signed_psbt(psbt).unwrap();
+        assert_eq!(status.inputs[0].branches[0].missing_signatures, 1);
+        assert_eq!(status.inputs[0].branches[1].missing_signatures, 1);
+    }
+
+    #[test]
+    fn test_coordinator_unknown_proposal() {
+        let (xprv_a, _) = keys(1);
+        let (_, xpub_b) = keys(2);
+        let (wallet, _, _) = get_funded_wallet(&format!("wsh(multi(2,{}/*,{}/*))", xprv_a, xpub_b));
+
+        let mut coordinator = Coordinator::new(&wallet);
+        let psbt = proposal(&wallet);
+        let txid = psbt.global.unsigned_tx.txid();
+        assert!(matches!(
+            coordinator.add_signed_psbt(psbt).unwrap_err(),
+            Error::Coordinator(CoordinatorError::UnknownProposal(t)) if t == txid
+        ));
+        assert!(matches!(
+            coordinator.status(&txid).unwrap_err(),
+            Error::Coordinator(CoordinatorError::UnknownProposal(_))
+        ));
+    }
+}
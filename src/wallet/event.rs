@@ -0,0 +1,334 @@
+// Bitcoin Dev Kit
+// Written in 2021 by the Bitcoin Dev Kit Developers
+//
+// Copyright (c) 2020-2021 Bitcoin Dev Kit Developers
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! Wallet events
+//!
+//! Every time a [`Wallet`](super::Wallet) is [synced](super::Wallet::sync) the content of its
+//! database before and after the sync is compared, and the differences are reported as
+//! [`WalletEvent`]s to all the listeners attached with
+//! [`Wallet::add_event_listener`](super::Wallet::add_event_listener).
+//!
+//! ## Example
+//!
+//! ```
+//! # use std::sync::Arc;
+//! # use bitcoin::*;
+//! # use bdk::database::*;
+//! # use bdk::wallet::event::*;
+//! # use bdk::*;
+//! #[derive(Debug)]
+//! struct PrintEvents;
+//!
+//! impl EventListener for PrintEvents {
+//!     fn on_event(&self, event: &WalletEvent) {
+//!         match event {
+//!             WalletEvent::TxReceived(details) => println!("New tx {}", details.txid),
+//!             WalletEvent::TxConfirmed { txid, .. } => println!("Tx {} confirmed", txid),
+//!             _ => {}
+//!         }
+//!     }
+//! }
+//!
+//! let descriptor = "wpkh(tpubD6NzVbkrYhZ4Xferm7Pz4VnjdcDPFyjVu5K4iZXQ4pVN8Cks4pHVowTBXBKRhX64pkRyJZJN5xAKj4UDNnLPb5p2sSKXhewoYx5GbTdUFWq/*)";
+//! let mut wallet = Wallet::new_offline(descriptor, None, Network::Testnet, MemoryDatabase::default())?;
+//! wallet.add_event_listener(Arc::new(PrintEvents));
+//! # Ok::<(), bdk::Error>(())
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use bitcoin::{OutPoint, Txid};
+
+use crate::database::Database;
+use crate::error::Error;
+use crate::types::{ConfirmationTime, LocalUtxo, TransactionDetails};
+
+/// Change in the state of the wallet detected during a sync
+#[derive(Debug, Clone, PartialEq)]
+pub enum WalletEvent {
+    /// A transaction that involves the wallet was found for the first time
+    TxReceived(TransactionDetails),
+    /// A transaction was confirmed, or moved to a different block by a reorg
+    TxConfirmed {
+        /// Id of the transaction
+        txid: Txid,
+        /// Block that confirms the transaction
+        confirmation_time: ConfirmationTime,
+    },
+    /// A confirmed transaction returned to the mempool after a reorg
+    TxUnconfirmed {
+        /// Id of the transaction
+        txid: Txid,
+    },
+    /// A transaction was replaced by a conflicting one that spends some of the same inputs
+    TxReplaced {
+        /// Id of the transaction that was replaced
+        txid: Txid,
+        /// Id of the replacement
+        replaced_by: Txid,
+    },
+    /// A transaction disappeared without a conflicting transaction in the wallet, for example
+    /// because it was evicted from the mempool
+    TxDropped {
+        /// Id of the transaction
+        txid: Txid,
+    },
+    /// An output of the wallet was spent
+    UtxoSpent {
+        /// The output that was spent
+        outpoint: OutPoint,
+        /// Id of the spending transaction
+        txid: Txid,
+    },
+}
+
+/// Trait implemented by the listeners of the [`WalletEvent`]s
+///
+/// All the listeners attached to a wallet with
+/// [`Wallet::add_event_listener`](super::Wallet::add_event_listener) are called in sequence,
+/// once per event, at the end of every sync.
+///
+/// For a usage example see [this module](crate::wallet::event)'s documentation.
+pub trait EventListener: Send + Sync + fmt::Debug {
+    /// Handle an event
+    fn on_event(&self, event: &WalletEvent);
+}
+
+/// Transactions and utxos stored in a database at a given time
+pub(crate) struct WalletSnapshot {
+    txs: Vec<TransactionDetails>,
+    utxos: HashSet<OutPoint>,
+}
+
+impl WalletSnapshot {
+    pub(crate) fn take<D: Database>(database: &D) -> Result<Self, Error> {
+        Ok(WalletSnapshot {
+            txs: database.iter_txs(true)?,
+            utxos: database
+                .iter_utxos()?
+                .into_iter()
+                .map(|LocalUtxo { outpoint, .. }| outpoint)
+                .collect(),
+        })
+    }
+
+    /// Compute the events that lead from `self` to the `after` snapshot
+    pub(crate) fn diff(&self, after: &WalletSnapshot) -> Vec<WalletEvent> {
+        let before_txs = self
+            .txs
+            .iter()
+            .map(|details| (details.txid, details))
+            .collect::<HashMap<_, _>>();
+        let after_txids = after
+            .txs
+            .iter()
+            .map(|details| details.txid)
+            .collect::<HashSet<_>>();
+        let spent_by = after
+            .txs
+            .iter()
+            .filter_map(|details| details.transaction.as_ref())
+            .flat_map(|tx| {
+                let txid = tx.txid();
+                tx.input
+                    .iter()
+                    .map(move |txin| (txin.previous_output, txid))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut events = vec![];
+        for details in &after.txs {
+            match before_txs.get(&details.txid) {
+                None => events.push(WalletEvent::TxReceived(details.clone())),
+                Some(before) if before.confirmation_time != details.confirmation_time => events
+                    .push(match &details.confirmation_time {
+                        Some(confirmation_time) => WalletEvent::TxConfirmed {
+                            txid: details.txid,
+                            confirmation_time: confirmation_time.clone(),
+                        },
+                        None => WalletEvent::TxUnconfirmed { txid: details.txid },
+                    }),
+                _ => {}
+            }
+        }
+
+        for details in self.txs.iter().filter(|d| !after_txids.contains(&d.txid)) {
+            let replaced_by = details.transaction.as_ref().and_then(|tx| {
+                tx.input
+                    .iter()
+                    .find_map(|txin| spent_by.get(&txin.previous_output))
+            });
+            events.push(match replaced_by {
+                Some(replaced_by) => WalletEvent::TxReplaced {
+                    txid: details.txid,
+                    replaced_by: *replaced_by,
+                },
+                None => WalletEvent::TxDropped { txid: details.txid },
+            });
+        }
+
+        // utxos that disappeared because their tx was dropped are not reported as spent
+        let mut spent = self
+            .utxos
+            .difference(&after.utxos)
+            .filter_map(|outpoint| spent_by.get(outpoint).map(|txid| (*outpoint, *txid)))
+            .collect::<Vec<_>>();
+        spent.sort();
+        events.extend(
+            spent
+                .into_iter()
+                .map(|(outpoint, txid)| WalletEvent::UtxoSpent { outpoint, txid }),
+        );
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::{OutPoint, Transaction, TxIn, TxOut};
+
+    use super::*;
+    use crate::database::{BatchOperations, MemoryDatabase};
+    use crate::types::KeychainKind;
+
+    fn tx_spending(previous_output: OutPoint, value: u64) -> Transaction {
+        Transaction {
+            version: 1,
+            lock_time: 0,
+            input: vec![TxIn {
+                previous_output,
+                ..Default::default()
+            }],
+            output: vec![TxOut {
+                value,
+                ..Default::default()
+            }],
+        }
+    }
+
+    fn add_tx(
+        db: &mut MemoryDatabase,
+        tx: &Transaction,
+        confirmation_time: Option<ConfirmationTime>,
+    ) {
+        db.set_tx(&TransactionDetails {
+            transaction: Some(tx.clone()),
+            txid: tx.txid(),
+            received: tx.output[0].value,
+            confirmation_time,
+            ..Default::default()
+        })
+        .unwrap();
+    }
+
+    fn add_utxo(db: &mut MemoryDatabase, tx: &Transaction) -> OutPoint {
+        let outpoint = OutPoint::new(tx.txid(), 0);
+        db.set_utxo(&LocalUtxo {
+            outpoint,
+            txout: tx.output[0].clone(),
+            keychain: KeychainKind::External,
+            is_coinbase: false,
+        })
+        .unwrap();
+
+        outpoint
+    }
+
+    #[test]
+    fn test_diff_received_and_confirmed() {
+        let mut db = MemoryDatabase::new();
+        let first = tx_spending(OutPoint::default(), 50_000);
+        add_tx(&mut db, &first, None);
+        let before = WalletSnapshot::take(&db).unwrap();
+
+        let confirmation_time = ConfirmationTime::new(Some(100), Some(1_000)).unwrap();
+        add_tx(&mut db, &first, Some(confirmation_time.clone()));
+        let second = tx_spending(OutPoint::default(), 10_000);
+        add_tx(&mut db, &second, None);
+        let after = WalletSnapshot::take(&db).unwrap();
+
+        let events = before.diff(&after);
+        assert_eq!(events.len(), 2);
+        assert!(events.contains(&WalletEvent::TxConfirmed {
+            txid: first.txid(),
+            confirmation_time
+        }));
+        assert!(events.iter().any(
+            |e| matches!(e, WalletEvent::TxReceived(details) if details.txid == second.txid())
+        ));
+
+        assert!(after.diff(&after).is_empty());
+    }
+
+    #[test]
+    fn test_diff_unconfirmed() {
+        let mut db = MemoryDatabase::new();
+        let tx = tx_spending(OutPoint::default(), 50_000);
+        add_tx(&mut db, &tx, ConfirmationTime::new(Some(100), Some(1_000)));
+        let before = WalletSnapshot::take(&db).unwrap();
+
+        add_tx(&mut db, &tx, None);
+        let after = WalletSnapshot::take(&db).unwrap();
+
+        assert_eq!(
+            before.diff(&after),
+            vec![WalletEvent::TxUnconfirmed { txid: tx.txid() }]
+        );
+    }
+
+    #[test]
+    fn test_diff_spent_replaced_and_dropped() {
+        let mut db = MemoryDatabase::new();
+        let funding = tx_spending(OutPoint::default(), 50_000);
+        add_tx(
+            &mut db,
+            &funding,
+            ConfirmationTime::new(Some(100), Some(1_000)),
+        );
+        let outpoint = add_utxo(&mut db, &funding);
+        let dropped = tx_spending(OutPoint::new(Txid::default(), 1), 20_000);
+        add_tx(&mut db, &dropped, None);
+        let dropped_outpoint = add_utxo(&mut db, &dropped);
+        let original = tx_spending(outpoint, 40_000);
+        add_tx(&mut db, &original, None);
+        let before = WalletSnapshot::take(&db).unwrap();
+
+        db.del_tx(&original.txid(), true).unwrap();
+        db.del_tx(&dropped.txid(), true).unwrap();
+        db.del_utxo(&dropped_outpoint).unwrap();
+        db.del_utxo(&outpoint).unwrap();
+        let replacement = tx_spending(outpoint, 30_000);
+        add_tx(&mut db, &replacement, None);
+        let after = WalletSnapshot::take(&db).unwrap();
+
+        let events = before.diff(&after);
+        assert_eq!(events.len(), 4);
+        assert!(events.iter().any(
+            |e| matches!(e, WalletEvent::TxReceived(details) if details.txid == replacement.txid())
+        ));
+        assert!(events.contains(&WalletEvent::TxReplaced {
+            txid: original.txid(),
+            replaced_by: replacement.txid()
+        }));
+        assert!(events.contains(&WalletEvent::TxDropped {
+            txid: dropped.txid()
+        }));
+        assert_eq!(
+            events.last(),
+            Some(&WalletEvent::UtxoSpent {
+                outpoint,
+                txid: replacement.txid()
+            })
+        );
+    }
+}
@@ -0,0 +1,330 @@
+// Bitcoin Dev Kit
+// Written in 2021 by the Bitcoin Dev Kit Developers
+//
+// Copyright (c) 2020-2021 Bitcoin Dev Kit Developers
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! Caravan multisig configs
+//!
+//! This module implements the JSON wallet configuration used by
+//! [Caravan](https://github.com/unchained-capital/caravan), that Sparrow can also import and
+//! export.
+//!
+//! ## Example
+//!
+//! ```
+//! # use std::str::FromStr;
+//! # use bitcoin::*;
+//! # use bdk::database::*;
+//! # use bdk::wallet::export::caravan::CaravanConfig;
+//! # use bdk::*;
+//! let import = r#"{
+//!     "name": "Family vault",
+//!     "addressType": "P2WSH",
+//!     "network": "testnet",
+//!     "client": { "type": "public" },
+//!     "quorum": { "requiredSigners": 2, "totalSigners": 2 },
+//!     "extendedPublicKeys": [
+//!         {
+//!             "name": "Alice",
+//!             "bip32Path": "m/48'/1'/0'/2'",
+//!             "xpub": "tpubDEU3eBekc59Yw68Nb3dmoXcinikF3qhGW9ymz39kMuBFSHFqX8MFuyt4mC3y9EiCWDmVw1rmQ3s7GjERKkARjGFwA2dAWRLpeMCU5oUuMXU",
+//!             "xfp": "c55b303f"
+//!         },
+//!         {
+//!             "name": "Bob",
+//!             "bip32Path": "m/48'/1'/0'/2'",
+//!             "xpub": "tpubDE8WcdSH7SBJWrXiJYbWyLuYCoeFv925voAtzYssJsfXgi2WGA3kxMbdp1fP2zWX4sL14jXJyEPtTDjTAJfDrjpPZoTnK9UMcsgAbTD4c7W",
+//!             "xfp": "34b00776"
+//!         }
+//!     ],
+//!     "startingAddressIndex": 0
+//! }"#;
+//!
+//! let import = CaravanConfig::from_str(import)?;
+//! let wallet = Wallet::new_offline(
+//!     &import.descriptor()?,
+//!     Some(&import.change_descriptor()?),
+//!     import.network()?,
+//!     MemoryDatabase::default(),
+//! )?;
+//! # Ok::<_, Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use bitcoin::util::bip32::{DerivationPath, Fingerprint};
+use bitcoin::Network;
+
+use super::{AccountKey, FormatError, MultisigScriptType, MultisigSetup, Slip132};
+use crate::database::BatchDatabase;
+use crate::types::KeychainKind;
+use crate::wallet::Wallet;
+
+/// A Caravan multisig config
+///
+/// For a usage example see [this module](crate::wallet::export::caravan)'s documentation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaravanConfig {
+    /// Name of the wallet
+    pub name: String,
+    /// Script type: `P2SH`, `P2WSH` or `P2SH-P2WSH`
+    pub address_type: String,
+    /// Network: `mainnet`, `testnet` or `regtest`
+    pub network: String,
+    /// Blockchain client used by Caravan
+    #[serde(default)]
+    pub client: CaravanClient,
+    /// Number of signatures required and number of keys
+    pub quorum: CaravanQuorum,
+    /// Keys of the cosigners
+    pub extended_public_keys: Vec<CaravanKey>,
+    /// First address index used by the wallet
+    #[serde(default)]
+    pub starting_address_index: u32,
+}
+
+/// Blockchain client of a [`CaravanConfig`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaravanClient {
+    /// Type of the client, `public` for the public block explorers
+    #[serde(rename = "type")]
+    pub client_type: String,
+}
+
+impl Default for CaravanClient {
+    fn default() -> Self {
+        CaravanClient {
+            client_type: "public".into(),
+        }
+    }
+}
+
+/// Quorum of a [`CaravanConfig`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaravanQuorum {
+    /// Number of signatures required
+    pub required_signers: usize,
+    /// Number of keys
+    pub total_signers: usize,
+}
+
+/// A cosigner of a [`CaravanConfig`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaravanKey {
+    /// Name of the cosigner
+    pub name: String,
+    /// Derivation path of the key
+    pub bip32_path: String,
+    /// Extended public key
+    pub xpub: String,
+    /// Fingerprint of the master key
+    pub xfp: String,
+    /// How the key was imported into Caravan
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+}
+
+impl CaravanConfig {
+    /// Export a multisig wallet
+    ///
+    /// See [`MultisigSetup::from_wallet`] for the wallets that can be exported.
+    pub fn export_wallet<B, D: BatchDatabase>(
+        wallet: &Wallet<B, D>,
+        name: &str,
+    ) -> Result<Self, FormatError> {
+        let setup = MultisigSetup::from_wallet(wallet)?;
+        let network = match wallet.network {
+            Network::Bitcoin => "mainnet",
+            Network::Testnet => "testnet",
+            Network::Regtest => "regtest",
+            Network::Signet => {
+                return Err(FormatError::Unsupported(
+                    "Caravan doesn't support signet".into(),
+                ))
+            }
+        };
+
+        Ok(CaravanConfig {
+            name: name.into(),
+            address_type: match setup.script_type {
+                MultisigScriptType::P2sh => "P2SH",
+                MultisigScriptType::P2wsh => "P2WSH",
+                MultisigScriptType::P2shP2wsh => "P2SH-P2WSH",
+            }
+            .into(),
+            network: network.into(),
+            client: CaravanClient::default(),
+            quorum: CaravanQuorum {
+                required_signers: setup.threshold,
+                total_signers: setup.cosigners.len(),
+            },
+            extended_public_keys: setup
+                .cosigners
+                .iter()
+                .enumerate()
+                .map(|(i, key)| CaravanKey {
+                    name: format!("Cosigner {}", i + 1),
+                    bip32_path: key.derivation_path.to_string(),
+                    xpub: key.xpub.to_string(),
+                    xfp: key.fingerprint.to_string(),
+                    method: Some("text".into()),
+                })
+                .collect(),
+            starting_address_index: 0,
+        })
+    }
+
+    /// Convert the config to a [`MultisigSetup`]
+    pub fn setup(&self) -> Result<MultisigSetup, FormatError> {
+        let script_type = match self.address_type.to_uppercase().as_str() {
+            "P2SH" => MultisigScriptType::P2sh,
+            "P2WSH" => MultisigScriptType::P2wsh,
+            "P2SH-P2WSH" | "P2SH_P2WSH" => MultisigScriptType::P2shP2wsh,
+            _ => {
+                return Err(FormatError::Unsupported(format!(
+                    "unknown address type `{}`",
+                    self.address_type
+                )))
+            }
+        };
+        let quorum = self.quorum;
+        if quorum.total_signers != self.extended_public_keys.len()
+            || quorum.required_signers > quorum.total_signers
+            || quorum.required_signers == 0
+        {
+            return Err(FormatError::Invalid(format!(
+                "quorum {} of {} with {} keys",
+                quorum.required_signers,
+                quorum.total_signers,
+                self.extended_public_keys.len()
+            )));
+        }
+
+        let cosigners = self
+            .extended_public_keys
+            .iter()
+            .map(|key| {
+                Ok(AccountKey {
+                    fingerprint: Fingerprint::from_str(&key.xfp).map_err(|_| {
+                        FormatError::Invalid(format!("invalid fingerprint `{}`", key.xfp))
+                    })?,
+                    derivation_path: DerivationPath::from_str(&key.bip32_path).map_err(|_| {
+                        FormatError::Invalid(format!("invalid path `{}`", key.bip32_path))
+                    })?,
+                    xpub: Slip132::decode(&key.xpub)?.0,
+                })
+            })
+            .collect::<Result<_, FormatError>>()?;
+
+        Ok(MultisigSetup {
+            script_type,
+            threshold: quorum.required_signers,
+            cosigners,
+        })
+    }
+
+    /// Return the network of the wallet
+    pub fn network(&self) -> Result<Network, FormatError> {
+        match self.network.as_str() {
+            "mainnet" => Ok(Network::Bitcoin),
+            "testnet" => Ok(Network::Testnet),
+            "regtest" => Ok(Network::Regtest),
+            network => Err(FormatError::Unsupported(format!(
+                "unknown network `{}`",
+                network
+            ))),
+        }
+    }
+
+    /// Return the external descriptor
+    pub fn descriptor(&self) -> Result<String, FormatError> {
+        Ok(self.setup()?.descriptor(KeychainKind::External))
+    }
+
+    /// Return the internal descriptor
+    pub fn change_descriptor(&self) -> Result<String, FormatError> {
+        Ok(self.setup()?.descriptor(KeychainKind::Internal))
+    }
+}
+
+impl fmt::Display for CaravanConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", serde_json::to_string_pretty(self).unwrap())
+    }
+}
+
+impl FromStr for CaravanConfig {
+    type Err = FormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::database::MemoryDatabase;
+
+    const DESCRIPTOR: &str = "sh(wsh(sortedmulti(2,[c55b303f/48'/1'/0'/1']tpubDEU3eBekc59YtsJckhTkCZ3PXiaNNdZZcxtVDzBMh2ti43jMPFgczbFAtZXLjynJxA1K64HoxZSvi4T5CEHx8YQo7r9Y4CxfKXBSAkAkTXr/0/*,[34b00776/48'/1'/0'/1']tpubDE8WcdSH7SBJTb7o8gC4cyBgwsPnuPjEdiBFAJd4zvietipew6m4fofTEsgc5cQhcTnfGDsTQyvq8wTMtDLW5fbBnXXvqQhV8Meug5Cdp3F/0/*)))";
+
+    #[test]
+    fn test_caravan_roundtrip() {
+        let wallet = Wallet::new_offline(
+            DESCRIPTOR,
+            Some(&DESCRIPTOR.replace("/0/*", "/1/*")),
+            Network::Testnet,
+            MemoryDatabase::default(),
+        )
+        .unwrap();
+
+        let export = CaravanConfig::export_wallet(&wallet, "Family vault").unwrap();
+        assert_eq!(export.address_type, "P2SH-P2WSH");
+        assert_eq!(export.network, "testnet");
+        assert_eq!(export.extended_public_keys[0].bip32_path, "m/48'/1'/0'/1'");
+        assert_eq!(export.extended_public_keys[1].xfp, "34b00776");
+
+        let import = CaravanConfig::from_str(&export.to_string()).unwrap();
+        assert_eq!(import, export);
+        assert_eq!(import.descriptor().unwrap(), DESCRIPTOR);
+        assert_eq!(
+            import.change_descriptor().unwrap(),
+            DESCRIPTOR.replace("/0/*", "/1/*")
+        );
+        assert_eq!(import.network().unwrap(), Network::Testnet);
+    }
+
+    #[test]
+    fn test_caravan_import_invalid_quorum() {
+        let import = r#"{
+            "name": "Broken",
+            "addressType": "P2WSH",
+            "network": "testnet",
+            "quorum": { "requiredSigners": 2, "totalSigners": 2 },
+            "extendedPublicKeys": [
+                {
+                    "name": "Alice",
+                    "bip32Path": "m/48'/1'/0'/2'",
+                    "xpub": "tpubDEU3eBekc59Yw68Nb3dmoXcinikF3qhGW9ymz39kMuBFSHFqX8MFuyt4mC3y9EiCWDmVw1rmQ3s7GjERKkARjGFwA2dAWRLpeMCU5oUuMXU",
+                    "xfp": "c55b303f"
+                }
+            ]
+        }"#;
+
+        let import = CaravanConfig::from_str(import).unwrap();
+        assert_eq!(import.client, CaravanClient::default());
+        assert!(matches!(import.descriptor(), Err(FormatError::Invalid(_))));
+    }
+}
@@ -0,0 +1,348 @@
+// Bitcoin Dev Kit
+// Written in 2021 by the Bitcoin Dev Kit Developers
+//
+// Copyright (c) 2020-2021 Bitcoin Dev Kit Developers
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! Coldcard multisig setup files
+//!
+//! This module implements the text format used by [Coldcard](https://coldcard.com/docs/multisig)
+//! to import and export the configuration of a multisig wallet. The same format is also used by
+//! Sparrow, Specter and other wallets to register a multisig wallet on a Coldcard.
+//!
+//! ## Example
+//!
+//! ```
+//! # use std::str::FromStr;
+//! # use bitcoin::*;
+//! # use bdk::database::*;
+//! # use bdk::wallet::export::coldcard::ColdcardMultisig;
+//! # use bdk::*;
+//! let import = "# Coldcard Multisig setup file
+//! Name: Family vault
+//! Policy: 2 of 2
+//! Derivation: m/48'/1'/0'/2'
+//! Format: P2WSH
+//!
+//! C55B303F: tpubDEU3eBekc59Yw68Nb3dmoXcinikF3qhGW9ymz39kMuBFSHFqX8MFuyt4mC3y9EiCWDmVw1rmQ3s7GjERKkARjGFwA2dAWRLpeMCU5oUuMXU
+//! 34B00776: tpubDE8WcdSH7SBJWrXiJYbWyLuYCoeFv925voAtzYssJsfXgi2WGA3kxMbdp1fP2zWX4sL14jXJyEPtTDjTAJfDrjpPZoTnK9UMcsgAbTD4c7W
+//! ";
+//!
+//! let import = ColdcardMultisig::from_str(import)?;
+//! let wallet = Wallet::new_offline(
+//!     &import.descriptor(),
+//!     Some(&import.change_descriptor()),
+//!     Network::Testnet,
+//!     MemoryDatabase::default(),
+//! )?;
+//! # Ok::<_, Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use bitcoin::util::bip32::{DerivationPath, Fingerprint};
+
+use super::{AccountKey, FormatError, MultisigScriptType, MultisigSetup, Slip132};
+use crate::database::BatchDatabase;
+use crate::types::KeychainKind;
+use crate::wallet::Wallet;
+
+/// A Coldcard multisig setup file
+///
+/// For a usage example see [this module](crate::wallet::export::coldcard)'s documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColdcardMultisig {
+    /// Name of the wallet, Coldcard only shows the first 20 characters
+    pub name: String,
+    /// Configuration of the wallet
+    pub setup: MultisigSetup,
+}
+
+impl ColdcardMultisig {
+    /// Export a multisig wallet
+    ///
+    /// See [`MultisigSetup::from_wallet`] for the wallets that can be exported.
+    pub fn export_wallet<B, D: BatchDatabase>(
+        wallet: &Wallet<B, D>,
+        name: &str,
+    ) -> Result<Self, FormatError> {
+        Ok(ColdcardMultisig {
+            name: name.into(),
+            setup: MultisigSetup::from_wallet(wallet)?,
+        })
+    }
+
+    /// Return the external descriptor
+    pub fn descriptor(&self) -> String {
+        self.setup.descriptor(KeychainKind::External)
+    }
+
+    /// Return the internal descriptor
+    pub fn change_descriptor(&self) -> String {
+        self.setup.descriptor(KeychainKind::Internal)
+    }
+}
+
+impl fmt::Display for ColdcardMultisig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let setup = &self.setup;
+        let format = match setup.script_type {
+            MultisigScriptType::P2sh => "P2SH",
+            MultisigScriptType::P2wsh => "P2WSH",
+            MultisigScriptType::P2shP2wsh => "P2SH-P2WSH",
+        };
+        // a single `Derivation` line is enough if all the keys share the same path
+        let common_path = match setup.cosigners.first() {
+            Some(first)
+                if setup
+                    .cosigners
+                    .iter()
+                    .all(|key| key.derivation_path == first.derivation_path) =>
+            {
+                Some(&first.derivation_path)
+            }
+            _ => None,
+        };
+
+        writeln!(f, "# Coldcard Multisig setup file")?;
+        writeln!(f, "Name: {}", self.name)?;
+        writeln!(
+            f,
+            "Policy: {} of {}",
+            setup.threshold,
+            setup.cosigners.len()
+        )?;
+        if let Some(path) = common_path {
+            writeln!(f, "Derivation: {}", path)?;
+        }
+        writeln!(f, "Format: {}", format)?;
+
+        for key in &setup.cosigners {
+            writeln!(f)?;
+            if common_path.is_none() {
+                writeln!(f, "Derivation: {}", key.derivation_path)?;
+            }
+            writeln!(
+                f,
+                "{}: {}",
+                key.fingerprint.to_string().to_uppercase(),
+                key.xpub
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for ColdcardMultisig {
+    type Err = FormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut name = None;
+        let mut policy = None;
+        let mut script_type = None;
+        let mut derivation_path = None;
+        let mut cosigners = vec![];
+
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (label, value) = match line.find(':') {
+                Some(pos) => (line[..pos].trim(), line[pos + 1..].trim()),
+                None => return Err(FormatError::Invalid(format!("unexpected line `{}`", line))),
+            };
+
+            match label.to_lowercase().as_str() {
+                "name" => name = Some(value.to_string()),
+                "policy" => policy = Some(parse_policy(value)?),
+                "derivation" => {
+                    derivation_path = Some(DerivationPath::from_str(value).map_err(|_| {
+                        FormatError::Invalid(format!("invalid derivation `{}`", value))
+                    })?)
+                }
+                "format" => {
+                    script_type = Some(match value.to_uppercase().as_str() {
+                        "P2SH" => MultisigScriptType::P2sh,
+                        "P2WSH" => MultisigScriptType::P2wsh,
+                        "P2SH-P2WSH" | "P2WSH-P2SH" => MultisigScriptType::P2shP2wsh,
+                        _ => {
+                            return Err(FormatError::Unsupported(format!(
+                                "unknown format `{}`",
+                                value
+                            )))
+                        }
+                    })
+                }
+                _ if label.len() == 8 => {
+                    let fingerprint = Fingerprint::from_str(label).map_err(|_| {
+                        FormatError::Invalid(format!("invalid fingerprint `{}`", label))
+                    })?;
+                    let (xpub, _) = Slip132::decode(value)?;
+                    // the derivation can be omitted for keys directly derived from the master key
+                    let derivation_path = derivation_path
+                        .clone()
+                        .unwrap_or_else(|| DerivationPath::from(vec![]));
+
+                    cosigners.push(AccountKey {
+                        fingerprint,
+                        derivation_path,
+                        xpub,
+                    });
+                }
+                // ignore any other field
+                _ => {}
+            }
+        }
+
+        let (threshold, total) = policy.unwrap_or((cosigners.len(), cosigners.len()));
+        if cosigners.is_empty() || total != cosigners.len() || threshold > total || threshold == 0 {
+            return Err(FormatError::Invalid(format!(
+                "policy {} of {} with {} keys",
+                threshold,
+                total,
+                cosigners.len()
+            )));
+        }
+
+        Ok(ColdcardMultisig {
+            name: name.unwrap_or_default(),
+            setup: MultisigSetup {
+                // Coldcard assumes P2SH if the format is missing
+                script_type: script_type.unwrap_or(MultisigScriptType::P2sh),
+                threshold,
+                cosigners,
+            },
+        })
+    }
+}
+
+fn parse_policy(value: &str) -> Result<(usize, usize), FormatError> {
+    let invalid = || FormatError::Invalid(format!("invalid policy `{}`", value));
+
+    let mut parts = value.split_whitespace();
+    let threshold = parts
+        .next()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(invalid)?;
+    if !matches!(parts.next(), Some(of) if of.eq_ignore_ascii_case("of")) {
+        return Err(invalid());
+    }
+    let total = parts
+        .next()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(invalid)?;
+
+    Ok((threshold, total))
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::Network;
+
+    use super::*;
+    use crate::database::MemoryDatabase;
+
+    const DESCRIPTOR: &str = "wsh(sortedmulti(2,[c55b303f/48'/1'/0'/2']tpubDEU3eBekc59Yw68Nb3dmoXcinikF3qhGW9ymz39kMuBFSHFqX8MFuyt4mC3y9EiCWDmVw1rmQ3s7GjERKkARjGFwA2dAWRLpeMCU5oUuMXU/0/*,[34b00776/48'/1'/0'/2']tpubDE8WcdSH7SBJWrXiJYbWyLuYCoeFv925voAtzYssJsfXgi2WGA3kxMbdp1fP2zWX4sL14jXJyEPtTDjTAJfDrjpPZoTnK9UMcsgAbTD4c7W/0/*))";
+
+    #[test]
+    fn test_coldcard_import() {
+        let import = r#"
+            # Coldcard Multisig setup file (created on C55B303F)
+            #
+            Name: Family vault
+            Policy: 2 of 2
+            Derivation: m/48'/1'/0'/2'
+            Format: P2WSH
+
+            C55B303F: tpubDEU3eBekc59Yw68Nb3dmoXcinikF3qhGW9ymz39kMuBFSHFqX8MFuyt4mC3y9EiCWDmVw1rmQ3s7GjERKkARjGFwA2dAWRLpeMCU5oUuMXU
+            34B00776: Vpub5kzScJ1CfkhFy3818JWABKiyFYHyFsDcsDBpzUJVaizHTQJFT2FpeuUTLGcu57ULh9dTpnpSQ2FF7ddUdH8uXK7ZbzS2sCe9PCELeGwBw4a
+        "#;
+
+        let import = ColdcardMultisig::from_str(import).unwrap();
+        assert_eq!(import.name, "Family vault");
+        assert_eq!(import.setup.threshold, 2);
+        assert_eq!(import.descriptor(), DESCRIPTOR);
+        assert_eq!(
+            import.change_descriptor(),
+            DESCRIPTOR.replace("/0/*", "/1/*")
+        );
+    }
+
+    #[test]
+    fn test_coldcard_import_per_key_derivation() {
+        let import = r#"
+            Name: Mixed
+            Policy: 1 of 2
+            Format: P2SH-P2WSH
+
+            Derivation: m/48'/1'/0'/1'
+            C55B303F: tpubDEU3eBekc59Yw68Nb3dmoXcinikF3qhGW9ymz39kMuBFSHFqX8MFuyt4mC3y9EiCWDmVw1rmQ3s7GjERKkARjGFwA2dAWRLpeMCU5oUuMXU
+            Derivation: m/45'
+            34B00776: tpubDE8WcdSH7SBJWrXiJYbWyLuYCoeFv925voAtzYssJsfXgi2WGA3kxMbdp1fP2zWX4sL14jXJyEPtTDjTAJfDrjpPZoTnK9UMcsgAbTD4c7W
+        "#;
+
+        let import = ColdcardMultisig::from_str(import).unwrap();
+        assert_eq!(import.setup.script_type, MultisigScriptType::P2shP2wsh);
+        assert!(import
+            .descriptor()
+            .starts_with("sh(wsh(sortedmulti(1,[c55b303f/48'/1'/0'/1']"));
+        assert!(import.descriptor().contains(",[34b00776/45']"));
+
+        // the per-key derivations are preserved
+        let exported = ColdcardMultisig::from_str(&import.to_string()).unwrap();
+        assert_eq!(exported, import);
+    }
+
+    #[test]
+    fn test_coldcard_import_invalid() {
+        let import = r#"
+            Policy: 3 of 2
+            C55B303F: tpubDEU3eBekc59Yw68Nb3dmoXcinikF3qhGW9ymz39kMuBFSHFqX8MFuyt4mC3y9EiCWDmVw1rmQ3s7GjERKkARjGFwA2dAWRLpeMCU5oUuMXU
+            34B00776: tpubDE8WcdSH7SBJWrXiJYbWyLuYCoeFv925voAtzYssJsfXgi2WGA3kxMbdp1fP2zWX4sL14jXJyEPtTDjTAJfDrjpPZoTnK9UMcsgAbTD4c7W
+        "#;
+        assert!(matches!(
+            ColdcardMultisig::from_str(import),
+            Err(FormatError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_coldcard_export() {
+        let wallet = Wallet::new_offline(
+            DESCRIPTOR,
+            Some(&DESCRIPTOR.replace("/0/*", "/1/*")),
+            Network::Testnet,
+            MemoryDatabase::default(),
+        )
+        .unwrap();
+
+        let export = ColdcardMultisig::export_wallet(&wallet, "Family vault").unwrap();
+        let text = export.to_string();
+        assert!(text.contains("Policy: 2 of 2\nDerivation: m/48'/1'/0'/2'\nFormat: P2WSH\n"));
+        assert!(text.contains("\nC55B303F: tpubDEU3eBekc59Yw68Nb3dmoXcinikF3qhGW9ymz39kMuBFSHFqX8MFuyt4mC3y9EiCWDmVw1rmQ3s7GjERKkARjGFwA2dAWRLpeMCU5oUuMXU\n"));
+        assert_eq!(ColdcardMultisig::from_str(&text).unwrap(), export);
+    }
+
+    #[test]
+    fn test_coldcard_export_single_sig() {
+        let wallet = Wallet::new_offline(
+            "wpkh([c55b303f/84'/1'/0']tpubDEU3eBekc59Yw68Nb3dmoXcinikF3qhGW9ymz39kMuBFSHFqX8MFuyt4mC3y9EiCWDmVw1rmQ3s7GjERKkARjGFwA2dAWRLpeMCU5oUuMXU/0/*)",
+            None,
+            Network::Testnet,
+            MemoryDatabase::default(),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            ColdcardMultisig::export_wallet(&wallet, "Single"),
+            Err(FormatError::Unsupported(_))
+        ));
+    }
+}
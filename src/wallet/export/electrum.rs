@@ -0,0 +1,422 @@
+// Bitcoin Dev Kit
+// Written in 2021 by the Bitcoin Dev Kit Developers
+//
+// Copyright (c) 2020-2021 Bitcoin Dev Kit Developers
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! Electrum wallet files
+//!
+//! This module implements the JSON wallet files of [Electrum](https://electrum.org), for
+//! both `standard` single-signature wallets and `MofN` multisig wallets. Electrum stores the
+//! script type of the wallet in the encoding of the extended keys, as defined by
+//! [SLIP-132](https://github.com/satoshilabs/slips/blob/master/slip-0132.md).
+//!
+//! Only the public part of the keystores is imported and exported: the files must not be
+//! encrypted, and the private keys they contain are ignored.
+//!
+//! ## Example
+//!
+//! ```
+//! # use std::str::FromStr;
+//! # use bitcoin::*;
+//! # use bdk::database::*;
+//! # use bdk::wallet::export::electrum::ElectrumWallet;
+//! # use bdk::*;
+//! let import = r#"{
+//!     "keystore": {
+//!         "type": "bip32",
+//!         "xpub": "vpub5Z1pfF8AHmTiXXwLsVXAd7gVC1MHccnDoCHbGi193bmebM5y1k9QvVBkSTUMpJMEUxE9tHExf8AckcQhtC5QiJQsfyozz9dsx9G8oxxRm7J",
+//!         "derivation": "m/84'/1'/0'",
+//!         "root_fingerprint": "c258d2e4"
+//!     },
+//!     "wallet_type": "standard",
+//!     "use_encryption": false,
+//!     "seed_version": 17
+//! }"#;
+//!
+//! let import = ElectrumWallet::from_str(import)?;
+//! let wallet = Wallet::new_offline(
+//!     &import.descriptor(),
+//!     Some(&import.change_descriptor()),
+//!     import.network(),
+//!     MemoryDatabase::default(),
+//! )?;
+//! # Ok::<_, Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use bitcoin::util::bip32::{DerivationPath, Fingerprint};
+use bitcoin::Network;
+
+use miniscript::descriptor::ShInner;
+use miniscript::Descriptor;
+
+use serde_json::{json, Map, Value};
+
+use super::{
+    wallet_descriptor, AccountKey, FormatError, MultisigScriptType, MultisigSetup, Slip132,
+};
+use crate::database::BatchDatabase;
+use crate::types::KeychainKind;
+use crate::wallet::Wallet;
+
+// Version of the wallet files written by `ElectrumWallet`
+const SEED_VERSION: u64 = 17;
+
+/// Script type of a single-signature wallet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StandardScriptType {
+    /// Legacy P2PKH, `pkh()`
+    P2pkh,
+    /// Nested segwit P2WPKH-P2SH, `sh(wpkh())`
+    P2shP2wpkh,
+    /// Native segwit P2WPKH, `wpkh()`
+    P2wpkh,
+}
+
+/// An Electrum wallet file
+///
+/// For a usage example see [this module](crate::wallet::export::electrum)'s documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElectrumWallet {
+    /// A `standard` single-signature wallet
+    Standard {
+        /// Script type
+        script_type: StandardScriptType,
+        /// Key of the wallet
+        keystore: AccountKey,
+    },
+    /// A `MofN` multisig wallet
+    Multisig(MultisigSetup),
+}
+
+impl ElectrumWallet {
+    /// Export a wallet
+    ///
+    /// The wallet must either be a `pkh()`, `sh(wpkh())` or `wpkh()` wallet or a multisig wallet
+    /// as described in [`MultisigSetup::from_wallet`], with extended keys derived with `/0/*` for
+    /// the external keychain and `/1/*` for the internal one.
+    pub fn export_wallet<B, D: BatchDatabase>(wallet: &Wallet<B, D>) -> Result<Self, FormatError> {
+        let descriptor = wallet_descriptor(wallet)?;
+        let (script_type, key) = match &descriptor {
+            Descriptor::Pkh(pkh) => (StandardScriptType::P2pkh, pkh.as_inner()),
+            Descriptor::Wpkh(wpkh) => (StandardScriptType::P2wpkh, wpkh.as_inner()),
+            Descriptor::Sh(sh) => match sh.as_inner() {
+                ShInner::Wpkh(wpkh) => (StandardScriptType::P2shP2wpkh, wpkh.as_inner()),
+                _ => return MultisigSetup::from_wallet(wallet).map(ElectrumWallet::Multisig),
+            },
+            _ => return MultisigSetup::from_wallet(wallet).map(ElectrumWallet::Multisig),
+        };
+
+        Ok(ElectrumWallet::Standard {
+            script_type,
+            keystore: AccountKey::from_descriptor_key(key)?,
+        })
+    }
+
+    /// Return the descriptor of a keychain of the wallet
+    fn keychain_descriptor(&self, keychain: KeychainKind) -> String {
+        match self {
+            ElectrumWallet::Standard {
+                script_type,
+                keystore,
+            } => {
+                let key = keystore.descriptor_key(keychain);
+                match script_type {
+                    StandardScriptType::P2pkh => format!("pkh({})", key),
+                    StandardScriptType::P2shP2wpkh => format!("sh(wpkh({}))", key),
+                    StandardScriptType::P2wpkh => format!("wpkh({})", key),
+                }
+            }
+            ElectrumWallet::Multisig(setup) => setup.descriptor(keychain),
+        }
+    }
+
+    /// Return the external descriptor
+    pub fn descriptor(&self) -> String {
+        self.keychain_descriptor(KeychainKind::External)
+    }
+
+    /// Return the internal descriptor
+    pub fn change_descriptor(&self) -> String {
+        self.keychain_descriptor(KeychainKind::Internal)
+    }
+
+    /// Return the network of the wallet
+    pub fn network(&self) -> Network {
+        match self {
+            ElectrumWallet::Standard { keystore, .. } => keystore.xpub.network,
+            ElectrumWallet::Multisig(setup) => setup.network(),
+        }
+    }
+}
+
+fn keystore_to_json(key: &AccountKey, encoding: Slip132) -> Value {
+    json!({
+        "type": "bip32",
+        "xpub": encoding.encode(&key.xpub),
+        "xprv": null,
+        "derivation": key.derivation_path.to_string(),
+        "root_fingerprint": key.fingerprint.to_string(),
+        "label": "",
+    })
+}
+
+fn keystore_from_json(keystore: &Value) -> Result<(AccountKey, Slip132), FormatError> {
+    let field = |name: &str| keystore.get(name).and_then(Value::as_str);
+
+    match field("type") {
+        Some("bip32") | Some("hardware") => {}
+        Some(other) => {
+            return Err(FormatError::Unsupported(format!(
+                "keystores of type `{}` are not supported",
+                other
+            )))
+        }
+        None => return Err(FormatError::Invalid("missing keystore type".into())),
+    }
+
+    let xpub = field("xpub").ok_or_else(|| FormatError::Invalid("missing xpub".into()))?;
+    let (xpub, encoding) = Slip132::decode(xpub)?;
+
+    let (fingerprint, derivation_path) = match (field("root_fingerprint"), field("derivation")) {
+        (Some(fingerprint), Some(path)) => (
+            Fingerprint::from_str(fingerprint).map_err(|_| {
+                FormatError::Invalid(format!("invalid fingerprint `{}`", fingerprint))
+            })?,
+            DerivationPath::from_str(path)
+                .map_err(|_| FormatError::Invalid(format!("invalid derivation `{}`", path)))?,
+        ),
+        // Old wallet files don't store the origin of the key
+        _ => (xpub.fingerprint(), DerivationPath::from(vec![])),
+    };
+
+    Ok((
+        AccountKey {
+            fingerprint,
+            derivation_path,
+            xpub,
+        },
+        encoding,
+    ))
+}
+
+impl fmt::Display for ElectrumWallet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut file = Map::new();
+        match self {
+            ElectrumWallet::Standard {
+                script_type,
+                keystore,
+            } => {
+                let encoding = match script_type {
+                    StandardScriptType::P2pkh => Slip132::Xpub,
+                    StandardScriptType::P2shP2wpkh => Slip132::Ypub,
+                    StandardScriptType::P2wpkh => Slip132::Zpub,
+                };
+                file.insert("keystore".into(), keystore_to_json(keystore, encoding));
+                file.insert("wallet_type".into(), "standard".into());
+            }
+            ElectrumWallet::Multisig(setup) => {
+                let encoding = match setup.script_type {
+                    MultisigScriptType::P2sh => Slip132::Xpub,
+                    MultisigScriptType::P2shP2wsh => Slip132::MultisigYpub,
+                    MultisigScriptType::P2wsh => Slip132::MultisigZpub,
+                };
+                for (i, key) in setup.cosigners.iter().enumerate() {
+                    file.insert(format!("x{}/", i + 1), keystore_to_json(key, encoding));
+                }
+                file.insert(
+                    "wallet_type".into(),
+                    format!("{}of{}", setup.threshold, setup.cosigners.len()).into(),
+                );
+            }
+        }
+        file.insert("use_encryption".into(), false.into());
+        file.insert("seed_version".into(), SEED_VERSION.into());
+
+        write!(f, "{}", serde_json::to_string_pretty(&file).unwrap())
+    }
+}
+
+impl FromStr for ElectrumWallet {
+    type Err = FormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let file: Value = serde_json::from_str(s)?;
+
+        if file.get("use_encryption").and_then(Value::as_bool) == Some(true) {
+            return Err(FormatError::Unsupported(
+                "encrypted wallet files are not supported".into(),
+            ));
+        }
+        let wallet_type = file
+            .get("wallet_type")
+            .and_then(Value::as_str)
+            .ok_or_else(|| FormatError::Invalid("missing wallet type".into()))?;
+
+        if wallet_type == "standard" {
+            let keystore = file
+                .get("keystore")
+                .ok_or_else(|| FormatError::Invalid("missing keystore".into()))?;
+            let (keystore, encoding) = keystore_from_json(keystore)?;
+            let script_type = match encoding {
+                Slip132::Xpub => StandardScriptType::P2pkh,
+                Slip132::Ypub => StandardScriptType::P2shP2wpkh,
+                Slip132::Zpub => StandardScriptType::P2wpkh,
+                _ => {
+                    return Err(FormatError::Invalid(
+                        "multisig key in a standard wallet".into(),
+                    ))
+                }
+            };
+
+            return Ok(ElectrumWallet::Standard {
+                script_type,
+                keystore,
+            });
+        }
+
+        let invalid_type = || FormatError::Unsupported(format!("wallet type `{}`", wallet_type));
+        let mut parts = wallet_type.splitn(2, "of");
+        let (threshold, n) = match (parts.next(), parts.next()) {
+            (Some(m), Some(n)) => (
+                m.parse::<usize>().map_err(|_| invalid_type())?,
+                n.parse::<usize>().map_err(|_| invalid_type())?,
+            ),
+            _ => return Err(invalid_type()),
+        };
+        if threshold == 0 || threshold > n {
+            return Err(invalid_type());
+        }
+
+        let mut script_type = None;
+        let mut cosigners = Vec::with_capacity(n);
+        for i in 1..=n {
+            let keystore = file
+                .get(format!("x{}/", i))
+                .ok_or_else(|| FormatError::Invalid(format!("missing keystore `x{}/`", i)))?;
+            let (key, encoding) = keystore_from_json(keystore)?;
+            let key_script_type = match encoding {
+                Slip132::Xpub => MultisigScriptType::P2sh,
+                Slip132::MultisigYpub => MultisigScriptType::P2shP2wsh,
+                Slip132::MultisigZpub => MultisigScriptType::P2wsh,
+                _ => {
+                    return Err(FormatError::Invalid(
+                        "single-signature key in a multisig wallet".into(),
+                    ))
+                }
+            };
+            if script_type.get_or_insert(key_script_type) != &key_script_type {
+                return Err(FormatError::Invalid(
+                    "the keys have different script types".into(),
+                ));
+            }
+
+            cosigners.push(key);
+        }
+
+        Ok(ElectrumWallet::Multisig(MultisigSetup {
+            script_type: script_type.ok_or_else(invalid_type)?,
+            threshold,
+            cosigners,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::database::MemoryDatabase;
+
+    const STANDARD: &str = "wpkh([c258d2e4/84'/1'/0']tpubDD3ynpHgJQW8VvWRzQ5WFDCrs4jqVFGHB3vLC3r49XHJSqP8bHKdK4AriuUKLccK68zfzowx7YhmDN8SiSkgCDENUFx9qVw65YyqM78vyVe/0/*)";
+    const MULTISIG: &str = "wsh(sortedmulti(2,[c55b303f/48'/1'/0'/2']tpubDEU3eBekc59Yw68Nb3dmoXcinikF3qhGW9ymz39kMuBFSHFqX8MFuyt4mC3y9EiCWDmVw1rmQ3s7GjERKkARjGFwA2dAWRLpeMCU5oUuMXU/0/*,[34b00776/48'/1'/0'/2']tpubDE8WcdSH7SBJWrXiJYbWyLuYCoeFv925voAtzYssJsfXgi2WGA3kxMbdp1fP2zWX4sL14jXJyEPtTDjTAJfDrjpPZoTnK9UMcsgAbTD4c7W/0/*))";
+
+    fn roundtrip(descriptor: &str) -> (ElectrumWallet, Value) {
+        let wallet = Wallet::new_offline(
+            descriptor,
+            Some(&descriptor.replace("/0/*", "/1/*")),
+            Network::Testnet,
+            MemoryDatabase::default(),
+        )
+        .unwrap();
+
+        let export = ElectrumWallet::export_wallet(&wallet).unwrap();
+        let file = export.to_string();
+        let import = ElectrumWallet::from_str(&file).unwrap();
+        assert_eq!(import, export);
+        assert_eq!(import.descriptor(), descriptor);
+        assert_eq!(
+            import.change_descriptor(),
+            descriptor.replace("/0/*", "/1/*")
+        );
+        assert_eq!(import.network(), Network::Testnet);
+
+        (import, serde_json::from_str(&file).unwrap())
+    }
+
+    #[test]
+    fn test_electrum_standard() {
+        let (import, file) = roundtrip(STANDARD);
+
+        assert!(matches!(
+            import,
+            ElectrumWallet::Standard {
+                script_type: StandardScriptType::P2wpkh,
+                ..
+            }
+        ));
+        assert_eq!(file["wallet_type"], "standard");
+        assert_eq!(file["keystore"]["derivation"], "m/84'/1'/0'");
+        assert_eq!(file["keystore"]["root_fingerprint"], "c258d2e4");
+        assert_eq!(file["keystore"]["xpub"], "vpub5Z1pfF8AHmTiXXwLsVXAd7gVC1MHccnDoCHbGi193bmebM5y1k9QvVBkSTUMpJMEUxE9tHExf8AckcQhtC5QiJQsfyozz9dsx9G8oxxRm7J");
+    }
+
+    #[test]
+    fn test_electrum_multisig() {
+        let (import, file) = roundtrip(MULTISIG);
+
+        assert!(matches!(import, ElectrumWallet::Multisig(_)));
+        assert_eq!(file["wallet_type"], "2of2");
+        assert_eq!(file["x2/"]["root_fingerprint"], "34b00776");
+        assert!(file["x1/"]["xpub"].as_str().unwrap().starts_with("Vpub"));
+    }
+
+    #[test]
+    fn test_electrum_import_mixed_script_types() {
+        let file = format!(
+            r#"{{
+                "x1/": {{ "type": "bip32", "xpub": "{}" }},
+                "x2/": {{ "type": "hardware", "xpub": "{}" }},
+                "wallet_type": "1of2",
+                "seed_version": 17
+            }}"#,
+            "tpubDEU3eBekc59Yw68Nb3dmoXcinikF3qhGW9ymz39kMuBFSHFqX8MFuyt4mC3y9EiCWDmVw1rmQ3s7GjERKkARjGFwA2dAWRLpeMCU5oUuMXU",
+            Slip132::MultisigZpub.encode(
+                &Slip132::decode("tpubDE8WcdSH7SBJWrXiJYbWyLuYCoeFv925voAtzYssJsfXgi2WGA3kxMbdp1fP2zWX4sL14jXJyEPtTDjTAJfDrjpPZoTnK9UMcsgAbTD4c7W").unwrap().0
+            ),
+        );
+
+        assert!(matches!(
+            ElectrumWallet::from_str(&file),
+            Err(FormatError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_electrum_import_encrypted() {
+        let file = r#"{ "wallet_type": "standard", "use_encryption": true }"#;
+
+        assert!(matches!(
+            ElectrumWallet::from_str(file),
+            Err(FormatError::Unsupported(_))
+        ));
+    }
+}
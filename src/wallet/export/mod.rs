@@ -13,6 +13,18 @@
 //!
 //! This modules implements the wallet export format used by [FullyNoded](https://github.com/Fonta1n3/FullyNoded/blob/10b7808c8b929b171cca537fb50522d015168ac9/Docs/Wallets/Wallet-Export-Spec.md).
 //!
+//! The submodules import and export the wallet files of other wallets, converting them to and
+//! from descriptors:
+//!
+//! - [`electrum`]: Electrum wallet files, both single-signature and multisig
+//! - [`coldcard`]: Coldcard multisig setup files, also used by Sparrow and other wallets
+//! - [`specter`]: Specter JSON wallet backups
+//! - [`caravan`]: Caravan multisig configs, also used by Sparrow
+//!
+//! The multisig formats are all based on a [`MultisigSetup`], that describes a
+//! `sortedmulti()` wallet whose keys are derived with `/0/*` for the external keychain and
+//! `/1/*` for the internal one.
+//!
 //! ## Examples
 //!
 //! ### Import from JSON
@@ -59,16 +71,27 @@
 //! # Ok::<_, bdk::Error>(())
 //! ```
 
+use std::fmt;
 use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 
-use miniscript::descriptor::{ShInner, WshInner};
+use bitcoin::util::base58;
+use bitcoin::util::bip32::{ChildNumber, DerivationPath, ExtendedPubKey, Fingerprint};
+use bitcoin::Network;
+
+use miniscript::descriptor::{ShInner, Wildcard, WshInner};
 use miniscript::{Descriptor, DescriptorPublicKey, ScriptContext, Terminal};
 
 use crate::database::BatchDatabase;
+use crate::types::KeychainKind;
 use crate::wallet::Wallet;
 
+pub mod caravan;
+pub mod coldcard;
+pub mod electrum;
+pub mod specter;
+
 /// Structure that contains the export of a wallet
 ///
 /// For a usage example see [this module](crate::wallet::export)'s documentation.
@@ -202,6 +225,277 @@ impl WalletExport {
     }
 }
 
+/// Errors while importing or exporting the wallet files of other wallets
+#[derive(Debug)]
+pub enum FormatError {
+    /// The wallet file is malformed
+    Invalid(String),
+    /// The wallet can't be represented in the requested format
+    Unsupported(String),
+    /// Error while parsing or serializing JSON
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FormatError::Invalid(msg) => write!(f, "Invalid wallet file: {}", msg),
+            FormatError::Unsupported(msg) => write!(f, "Unsupported wallet: {}", msg),
+            FormatError::Json(e) => write!(f, "JSON error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl_error!(serde_json::Error, Json, FormatError);
+
+/// An account-level extended public key, together with its origin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountKey {
+    /// Fingerprint of the master key
+    pub fingerprint: Fingerprint,
+    /// Derivation path from the master key to the account key
+    pub derivation_path: DerivationPath,
+    /// The account key
+    pub xpub: ExtendedPubKey,
+}
+
+impl AccountKey {
+    // Return the key expression used in the descriptors, e.g. `[c55b303f/48'/1'/0'/2']tpub.../0/*`
+    fn descriptor_key(&self, keychain: KeychainKind) -> String {
+        let index = match keychain {
+            KeychainKind::External => 0,
+            KeychainKind::Internal => 1,
+        };
+        // `DerivationPath` is displayed as "m/a/b", we only keep the "/a/b" part
+        let path = self.derivation_path.to_string();
+
+        format!(
+            "[{}{}]{}/{}/*",
+            self.fingerprint,
+            &path[1..],
+            self.xpub,
+            index
+        )
+    }
+
+    // Extract the account key from a descriptor key like the ones generated by `descriptor_key`
+    fn from_descriptor_key(key: &DescriptorPublicKey) -> Result<Self, FormatError> {
+        let xkey = match key {
+            DescriptorPublicKey::XPub(xkey) => xkey,
+            DescriptorPublicKey::SinglePub(_) => {
+                return Err(FormatError::Unsupported(
+                    "single keys are not supported, only extended keys".into(),
+                ))
+            }
+        };
+        if xkey.wildcard != Wildcard::Unhardened
+            || xkey.derivation_path.as_ref() != [ChildNumber::Normal { index: 0 }]
+        {
+            return Err(FormatError::Unsupported(format!(
+                "the extended keys must be derived with `/0/*`: {}",
+                key
+            )));
+        }
+
+        let (fingerprint, derivation_path) = match &xkey.origin {
+            Some((fingerprint, path)) => (*fingerprint, path.clone()),
+            None => (xkey.xkey.fingerprint(), DerivationPath::from(vec![])),
+        };
+
+        Ok(AccountKey {
+            fingerprint,
+            derivation_path,
+            xpub: xkey.xkey,
+        })
+    }
+}
+
+// Return the descriptor of the external keychain of `wallet` and check that the internal one, if
+// present, only differs in the `/0/*` derivation
+fn wallet_descriptor<B, D: BatchDatabase>(
+    wallet: &Wallet<B, D>,
+) -> Result<Descriptor<DescriptorPublicKey>, FormatError> {
+    let descriptor = wallet.descriptor.clone();
+    if let Some(change_descriptor) = &wallet.change_descriptor {
+        let expected = descriptor.to_string().replace("/0/*", "/1/*");
+        if remove_checksum(change_descriptor.to_string()) != remove_checksum(expected) {
+            return Err(FormatError::Unsupported(
+                "incompatible change descriptor".into(),
+            ));
+        }
+    }
+
+    Ok(descriptor)
+}
+
+// Encodings of the extended public keys defined by SLIP-132, used by some wallets to also store
+// the script type in the key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Slip132 {
+    Xpub,
+    Ypub,
+    Zpub,
+    MultisigYpub,
+    MultisigZpub,
+}
+
+impl Slip132 {
+    const ALL: [Slip132; 5] = [
+        Slip132::Xpub,
+        Slip132::Ypub,
+        Slip132::Zpub,
+        Slip132::MultisigYpub,
+        Slip132::MultisigZpub,
+    ];
+
+    // version bytes on mainnet and testnet
+    fn versions(self) -> ([u8; 4], [u8; 4]) {
+        match self {
+            Slip132::Xpub => ([0x04, 0x88, 0xb2, 0x1e], [0x04, 0x35, 0x87, 0xcf]),
+            Slip132::Ypub => ([0x04, 0x9d, 0x7c, 0xb2], [0x04, 0x4a, 0x52, 0x62]),
+            Slip132::Zpub => ([0x04, 0xb2, 0x47, 0x46], [0x04, 0x5f, 0x1c, 0xf6]),
+            Slip132::MultisigYpub => ([0x02, 0x95, 0xb4, 0x3f], [0x02, 0x42, 0x89, 0xef]),
+            Slip132::MultisigZpub => ([0x02, 0xaa, 0x7e, 0xd3], [0x02, 0x57, 0x54, 0x83]),
+        }
+    }
+
+    fn encode(self, xpub: &ExtendedPubKey) -> String {
+        let (mainnet, testnet) = self.versions();
+        let mut data = xpub.encode();
+        data[..4].copy_from_slice(match xpub.network {
+            Network::Bitcoin => &mainnet,
+            _ => &testnet,
+        });
+
+        base58::check_encode_slice(&data)
+    }
+
+    fn decode(s: &str) -> Result<(ExtendedPubKey, Slip132), FormatError> {
+        let invalid = || FormatError::Invalid(format!("invalid extended key `{}`", s));
+
+        let mut data = base58::from_check(s).map_err(|_| invalid())?;
+        if data.len() != 78 {
+            return Err(invalid());
+        }
+        let (encoding, is_mainnet) = Slip132::ALL
+            .iter()
+            .find_map(|encoding| {
+                let (mainnet, testnet) = encoding.versions();
+                match &data[..4] {
+                    version if version == mainnet => Some((*encoding, true)),
+                    version if version == testnet => Some((*encoding, false)),
+                    _ => None,
+                }
+            })
+            .ok_or_else(invalid)?;
+
+        let (mainnet, testnet) = Slip132::Xpub.versions();
+        data[..4].copy_from_slice(if is_mainnet { &mainnet } else { &testnet });
+        let xpub = ExtendedPubKey::decode(&data).map_err(|_| invalid())?;
+
+        Ok((xpub, encoding))
+    }
+}
+
+/// Script type of a multisig wallet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultisigScriptType {
+    /// Legacy P2SH, `sh(sortedmulti())`
+    P2sh,
+    /// Native segwit P2WSH, `wsh(sortedmulti())`
+    P2wsh,
+    /// Nested segwit P2WSH-P2SH, `sh(wsh(sortedmulti()))`
+    P2shP2wsh,
+}
+
+/// A `sortedmulti()` wallet, the common base of all the multisig wallet files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultisigSetup {
+    /// Script type
+    pub script_type: MultisigScriptType,
+    /// Number of signatures required
+    pub threshold: usize,
+    /// Keys of the cosigners
+    pub cosigners: Vec<AccountKey>,
+}
+
+impl MultisigSetup {
+    /// Parse the external descriptor of a multisig wallet
+    pub fn from_descriptor(descriptor: &str) -> Result<Self, FormatError> {
+        let descriptor = Descriptor::<DescriptorPublicKey>::from_str(descriptor)
+            .map_err(|e| FormatError::Invalid(e.to_string()))?;
+
+        Self::from_parsed_descriptor(&descriptor)
+    }
+
+    /// Extract the multisig setup of a [`Wallet`]
+    ///
+    /// This function returns an error if the wallet's descriptor is not a `sortedmulti()` of
+    /// extended keys derived with `/0/*`, or if its change descriptor is not the same with `/1/*`.
+    pub fn from_wallet<B, D: BatchDatabase>(wallet: &Wallet<B, D>) -> Result<Self, FormatError> {
+        Self::from_parsed_descriptor(&wallet_descriptor(wallet)?)
+    }
+
+    fn from_parsed_descriptor(
+        descriptor: &Descriptor<DescriptorPublicKey>,
+    ) -> Result<Self, FormatError> {
+        let (script_type, multi) = match descriptor {
+            Descriptor::Sh(sh) => match sh.as_inner() {
+                ShInner::SortedMulti(multi) => (MultisigScriptType::P2sh, (multi.k, &multi.pks)),
+                ShInner::Wsh(wsh) => match wsh.as_inner() {
+                    WshInner::SortedMulti(multi) => {
+                        (MultisigScriptType::P2shP2wsh, (multi.k, &multi.pks))
+                    }
+                    _ => return Err(FormatError::Unsupported("not a sortedmulti()".into())),
+                },
+                _ => return Err(FormatError::Unsupported("not a sortedmulti()".into())),
+            },
+            Descriptor::Wsh(wsh) => match wsh.as_inner() {
+                WshInner::SortedMulti(multi) => (MultisigScriptType::P2wsh, (multi.k, &multi.pks)),
+                _ => return Err(FormatError::Unsupported("not a sortedmulti()".into())),
+            },
+            _ => return Err(FormatError::Unsupported("not a sortedmulti()".into())),
+        };
+        let (threshold, keys) = multi;
+
+        Ok(MultisigSetup {
+            script_type,
+            threshold,
+            cosigners: keys
+                .iter()
+                .map(AccountKey::from_descriptor_key)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Return the descriptor of a keychain of the wallet
+    pub fn descriptor(&self, keychain: KeychainKind) -> String {
+        let keys = self
+            .cosigners
+            .iter()
+            .map(|key| key.descriptor_key(keychain))
+            .collect::<Vec<_>>()
+            .join(",");
+        let multi = format!("sortedmulti({},{})", self.threshold, keys);
+
+        match self.script_type {
+            MultisigScriptType::P2sh => format!("sh({})", multi),
+            MultisigScriptType::P2wsh => format!("wsh({})", multi),
+            MultisigScriptType::P2shP2wsh => format!("sh(wsh({}))", multi),
+        }
+    }
+
+    /// Return the network of the keys
+    pub fn network(&self) -> Network {
+        self.cosigners
+            .first()
+            .map(|key| key.xpub.network)
+            .unwrap_or(Network::Bitcoin)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::str::FromStr;
@@ -229,8 +523,10 @@ mod test {
             confirmation_time: Some(ConfirmationTime {
                 timestamp: 12345678,
                 height: 5000,
+                block_hash: None,
             }),
             verified: true,
+            ancestors_fee: None,
         })
         .unwrap();
 
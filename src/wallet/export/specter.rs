@@ -0,0 +1,228 @@
+// Bitcoin Dev Kit
+// Written in 2021 by the Bitcoin Dev Kit Developers
+//
+// Copyright (c) 2020-2021 Bitcoin Dev Kit Developers
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! Specter wallet backups
+//!
+//! This module implements the JSON wallet backups of
+//! [Specter Desktop](https://github.com/cryptoadvance/specter-desktop). The backup contains the
+//! external descriptor of the wallet, the internal one is obtained by replacing `/0/*` with
+//! `/1/*`. The `/{0,1}/*` and `/<0;1>/*` multipath notations are also accepted on import.
+//!
+//! ## Example
+//!
+//! ```
+//! # use std::str::FromStr;
+//! # use bitcoin::*;
+//! # use bdk::database::*;
+//! # use bdk::wallet::export::specter::SpecterWallet;
+//! # use bdk::*;
+//! let import = r#"{
+//!     "label": "Savings",
+//!     "blockheight": 1782088,
+//!     "descriptor": "wpkh([c258d2e4/84h/1h/0h]tpubDD3ynpHgJQW8VvWRzQ5WFDCrs4jqVFGHB3vLC3r49XHJSqP8bHKdK4AriuUKLccK68zfzowx7YhmDN8SiSkgCDENUFx9qVw65YyqM78vyVe/{0,1}/*)",
+//!     "devices": [{ "type": "coldcard", "label": "My Coldcard" }]
+//! }"#;
+//!
+//! let import = SpecterWallet::from_str(import)?;
+//! let wallet = Wallet::new_offline(
+//!     &import.descriptor(),
+//!     Some(&import.change_descriptor()),
+//!     Network::Testnet,
+//!     MemoryDatabase::default(),
+//! )?;
+//! # Ok::<_, Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use miniscript::{ForEach, ForEachKey};
+
+use super::{remove_checksum, wallet_descriptor, FormatError};
+use crate::database::BatchDatabase;
+use crate::wallet::Wallet;
+
+/// A Specter wallet backup
+///
+/// For a usage example see [this module](crate::wallet::export::specter)'s documentation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpecterWallet {
+    /// Name of the wallet
+    pub label: String,
+    /// Earliest block to rescan when looking for the wallet's transactions
+    #[serde(default)]
+    pub blockheight: u32,
+    /// External descriptor of the wallet
+    pub descriptor: String,
+    /// Devices holding the keys of the wallet
+    #[serde(default)]
+    pub devices: Vec<SpecterDevice>,
+}
+
+/// A device of a [`SpecterWallet`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpecterDevice {
+    /// Type of the device, e.g. `coldcard` or `trezor`
+    #[serde(rename = "type")]
+    pub device_type: String,
+    /// Name of the device
+    pub label: String,
+}
+
+impl SpecterWallet {
+    /// Export a wallet
+    ///
+    /// Since the type of the devices holding the keys is unknown, one device of type `other` is
+    /// added for every key, labeled with the fingerprint of the key.
+    ///
+    /// If `include_blockheight` is `true` the height of the oldest transaction in the `wallet`'s
+    /// database is used as `blockheight`, otherwise it's set to `0`.
+    pub fn export_wallet<B, D: BatchDatabase>(
+        wallet: &Wallet<B, D>,
+        label: &str,
+        include_blockheight: bool,
+    ) -> Result<Self, FormatError> {
+        let descriptor = wallet_descriptor(wallet)?;
+        if !descriptor.to_string().contains("/0/*") {
+            return Err(FormatError::Unsupported(
+                "the descriptor doesn't contain a `/0/*` derivation".into(),
+            ));
+        }
+
+        let mut devices = Vec::new();
+        descriptor.for_each_key(|key| {
+            if let ForEach::Key(key) = key {
+                devices.push(SpecterDevice {
+                    device_type: "other".into(),
+                    label: key.master_fingerprint().to_string(),
+                });
+            }
+            true
+        });
+
+        let blockheight = match wallet.database.borrow().iter_txs(false) {
+            Ok(txs) if include_blockheight => txs
+                .into_iter()
+                .filter_map(|tx| tx.confirmation_time.map(|c| c.height))
+                .min()
+                .unwrap_or(0),
+            _ => 0,
+        };
+
+        Ok(SpecterWallet {
+            label: label.into(),
+            blockheight,
+            descriptor: descriptor.to_string(),
+            devices,
+        })
+    }
+
+    /// Return the external descriptor
+    pub fn descriptor(&self) -> String {
+        remove_checksum(self.descriptor.clone())
+            .replace("/{0,1}/*", "/0/*")
+            .replace("/<0;1>/*", "/0/*")
+    }
+
+    /// Return the internal descriptor
+    pub fn change_descriptor(&self) -> String {
+        self.descriptor().replace("/0/*", "/1/*")
+    }
+}
+
+impl fmt::Display for SpecterWallet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", serde_json::to_string(self).unwrap())
+    }
+}
+
+impl FromStr for SpecterWallet {
+    type Err = FormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bitcoin::Network;
+
+    use super::*;
+    use crate::database::MemoryDatabase;
+
+    const DESCRIPTOR: &str = "wpkh([c258d2e4/84'/1'/0']tpubDD3ynpHgJQW8VvWRzQ5WFDCrs4jqVFGHB3vLC3r49XHJSqP8bHKdK4AriuUKLccK68zfzowx7YhmDN8SiSkgCDENUFx9qVw65YyqM78vyVe/0/*)";
+
+    #[test]
+    fn test_specter_multipath_import() {
+        for descriptor in &[
+            DESCRIPTOR.replace("/0/*", "/{0,1}/*"),
+            DESCRIPTOR.replace("/0/*", "/<0;1>/*"),
+        ] {
+            let import = SpecterWallet::from_str(&format!(
+                r#"{{"label":"Savings","descriptor":"{}","devices":[]}}"#,
+                descriptor
+            ))
+            .unwrap();
+
+            assert_eq!(import.blockheight, 0);
+            assert_eq!(import.descriptor(), DESCRIPTOR);
+            assert_eq!(
+                import.change_descriptor(),
+                DESCRIPTOR.replace("/0/*", "/1/*")
+            );
+        }
+    }
+
+    #[test]
+    fn test_specter_export() {
+        let wallet = Wallet::new_offline(
+            DESCRIPTOR,
+            Some(&DESCRIPTOR.replace("/0/*", "/1/*")),
+            Network::Testnet,
+            MemoryDatabase::default(),
+        )
+        .unwrap();
+
+        let export = SpecterWallet::export_wallet(&wallet, "Savings", true).unwrap();
+        assert_eq!(export.label, "Savings");
+        assert_eq!(export.blockheight, 0);
+        assert_eq!(export.descriptor(), DESCRIPTOR);
+        assert_eq!(
+            export.devices,
+            vec![SpecterDevice {
+                device_type: "other".into(),
+                label: "c258d2e4".into(),
+            }]
+        );
+
+        let import = SpecterWallet::from_str(&export.to_string()).unwrap();
+        assert_eq!(import, export);
+    }
+
+    #[test]
+    fn test_specter_export_incompatible_change() {
+        let wallet = Wallet::new_offline(
+            DESCRIPTOR,
+            Some(&DESCRIPTOR.replace("/0/*", "/2/*")),
+            Network::Testnet,
+            MemoryDatabase::default(),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            SpecterWallet::export_wallet(&wallet, "Savings", false),
+            Err(FormatError::Unsupported(_))
+        ));
+    }
+}
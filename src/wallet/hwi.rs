@@ -0,0 +1,617 @@
+// Bitcoin Dev Kit
+// Written in 2021 by the Bitcoin Dev Kit Developers
+//
+// Copyright (c) 2020-2021 Bitcoin Dev Kit Developers
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! Hardware wallets
+//!
+//! This module provides an [`HwiDevice`] that talks to a hardware wallet using the JSON command
+//! line interface of [HWI](https://github.com/bitcoin-core/HWI), an [`HwiSigner`] that
+//! delegates the signing of the PSBTs created by a [`Wallet`](super::Wallet) to the device, and
+//! an [`HwiAddressValidator`] that shows the addresses generated by the wallet on the device, so
+//! that the user can check them.
+//!
+//! By default the commands are run by calling the `hwi` executable found in the `PATH`, a
+//! different [`HwiTransport`] can be used to forward them elsewhere.
+//!
+//! ## Example
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use std::str::FromStr;
+//! # use bitcoin::*;
+//! # use bitcoin::util::bip32::{DerivationPath, Fingerprint};
+//! # use bdk::database::*;
+//! # use bdk::signer::SignerOrdering;
+//! # use bdk::wallet::hwi::*;
+//! # use bdk::*;
+//! let fingerprint = Fingerprint::from_str("8a4f9d31")?;
+//! let device = HwiDevice::new(fingerprint, Network::Testnet);
+//!
+//! let path = DerivationPath::from_str("m/84'/1'/0'")?;
+//! let xpub = device.get_xpub(&path)?;
+//! let descriptor = format!("wpkh([{}/84'/1'/0']{}/0/*)", fingerprint, xpub);
+//!
+//! let mut wallet = Wallet::new_offline(&descriptor, None, Network::Testnet, MemoryDatabase::default())?;
+//! wallet.add_signer(
+//!     KeychainKind::External,
+//!     SignerOrdering(200),
+//!     Arc::new(HwiSigner::new(device.clone())),
+//! );
+//! wallet.add_address_validator(Arc::new(
+//!     HwiAddressValidator::new(device).add_descriptor(KeychainKind::External, descriptor.parse()?),
+//! ));
+//! # Ok::<_, Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use bitcoin::util::bip32::{ChildNumber, DerivationPath, ExtendedPubKey, Fingerprint};
+use bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
+use bitcoin::{Address, Network, Script};
+
+use serde_json::Value;
+
+use super::address_validator::{AddressValidator, AddressValidatorError};
+use super::signer::{Signer, SignerError, SignerId};
+use super::utils::SecpCtx;
+use crate::descriptor::{get_checksum, ExtendedDescriptor, HdKeyPaths};
+use crate::types::KeychainKind;
+
+/// Code used by HWI when the user cancels an action on the device
+const HWI_ACTION_CANCELED: i64 = -14;
+
+/// Errors that can happen while talking to a hardware wallet
+#[derive(Debug)]
+pub enum HwiError {
+    /// Error while running the HWI process
+    Io(std::io::Error),
+    /// The response returned by HWI can't be parsed
+    InvalidResponse(String),
+    /// Error reported by HWI or by the device
+    Device {
+        /// HWI error code
+        code: i64,
+        /// Error message
+        message: String,
+    },
+}
+
+impl fmt::Display for HwiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for HwiError {}
+
+impl_error!(std::io::Error, Io, HwiError);
+
+impl From<HwiError> for AddressValidatorError {
+    fn from(err: HwiError) -> Self {
+        match err {
+            HwiError::Device { code, .. } if code == HWI_ACTION_CANCELED => {
+                AddressValidatorError::UserRejected
+            }
+            HwiError::Io(_) => AddressValidatorError::ConnectionError,
+            e => AddressValidatorError::Message(e.to_string()),
+        }
+    }
+}
+
+impl From<HwiError> for SignerError {
+    fn from(err: HwiError) -> Self {
+        match err {
+            HwiError::Device { code, .. } if code == HWI_ACTION_CANCELED => {
+                SignerError::UserCanceled
+            }
+            e => SignerError::External(e.to_string()),
+        }
+    }
+}
+
+/// Trait for the transports that run the HWI commands
+pub trait HwiTransport: fmt::Debug + Send + Sync {
+    /// Run a command and return its JSON response
+    ///
+    /// `args` contains the global options followed by the command and its arguments, just like
+    /// they would be passed to the `hwi` executable.
+    fn call(&self, args: &[String]) -> Result<Value, HwiError>;
+}
+
+/// Transport that runs the `hwi` executable
+#[derive(Debug, Clone)]
+pub struct HwiProcess {
+    path: PathBuf,
+}
+
+impl HwiProcess {
+    /// Run the executable at `path`
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        HwiProcess { path: path.into() }
+    }
+}
+
+impl Default for HwiProcess {
+    /// Run the `hwi` executable found in the `PATH`
+    fn default() -> Self {
+        HwiProcess::new("hwi")
+    }
+}
+
+impl HwiTransport for HwiProcess {
+    fn call(&self, args: &[String]) -> Result<Value, HwiError> {
+        let output = Command::new(&self.path).args(args).output()?;
+
+        serde_json::from_slice(&output.stdout).map_err(|_| {
+            HwiError::InvalidResponse(String::from_utf8_lossy(&output.stderr).into_owned())
+        })
+    }
+}
+
+/// Hardware wallet controlled through HWI
+#[derive(Debug, Clone)]
+pub struct HwiDevice {
+    transport: Arc<dyn HwiTransport>,
+    fingerprint: Fingerprint,
+    network: Network,
+}
+
+impl HwiDevice {
+    /// Connect to the device whose master key has `fingerprint` using the `hwi` executable
+    pub fn new(fingerprint: Fingerprint, network: Network) -> Self {
+        Self::with_transport(Arc::new(HwiProcess::default()), fingerprint, network)
+    }
+
+    /// Connect to the device whose master key has `fingerprint` using a custom transport
+    pub fn with_transport(
+        transport: Arc<dyn HwiTransport>,
+        fingerprint: Fingerprint,
+        network: Network,
+    ) -> Self {
+        HwiDevice {
+            transport,
+            fingerprint,
+            network,
+        }
+    }
+
+    /// Return the fingerprint of the master key of the device
+    pub fn fingerprint(&self) -> Fingerprint {
+        self.fingerprint
+    }
+
+    /// Return the extended public key at `path`
+    pub fn get_xpub(&self, path: &DerivationPath) -> Result<ExtendedPubKey, HwiError> {
+        let response = self.call("getxpub", &[path.to_string()])?;
+        let xpub = get_field(&response, "xpub")?;
+
+        ExtendedPubKey::from_str(xpub).map_err(|e| HwiError::InvalidResponse(e.to_string()))
+    }
+
+    /// Sign `psbt` on the device, returning the signed copy returned by it
+    ///
+    /// The inputs must contain the derivation paths of the keys, so that the device can tell
+    /// which ones it has to sign.
+    pub fn sign_psbt(&self, psbt: &Psbt) -> Result<Psbt, HwiError> {
+        let response = self.call("signtx", &[psbt.to_string()])?;
+        let psbt = get_field(&response, "psbt")?;
+
+        Psbt::from_str(psbt).map_err(|e| HwiError::InvalidResponse(e.to_string()))
+    }
+
+    /// Show the address of `descriptor` on the screen of the device, returning the address
+    /// computed by it
+    ///
+    /// The descriptor must not contain wildcards, and its keys must include their origin.
+    pub fn display_address(&self, descriptor: &ExtendedDescriptor) -> Result<Address, HwiError> {
+        let descriptor = descriptor.to_string();
+        let checksum =
+            get_checksum(&descriptor).map_err(|e| HwiError::InvalidResponse(e.to_string()))?;
+        let response = self.call(
+            "displayaddress",
+            &["--desc".to_string(), format!("{}#{}", descriptor, checksum)],
+        )?;
+        let address = get_field(&response, "address")?;
+
+        Address::from_str(address).map_err(|e| HwiError::InvalidResponse(e.to_string()))
+    }
+
+    fn call(&self, command: &str, args: &[String]) -> Result<Value, HwiError> {
+        let chain = match self.network {
+            Network::Bitcoin => "main",
+            Network::Testnet => "test",
+            Network::Regtest => "regtest",
+            Network::Signet => "signet",
+        };
+        let mut full_args = vec![
+            "--fingerprint".to_string(),
+            self.fingerprint.to_string(),
+            "--chain".to_string(),
+            chain.to_string(),
+            command.to_string(),
+        ];
+        full_args.extend_from_slice(args);
+
+        let response = self.transport.call(&full_args)?;
+        if let Some(message) = response.get("error") {
+            return Err(HwiError::Device {
+                code: response.get("code").and_then(Value::as_i64).unwrap_or(0),
+                message: message.as_str().unwrap_or_default().to_string(),
+            });
+        }
+
+        Ok(response)
+    }
+}
+
+fn get_field<'v>(response: &'v Value, field: &str) -> Result<&'v str, HwiError> {
+    response
+        .get(field)
+        .and_then(Value::as_str)
+        .ok_or_else(|| HwiError::InvalidResponse(response.to_string()))
+}
+
+/// Signer that asks an [`HwiDevice`] to sign the whole transaction
+#[derive(Debug, Clone)]
+pub struct HwiSigner {
+    device: HwiDevice,
+}
+
+impl HwiSigner {
+    /// Create a signer for `device`
+    pub fn new(device: HwiDevice) -> Self {
+        HwiSigner { device }
+    }
+}
+
+impl Signer for HwiSigner {
+    fn sign(
+        &self,
+        psbt: &mut Psbt,
+        _input_index: Option<usize>,
+        _secp: &SecpCtx,
+    ) -> Result<(), SignerError> {
+        // skip the round-trip to the device when there's nothing it could sign
+        let fingerprint = self.device.fingerprint;
+        let has_our_keys = psbt.inputs.iter().any(|input| {
+            input.final_script_sig.is_none()
+                && input.final_script_witness.is_none()
+                && input
+                    .bip32_derivation
+                    .values()
+                    .any(|(f, _)| *f == fingerprint)
+        });
+        if !has_our_keys {
+            return Ok(());
+        }
+
+        let signed = self.device.sign_psbt(psbt)?;
+        psbt.merge(signed)
+            .map_err(|e| SignerError::External(e.to_string()))
+    }
+
+    fn sign_whole_tx(&self) -> bool {
+        true
+    }
+
+    fn id(&self, _secp: &SecpCtx) -> SignerId {
+        SignerId::from(self.device.fingerprint)
+    }
+}
+
+/// Address validator that shows the addresses on an [`HwiDevice`]
+///
+/// Only the addresses of the keychains registered with
+/// [`add_descriptor`](HwiAddressValidator::add_descriptor) that contain a key of the device are
+/// shown, the others are accepted without asking it. The address computed by the device must
+/// match the one generated by the wallet.
+#[derive(Debug, Clone)]
+pub struct HwiAddressValidator {
+    device: HwiDevice,
+    descriptors: HashMap<KeychainKind, ExtendedDescriptor>,
+}
+
+impl HwiAddressValidator {
+    /// Create a validator for `device`, with no keychains registered
+    pub fn new(device: HwiDevice) -> Self {
+        HwiAddressValidator {
+            device,
+            descriptors: HashMap::new(),
+        }
+    }
+
+    /// Show the addresses of `keychain`, generated from `descriptor`
+    ///
+    /// The descriptor must be the same used by the wallet for that keychain, and the keys of the
+    /// device must include their origin.
+    pub fn add_descriptor(
+        mut self,
+        keychain: KeychainKind,
+        descriptor: ExtendedDescriptor,
+    ) -> Self {
+        self.descriptors.insert(keychain, descriptor);
+        self
+    }
+}
+
+impl AddressValidator for HwiAddressValidator {
+    fn validate(
+        &self,
+        keychain: KeychainKind,
+        hd_keypaths: &HdKeyPaths,
+        script: &Script,
+    ) -> Result<(), AddressValidatorError> {
+        let descriptor = match self.descriptors.get(&keychain) {
+            Some(descriptor) => descriptor,
+            None => return Ok(()),
+        };
+        let path = match hd_keypaths
+            .values()
+            .find(|(fingerprint, _)| *fingerprint == self.device.fingerprint)
+        {
+            Some((_, path)) => path,
+            None => return Ok(()),
+        };
+
+        let descriptor = match (descriptor.is_deriveable(), path.into_iter().last()) {
+            (false, _) => descriptor.clone(),
+            (true, Some(ChildNumber::Normal { index })) => descriptor.derive(*index),
+            (true, _) => return Err(AddressValidatorError::InvalidScript),
+        };
+        let address = self.device.display_address(&descriptor)?;
+        if &address.script_pubkey() != script {
+            return Err(AddressValidatorError::InvalidScript);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::str::FromStr;
+    use std::sync::{Arc, Mutex};
+
+    use bitcoin::secp256k1::Secp256k1;
+    use bitcoin::util::bip32::ExtendedPrivKey;
+    use miniscript::descriptor::DescriptorTrait;
+
+    use super::*;
+    use crate::descriptor::derived::AsDerived;
+    use crate::signer::SignerOrdering;
+    use crate::types::KeychainKind;
+    use crate::wallet::test::get_funded_wallet;
+    use crate::SignOptions;
+
+    /// In-process device that answers the HWI commands using a software key
+    #[derive(Debug)]
+    pub(crate) struct MockDevice {
+        xprv: ExtendedPrivKey,
+        pub(crate) calls: Mutex<Vec<String>>,
+        pub(crate) cancel: bool,
+    }
+
+    impl MockDevice {
+        pub(crate) fn new() -> Self {
+            MockDevice {
+                xprv: ExtendedPrivKey::from_str("tprv8ZgxMBicQKsPdpkqS7Eair4YxjcuuvDPNYmKX3sCniCf16tHEVrjjiSXEkFRnUH77yXc6ZcwHHcLNfjdi5qUvw3VDfgYiH5mNsj5izuiu2N").unwrap(),
+                calls: Mutex::new(vec![]),
+                cancel: false,
+            }
+        }
+
+        pub(crate) fn fingerprint(&self) -> Fingerprint {
+            self.xprv.fingerprint(&Secp256k1::new())
+        }
+
+        /// Descriptor watching the BIP84 account of the device
+        pub(crate) fn descriptor(&self) -> String {
+            let secp = Secp256k1::new();
+            let path = DerivationPath::from_str("m/84'/1'/0'").unwrap();
+            let xpub =
+                ExtendedPubKey::from_private(&secp, &self.xprv.derive_priv(&secp, &path).unwrap());
+
+            format!("wpkh([{}/84'/1'/0']{}/0/*)", self.fingerprint(), xpub)
+        }
+    }
+
+    impl HwiTransport for MockDevice {
+        fn call(&self, args: &[String]) -> Result<Value, HwiError> {
+            assert_eq!(args[1], self.fingerprint().to_string());
+            let (command, args) = (&args[4], &args[5..]);
+            self.calls.lock().unwrap().push(command.clone());
+            if self.cancel {
+                return Ok(serde_json::json!({ "error": "Action canceled", "code": -14 }));
+            }
+
+            let secp = Secp256k1::new();
+            match command.as_str() {
+                "getxpub" => {
+                    let path = DerivationPath::from_str(&args[0]).unwrap();
+                    let xprv = self.xprv.derive_priv(&secp, &path).unwrap();
+                    Ok(
+                        serde_json::json!({ "xpub": ExtendedPubKey::from_private(&secp, &xprv).to_string() }),
+                    )
+                }
+                "signtx" => {
+                    let mut psbt = Psbt::from_str(&args[0]).unwrap();
+                    for index in 0..psbt.inputs.len() {
+                        let paths = psbt.inputs[index]
+                            .bip32_derivation
+                            .values()
+                            .filter(|(fingerprint, _)| *fingerprint == self.fingerprint())
+                            .map(|(_, path)| path.clone())
+                            .collect::<Vec<_>>();
+                        for path in paths {
+                            let key = self.xprv.derive_priv(&secp, &path).unwrap().private_key;
+                            key.sign(&mut psbt, Some(index), &secp).unwrap();
+                        }
+                    }
+                    Ok(serde_json::json!({ "psbt": psbt.to_string(), "signed": true }))
+                }
+                "displayaddress" => {
+                    assert_eq!(args[0], "--desc");
+                    let descriptor = args[1].splitn(2, '#').next().unwrap();
+                    let descriptor = ExtendedDescriptor::from_str(descriptor).unwrap();
+                    let address = descriptor
+                        .as_derived_fixed(&secp)
+                        .address(Network::Testnet)
+                        .unwrap();
+                    Ok(serde_json::json!({ "address": address.to_string() }))
+                }
+                _ => Ok(serde_json::json!({ "error": "Unknown command", "code": -8 })),
+            }
+        }
+    }
+
+    fn get_device(mock: MockDevice) -> HwiDevice {
+        let fingerprint = mock.fingerprint();
+        HwiDevice::with_transport(Arc::new(mock), fingerprint, Network::Testnet)
+    }
+
+    #[test]
+    fn test_hwi_get_xpub() {
+        let device = get_device(MockDevice::new());
+        let path = DerivationPath::from_str("m/84'/1'/0'").unwrap();
+
+        let xpub = device.get_xpub(&path).unwrap();
+        assert_eq!(xpub.depth, 3);
+    }
+
+    #[test]
+    fn test_hwi_display_address() {
+        let mock = Arc::new(MockDevice::new());
+        let device = HwiDevice::with_transport(mock.clone(), mock.fingerprint(), Network::Testnet);
+        let descriptor =
+            ExtendedDescriptor::from_str(&MockDevice::new().descriptor().replace("/0/*", "/0/3"))
+                .unwrap();
+
+        let address = device.display_address(&descriptor).unwrap();
+        assert_eq!(
+            address,
+            descriptor
+                .as_derived_fixed(&Secp256k1::new())
+                .address(Network::Testnet)
+                .unwrap()
+        );
+        assert_eq!(*mock.calls.lock().unwrap(), vec!["displayaddress"]);
+    }
+
+    #[test]
+    fn test_hwi_address_validator() {
+        let mock = Arc::new(MockDevice::new());
+        let descriptor = mock.descriptor();
+        let (mut wallet, _, _) = get_funded_wallet(&descriptor);
+        let device = HwiDevice::with_transport(mock.clone(), mock.fingerprint(), Network::Testnet);
+        wallet.add_address_validator(Arc::new(
+            HwiAddressValidator::new(device)
+                .add_descriptor(KeychainKind::External, descriptor.parse().unwrap()),
+        ));
+
+        wallet
+            .get_address(crate::wallet::AddressIndex::New)
+            .unwrap();
+        assert_eq!(*mock.calls.lock().unwrap(), vec!["displayaddress"]);
+    }
+
+    #[test]
+    fn test_hwi_address_validator_mismatch() {
+        let mock = MockDevice::new();
+        let descriptor = mock.descriptor();
+        let (mut wallet, _, _) = get_funded_wallet(&descriptor);
+        // the device derives the addresses of a different keychain
+        let wrong_descriptor = descriptor.replace("/0/*", "/1/*").parse().unwrap();
+        wallet.add_address_validator(Arc::new(
+            HwiAddressValidator::new(get_device(mock))
+                .add_descriptor(KeychainKind::External, wrong_descriptor),
+        ));
+
+        assert!(matches!(
+            wallet.get_address(crate::wallet::AddressIndex::New),
+            Err(crate::Error::AddressValidator(
+                AddressValidatorError::InvalidScript
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_hwi_address_validator_rejected() {
+        let mut mock = MockDevice::new();
+        mock.cancel = true;
+        let descriptor = mock.descriptor();
+        let (mut wallet, _, _) = get_funded_wallet(&descriptor);
+        wallet.add_address_validator(Arc::new(
+            HwiAddressValidator::new(get_device(mock))
+                .add_descriptor(KeychainKind::External, descriptor.parse().unwrap()),
+        ));
+
+        assert!(matches!(
+            wallet.get_address(crate::wallet::AddressIndex::New),
+            Err(crate::Error::AddressValidator(
+                AddressValidatorError::UserRejected
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_hwi_signer() {
+        let mock = MockDevice::new();
+        let (mut wallet, _, _) = get_funded_wallet(&mock.descriptor());
+        let device = get_device(mock);
+        wallet.add_signer(
+            KeychainKind::External,
+            SignerOrdering(200),
+            Arc::new(HwiSigner::new(device)),
+        );
+
+        let addr = wallet
+            .get_address(crate::wallet::AddressIndex::New)
+            .unwrap();
+        let mut builder = wallet.build_tx();
+        builder
+            .set_single_recipient(addr.script_pubkey())
+            .drain_wallet();
+        let (mut psbt, _) = builder.finish().unwrap();
+
+        let finalized = wallet.sign(&mut psbt, SignOptions::default()).unwrap();
+        assert!(finalized);
+    }
+
+    #[test]
+    fn test_hwi_signer_canceled() {
+        let mut mock = MockDevice::new();
+        mock.cancel = true;
+        let (mut wallet, _, _) = get_funded_wallet(&mock.descriptor());
+        wallet.add_signer(
+            KeychainKind::External,
+            SignerOrdering(200),
+            Arc::new(HwiSigner::new(get_device(mock))),
+        );
+
+        let addr = wallet
+            .get_address(crate::wallet::AddressIndex::New)
+            .unwrap();
+        let mut builder = wallet.build_tx();
+        builder
+            .set_single_recipient(addr.script_pubkey())
+            .drain_wallet();
+        let (mut psbt, _) = builder.finish().unwrap();
+
+        assert!(matches!(
+            wallet.sign(&mut psbt, SignOptions::default()),
+            Err(crate::Error::Signer(SignerError::UserCanceled))
+        ));
+    }
+}
@@ -13,9 +13,9 @@
 //!
 //! This module defines the [`Wallet`] structure.
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fmt;
 use std::ops::{Deref, DerefMut};
 use std::sync::Arc;
@@ -36,8 +36,20 @@ use miniscript::psbt::PsbtInputSatisfier;
 use log::{debug, error, info, trace};
 
 pub mod address_validator;
+pub mod backup;
 pub mod coin_selection;
+pub mod coordinator;
+pub mod event;
 pub mod export;
+#[cfg(feature = "hwi")]
+#[cfg_attr(docsrs, doc(cfg(feature = "hwi")))]
+pub mod hwi;
+#[cfg(feature = "payjoin")]
+#[cfg_attr(docsrs, doc(cfg(feature = "payjoin")))]
+pub mod payjoin;
+#[cfg(feature = "remote-signer")]
+#[cfg_attr(docsrs, doc(cfg(feature = "remote-signer")))]
+pub mod remote_signer;
 pub mod signer;
 pub mod time;
 pub mod tx_builder;
@@ -49,22 +61,29 @@ pub mod verify;
 pub use utils::IsDust;
 
 use address_validator::AddressValidator;
+use backup::{BackupError, WalletBackup, BACKUP_VERSION};
 use coin_selection::DefaultCoinSelectionAlgorithm;
+use event::{EventListener, WalletSnapshot};
 use signer::{SignOptions, Signer, SignerOrdering, SignersContainer};
 use tx_builder::{BumpFee, CreateTx, FeePolicy, TxBuilder, TxParams};
-use utils::{check_nlocktime, check_nsequence_rbf, After, Older, SecpCtx, DUST_LIMIT_SATOSHI};
+use utils::{
+    check_nlocktime, check_nsequence_rbf, After, Older, SecpCtx, COINBASE_MATURITY,
+    DUST_LIMIT_SATOSHI,
+};
 
 use crate::blockchain::{Blockchain, Progress};
 use crate::database::{BatchDatabase, BatchOperations, DatabaseUtils};
 use crate::descriptor::derived::AsDerived;
-use crate::descriptor::policy::BuildSatisfaction;
+use crate::descriptor::policy::{BuildSatisfaction, Satisfaction};
 use crate::descriptor::{
     get_checksum, into_wallet_descriptor_checked, DerivedDescriptor, DerivedDescriptorMeta,
     DescriptorMeta, DescriptorScripts, ExtendedDescriptor, ExtractPolicy, IntoWalletDescriptor,
     Policy, XKeyUtils,
 };
 use crate::error::Error;
-use crate::psbt::PsbtUtils;
+use crate::psbt::{
+    InputSummary, MissingSignatures, OutputSummary, Ownership, PsbtSummary, PsbtUtils,
+};
 use crate::signer::SignerError;
 use crate::types::*;
 
@@ -89,10 +108,11 @@ pub struct Wallet<B, D> {
     change_signers: Arc<SignersContainer>,
 
     address_validators: Vec<Arc<dyn AddressValidator>>,
+    event_listeners: Vec<Arc<dyn EventListener>>,
 
     network: Network,
 
-    current_height: Option<u32>,
+    current_height: Cell<Option<u32>>,
 
     client: B,
     database: RefCell<D>,
@@ -129,6 +149,8 @@ where
     ) -> Result<Self, Error> {
         let secp = Secp256k1::new();
 
+        database.migrate()?;
+
         let (descriptor, keymap) = into_wallet_descriptor_checked(descriptor, &secp, network)?;
         database.check_descriptor_checksum(
             KeychainKind::External,
@@ -160,8 +182,9 @@ where
             signers,
             change_signers,
             address_validators: Vec::new(),
+            event_listeners: Vec::new(),
             network,
-            current_height,
+            current_height: Cell::new(current_height),
             client,
             database: RefCell::new(database),
             secp,
@@ -338,15 +361,266 @@ where
         self.database.borrow().iter_txs(include_raw)
     }
 
-    /// Return the balance, meaning the sum of this wallet's unspent outputs' values
+    /// Return the balance, separated into available, trusted-pending, untrusted-pending and
+    /// immature values
+    ///
+    /// Unconfirmed outputs are considered "trusted" when every input of the transaction that
+    /// created them spends one of our outputs. Coinbase outputs are "immature" until they reach
+    /// 100 confirmations: when the current height is not known they are always considered
+    /// immature. Outputs whose transaction is not stored in the database are considered
+    /// untrusted pending.
     ///
     /// Note that this methods only operate on the internal database, which first needs to be
     /// [`Wallet::sync`] manually.
-    pub fn get_balance(&self) -> Result<u64, Error> {
-        Ok(self
-            .list_unspent()?
+    pub fn get_balance(&self) -> Result<Balance, Error> {
+        let mut balance = Balance::default();
+
+        let database = self.database.borrow();
+        for utxo in database.iter_utxos()? {
+            let value = utxo.txout.value;
+            let details = match database.get_tx(&utxo.outpoint.txid, true)? {
+                Some(details) => details,
+                // the state of the output is unknown without its transaction
+                None => {
+                    balance.untrusted_pending += value;
+                    continue;
+                }
+            };
+
+            match (&details.confirmation_time, &details.transaction) {
+                _ if utxo.is_coinbase => {
+                    if self.is_mature(&details, self.current_height.get()) {
+                        balance.confirmed += value;
+                    } else {
+                        balance.immature += value;
+                    }
+                }
+                (Some(_), _) => balance.confirmed += value,
+                (None, Some(tx)) => {
+                    let mut trusted = !tx.input.is_empty();
+                    for input in &tx.input {
+                        match database.get_previous_output(&input.previous_output)? {
+                            Some(prev_out) if database.is_mine(&prev_out.script_pubkey)? => {}
+                            _ => {
+                                trusted = false;
+                                break;
+                            }
+                        }
+                    }
+
+                    if trusted {
+                        balance.trusted_pending += value;
+                    } else {
+                        balance.untrusted_pending += value;
+                    }
+                }
+                (None, None) => balance.untrusted_pending += value,
+            }
+        }
+
+        Ok(balance)
+    }
+
+    /// Store a [`Label`], replacing any previous label attached to the same object
+    pub fn set_label(&self, label: Label) -> Result<(), Error> {
+        self.database.borrow_mut().set_label(&label)
+    }
+
+    /// Return the [`Label`] attached to an object given its type and reference, if any
+    pub fn get_label(
+        &self,
+        label_type: LabelType,
+        reference: &str,
+    ) -> Result<Option<Label>, Error> {
+        self.database.borrow().get_label(label_type, reference)
+    }
+
+    /// Remove the [`Label`] attached to an object given its type and reference, returning it
+    pub fn del_label(
+        &self,
+        label_type: LabelType,
+        reference: &str,
+    ) -> Result<Option<Label>, Error> {
+        self.database.borrow_mut().del_label(label_type, reference)
+    }
+
+    /// Return the list of [`Label`]s stored in the wallet
+    pub fn list_labels(&self) -> Result<Vec<Label>, Error> {
+        self.database.borrow().iter_labels()
+    }
+
+    /// Export the labels stored in the wallet in the [BIP-329](https://github.com/bitcoin/bips/blob/master/bip-0329.mediawiki)
+    /// format, one JSON record per line
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use std::str::FromStr;
+    /// # use bitcoin::*;
+    /// # use bdk::*;
+    /// # let wallet = doctest_wallet!();
+    /// let txid = Txid::from_str("f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd")?;
+    /// wallet.set_label(Label::tx(&txid, "Rent"))?;
+    ///
+    /// assert_eq!(
+    ///     wallet.export_labels()?,
+    ///     r#"{"type":"tx","ref":"f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd","label":"Rent"}"#
+    /// );
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn export_labels(&self) -> Result<String, Error> {
+        let lines = self
+            .list_labels()?
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(lines.join("\n"))
+    }
+
+    /// Import labels in the [BIP-329](https://github.com/bitcoin/bips/blob/master/bip-0329.mediawiki)
+    /// format, one JSON record per line, returning the number of labels imported
+    ///
+    /// Imported labels replace the ones already attached to the same objects. As required by
+    /// BIP-329, records with an unknown `type` and empty lines are skipped.
+    pub fn import_labels(&self, jsonl: &str) -> Result<usize, Error> {
+        let mut batch = self.database.borrow().begin_batch();
+        let mut count = 0;
+        for line in jsonl.lines().filter(|l| !l.trim().is_empty()) {
+            let value: serde_json::Value = serde_json::from_str(line)?;
+            let label: Label = match serde_json::from_value::<LabelType>(value["type"].clone()) {
+                Ok(_) => serde_json::from_value(value)?,
+                Err(_) => {
+                    debug!("Skipping label with unknown type: {}", line);
+                    continue;
+                }
+            };
+
+            batch.set_label(&label)?;
+            count += 1;
+        }
+        self.database.borrow_mut().commit_batch(batch)?;
+
+        Ok(count)
+    }
+
+    /// Create a full backup of the wallet
+    ///
+    /// The backup contains the descriptors, including their private keys, the last derivation
+    /// index of each keychain and the labels. If `include_transactions` is `true` the transaction
+    /// history is included as well, so that a restored wallet has its balance and history before
+    /// the first sync.
+    ///
+    /// See [the `backup` module](backup) for an example.
+    pub fn backup(&self, include_transactions: bool) -> Result<WalletBackup, Error> {
+        let database = self.database.borrow();
+        let transactions = match include_transactions {
+            true => Some(database.iter_txs(true)?),
+            false => None,
+        };
+
+        Ok(WalletBackup {
+            version: BACKUP_VERSION,
+            network: self.network,
+            descriptor: self
+                .descriptor
+                .to_string_with_secret(&self.signers.as_key_map(&self.secp)),
+            change_descriptor: self.change_descriptor.as_ref().map(|desc| {
+                desc.to_string_with_secret(&self.change_signers.as_key_map(&self.secp))
+            }),
+            last_external_index: database.get_last_index(KeychainKind::External)?,
+            last_internal_index: database.get_last_index(KeychainKind::Internal)?,
+            labels: database.iter_labels()?,
+            transactions,
+        })
+    }
+
+    /// Restore the state of the wallet from a [`WalletBackup`]
+    ///
+    /// The wallet must have been created with the same descriptors and network as the backup.
+    /// The derivation indexes are only moved forward, and the labels and transactions in the
+    /// backup replace the ones already stored with the same references. The unspent outputs are
+    /// rebuilt from the transactions in the backup.
+    pub fn restore_from_backup(&self, backup: &WalletBackup) -> Result<(), Error> {
+        if backup.network != self.network {
+            return Err(Error::Backup(BackupError::NetworkMismatch {
+                backup: backup.network,
+                wallet: self.network,
+            }));
+        }
+        let parse = |descriptor: &str| -> Result<ExtendedDescriptor, Error> {
+            Ok(into_wallet_descriptor_checked(descriptor, &self.secp, self.network)?.0)
+        };
+        if parse(&backup.descriptor)? != self.descriptor {
+            return Err(Error::Backup(BackupError::DescriptorMismatch(
+                KeychainKind::External,
+            )));
+        }
+        let change_descriptor = backup
+            .change_descriptor
+            .as_ref()
+            .map(|desc| parse(desc))
+            .transpose()?;
+        if change_descriptor != self.change_descriptor {
+            return Err(Error::Backup(BackupError::DescriptorMismatch(
+                KeychainKind::Internal,
+            )));
+        }
+
+        for keychain in &[KeychainKind::External, KeychainKind::Internal] {
+            let (_, keychain) = self._get_descriptor_for_keychain(*keychain);
+            let index = match (
+                backup.last_index(keychain),
+                self.database.borrow().get_last_index(keychain)?,
+            ) {
+                (Some(backup), Some(current)) if backup <= current => continue,
+                (Some(backup), _) => backup,
+                (None, _) => continue,
+            };
+
+            self.set_index(keychain, index)?;
+            self.cache_addresses(keychain, 0, index + CACHE_ADDR_BATCH_SIZE)?;
+        }
+
+        let mut batch = self.database.borrow().begin_batch();
+        for label in &backup.labels {
+            batch.set_label(label)?;
+        }
+
+        let transactions = backup.transactions.as_deref().unwrap_or_default();
+        let spent: HashSet<OutPoint> = transactions
             .iter()
-            .fold(0, |sum, i| sum + i.txout.value))
+            .filter_map(|details| details.transaction.as_ref())
+            .flat_map(|tx| tx.input.iter().map(|input| input.previous_output))
+            .collect();
+        for details in transactions {
+            batch.set_tx(details)?;
+
+            let tx = match &details.transaction {
+                Some(tx) => tx,
+                None => continue,
+            };
+            for (vout, txout) in tx.output.iter().enumerate() {
+                let outpoint = OutPoint::new(details.txid, vout as u32);
+                if spent.contains(&outpoint) {
+                    continue;
+                }
+                if let Some((keychain, _)) = self
+                    .database
+                    .borrow()
+                    .get_path_from_script_pubkey(&txout.script_pubkey)?
+                {
+                    batch.set_utxo(&LocalUtxo {
+                        outpoint,
+                        txout: txout.clone(),
+                        keychain,
+                        is_coinbase: tx.is_coin_base(),
+                    })?;
+                }
+            }
+        }
+
+        self.database.borrow_mut().commit_batch(batch)
     }
 
     /// Add an external signer
@@ -373,6 +647,13 @@ where
         self.address_validators.push(validator);
     }
 
+    /// Add a listener for the events detected during a sync
+    ///
+    /// See [the `event` module](event) for an example.
+    pub fn add_event_listener(&mut self, listener: Arc<dyn EventListener>) {
+        self.event_listeners.push(listener);
+    }
+
     /// Start building a transaction.
     ///
     /// This returns a blank [`TxBuilder`] from which you can specify the parameters for the transaction.
@@ -628,16 +909,50 @@ where
             params.drain_wallet,
             params.manually_selected_only,
             params.bumping_fee.is_some(), // we mandate confirmed transactions if we're bumping the fee
+            params.current_height.or_else(|| self.current_height.get()),
         )?;
+        let satisfaction_weights = match params.bumping_fee {
+            Some(previous_fee) if previous_fee.merged => required_utxos
+                .iter()
+                .chain(optional_utxos.iter())
+                .map(|wu| (wu.utxo.outpoint(), wu.satisfaction_weight))
+                .collect(),
+            _ => HashMap::new(),
+        };
 
-        let coin_selection = coin_selection.coin_select(
-            self.database.borrow().deref(),
-            required_utxos,
-            optional_utxos,
-            fee_rate,
-            outgoing,
-            fee_amount,
-        )?;
+        let pay_for_ancestors = params.pay_for_ancestors
+            && matches!(params.fee_policy, None | Some(FeePolicy::FeeRate(_)));
+        // the fee missing from the unconfirmed ancestors of the selected utxos depends on the
+        // selection, which in turn depends on the fee: select again with the missing fee until it
+        // covers the ancestors of the selected utxos. it can only grow, so this always ends
+        let mut ancestors_fee = 0.0;
+        let coin_selection = loop {
+            let coin_selection = coin_selection.coin_select(
+                self.database.borrow().deref(),
+                required_utxos.clone(),
+                optional_utxos.clone(),
+                fee_rate,
+                outgoing,
+                fee_amount + ancestors_fee,
+            )?;
+            if !pay_for_ancestors {
+                break coin_selection;
+            }
+
+            let txids = coin_selection
+                .selected
+                .iter()
+                .map(|utxo| utxo.outpoint().txid)
+                .collect::<Vec<_>>();
+            let missing_fee = self
+                .get_unconfirmed_ancestors(&txids)?
+                .missing_fee(fee_rate)
+                .ceil();
+            if missing_fee <= ancestors_fee {
+                break coin_selection;
+            }
+            ancestors_fee = missing_fee;
+        };
         let mut fee_amount = coin_selection.fee_amount;
 
         tx.input = coin_selection
@@ -699,6 +1014,26 @@ where
             }
         }
 
+        match params.bumping_fee {
+            Some(previous_fee) if previous_fee.merged => {
+                let weight = tx.get_weight()
+                    + coin_selection
+                        .selected
+                        .iter()
+                        .filter_map(|utxo| satisfaction_weights.get(&utxo.outpoint()))
+                        .sum::<usize>();
+                // the replacement must pay for its own relay at the minimum relay fee rate
+                let min_relay_fee = (weight as f32 / 4.0
+                    * FeeRate::default_min_relay_fee().as_sat_vb())
+                .ceil() as u64;
+                let required = previous_fee.absolute + min_relay_fee;
+                if fee_amount < required {
+                    return Err(Error::FeeTooLow { required });
+                }
+            }
+            _ => {}
+        }
+
         // sort input/outputs according to the chosen algorithm
         params.ordering.sort_tx(&mut tx);
 
@@ -714,6 +1049,10 @@ where
             sent,
             fee: Some(fee_amount),
             verified: true,
+            ancestors_fee: match pay_for_ancestors {
+                true => Some(ancestors_fee as u64),
+                false => None,
+            },
         };
 
         Ok((psbt, transaction_details))
@@ -762,13 +1101,234 @@ where
     /// // broadcast fee_bumped_tx to replace original
     /// # Ok::<(), bdk::Error>(())
     /// ```
-    // TODO: support for merging multiple transactions while bumping the fees
     // TODO: option to force addition of an extra output? seems bad for privacy to update the
     // change
     pub fn build_fee_bump(
         &self,
         txid: Txid,
     ) -> Result<TxBuilder<'_, B, D, DefaultCoinSelectionAlgorithm, BumpFee>, Error> {
+        let (tx, original_utxos, previous_fee) = self.get_replaceable_tx(txid)?;
+
+        let params = TxParams {
+            // TODO: figure out what rbf option should be?
+            version: Some(tx_builder::Version(tx.version)),
+            recipients: tx
+                .output
+                .into_iter()
+                .map(|txout| (txout.script_pubkey, txout.value))
+                .collect(),
+            utxos: original_utxos,
+            bumping_fee: Some(previous_fee),
+            ..Default::default()
+        };
+
+        Ok(TxBuilder {
+            wallet: self,
+            params,
+            coin_selection: DefaultCoinSelectionAlgorithm::default(),
+            phantom: core::marker::PhantomData,
+        })
+    }
+
+    /// Bump the fees of several transactions previously created with this wallet, merging them
+    /// into a single replacement
+    ///
+    /// Every transaction must be unconfirmed and signal *replace by fee* (RBF), like in
+    /// [`build_fee_bump`](Self::build_fee_bump). The returned [`TxBuilder`] is pre-populated with
+    /// all the inputs of the original transactions, and with all their outputs except for the
+    /// change ones, which are merged into a single change output.
+    ///
+    /// As required by BIP-125, the replacement must pay a fee rate higher than every original
+    /// transaction, and a fee of at least the sum of the fees of all the original transactions
+    /// plus the minimum relay fee for its own size. If that's not the case the transaction
+    /// creation fails with [`Error::FeeRateTooLow`] or [`Error::FeeTooLow`].
+    ///
+    /// A transaction that spends an output of another transaction in the list can't be merged,
+    /// since that output won't exist anymore once its transaction is replaced.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use std::str::FromStr;
+    /// # use bitcoin::*;
+    /// # use bdk::*;
+    /// # use bdk::database::*;
+    /// # let wallet = doctest_wallet!();
+    /// # let stuck_txids: Vec<Txid> = vec![];
+    /// let (mut psbt, _) = {
+    ///     let mut builder = wallet.build_batch_fee_bump(&stuck_txids)?;
+    ///     builder.fee_rate(FeeRate::from_sat_per_vb(20.0));
+    ///     builder.finish()?
+    /// };
+    ///
+    /// let _ = wallet.sign(&mut psbt, SignOptions::default())?;
+    /// let replacement = psbt.extract_tx();
+    /// // broadcast the replacement of all the stuck transactions
+    /// # Ok::<(), bdk::Error>(())
+    /// ```
+    pub fn build_batch_fee_bump(
+        &self,
+        txids: &[Txid],
+    ) -> Result<TxBuilder<'_, B, D, DefaultCoinSelectionAlgorithm, BumpFee>, Error> {
+        let txids = txids.iter().cloned().collect::<BTreeSet<_>>();
+        if txids.is_empty() {
+            return Err(Error::Generic("No transaction to bump".into()));
+        }
+
+        let mut version = 1;
+        let mut recipients = vec![];
+        let mut utxos: Vec<WeightedUtxo> = vec![];
+        let mut previous_fee = tx_builder::PreviousFee {
+            absolute: 0,
+            rate: 0.0,
+            merged: txids.len() > 1,
+        };
+        for txid in &txids {
+            let (tx, original_utxos, fee) = self.get_replaceable_tx(*txid)?;
+
+            if let Some(utxo) = original_utxos
+                .iter()
+                .find(|utxo| txids.contains(&utxo.utxo.outpoint().txid))
+            {
+                return Err(Error::Generic(format!(
+                    "Transaction {} spends {}, created by another transaction to bump",
+                    txid,
+                    utxo.utxo.outpoint()
+                )));
+            }
+
+            version = version.max(tx.version);
+            recipients.extend(
+                tx.output
+                    .into_iter()
+                    .map(|txout| (txout.script_pubkey, txout.value)),
+            );
+            utxos.extend(original_utxos);
+            previous_fee.absolute += fee.absolute;
+            previous_fee.rate = previous_fee.rate.max(fee.rate);
+        }
+
+        let params = TxParams {
+            version: Some(tx_builder::Version(version)),
+            recipients,
+            utxos,
+            bumping_fee: Some(previous_fee),
+            ..Default::default()
+        };
+
+        Ok(TxBuilder {
+            wallet: self,
+            params,
+            coin_selection: DefaultCoinSelectionAlgorithm::default(),
+            phantom: core::marker::PhantomData,
+        })
+    }
+
+    /// Accelerate an unconfirmed transaction by spending its outputs with a *child pays for parent*
+    /// (CPFP) transaction.
+    ///
+    /// Returns a [`TxBuilder`] that spends all the unspent outputs of the wallet created by
+    /// `parent_txid` and sends them to a new change address, with a fee high enough for the parent,
+    /// its unconfirmed ancestors and the child to reach `target_package_feerate` as a package. This
+    /// is useful when the parent can't be replaced, for instance an incoming payment that doesn't
+    /// signal RBF.
+    ///
+    /// Only the ancestors stored in the database are accounted for, the ones whose fee is not
+    /// known are considered to pay no fee. If the parent and its ancestors already pay more than
+    /// `target_package_feerate` the child pays exactly that fee rate.
+    ///
+    /// Changing the fee policy of the returned [`TxBuilder`] overrides `target_package_feerate`,
+    /// the fee is always applied to the whole package unless it's an absolute fee.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// # use std::str::FromStr;
+    /// # use bitcoin::*;
+    /// # use bdk::*;
+    /// # use bdk::database::*;
+    /// # let wallet = doctest_wallet!();
+    /// # let parent_txid = Txid::from_str("f91d0a8a78462bc59398f2c5d7a84fcff491c26ba54c4833478b202796c8aafd").unwrap();
+    /// let (mut psbt, details) = {
+    ///     let builder = wallet.build_cpfp(parent_txid, FeeRate::from_sat_per_vb(10.0))?;
+    ///     builder.finish()?
+    /// };
+    /// let _ = wallet.sign(&mut psbt, SignOptions::default())?;
+    /// let child_tx = psbt.extract_tx();
+    /// // broadcast child_tx
+    /// # Ok::<(), bdk::Error>(())
+    /// ```
+    pub fn build_cpfp(
+        &self,
+        parent_txid: Txid,
+        target_package_feerate: FeeRate,
+    ) -> Result<TxBuilder<'_, B, D, DefaultCoinSelectionAlgorithm, CreateTx>, Error> {
+        match self.database.borrow().get_tx(&parent_txid, false)? {
+            None => return Err(Error::TransactionNotFound),
+            Some(tx) if tx.confirmation_time.is_some() => return Err(Error::TransactionConfirmed),
+            Some(_) => {}
+        }
+
+        let outpoints = self
+            .list_unspent()?
+            .into_iter()
+            .map(|utxo| utxo.outpoint)
+            .filter(|outpoint| outpoint.txid == parent_txid)
+            .collect::<Vec<_>>();
+        if outpoints.is_empty() {
+            return Err(Error::Generic(format!(
+                "Transaction {} has no unspent output of this wallet",
+                parent_txid
+            )));
+        }
+
+        let mut builder = self.build_tx();
+        builder
+            .add_utxos(&outpoints)?
+            .manually_selected_only()
+            .set_single_recipient(self.get_change_address()?)
+            .fee_rate(target_package_feerate)
+            .pay_for_unconfirmed_ancestors();
+
+        Ok(builder)
+    }
+
+    // Return the total weight and fee of the unconfirmed transactions in `txids` and of all their
+    // unconfirmed ancestors stored in the database
+    fn get_unconfirmed_ancestors(
+        &self,
+        txids: &[Txid],
+    ) -> Result<tx_builder::UnconfirmedAncestors, Error> {
+        let mut ancestors = tx_builder::UnconfirmedAncestors::default();
+        let mut visited = HashSet::new();
+        let mut queue = txids.to_vec();
+        while let Some(txid) = queue.pop() {
+            if !visited.insert(txid) {
+                continue;
+            }
+
+            let details = match self.database.borrow().get_tx(&txid, true)? {
+                Some(details) if details.confirmation_time.is_none() => details,
+                _ => continue,
+            };
+            let tx = details.transaction.ok_or(Error::TransactionNotFound)?;
+
+            // the fee of txs synced from backends that can't compute it is unknown, counting it as
+            // zero can only make the new tx pay more than needed
+            ancestors.weight += tx.get_weight();
+            ancestors.fee += details.fee.unwrap_or(0);
+            queue.extend(tx.input.iter().map(|txin| txin.previous_output.txid));
+        }
+
+        Ok(ancestors)
+    }
+
+    // Return the unconfirmed transaction `txid` without its inputs and change output, the utxos it
+    // spends and its fee, checking that it can be replaced
+    fn get_replaceable_tx(
+        &self,
+        txid: Txid,
+    ) -> Result<(Transaction, Vec<WeightedUtxo>, tx_builder::PreviousFee), Error> {
         let mut details = match self.database.borrow().get_tx(&txid, true)? {
             None => return Err(Error::TransactionNotFound),
             Some(tx) if tx.transaction.is_none() => return Err(Error::TransactionNotFound),
@@ -819,6 +1379,7 @@ where
                     outpoint: txin.previous_output,
                     txout,
                     keychain,
+                    is_coinbase: false,
                 };
 
                 Ok(WeightedUtxo {
@@ -847,28 +1408,13 @@ where
             }
         }
 
-        let params = TxParams {
-            // TODO: figure out what rbf option should be?
-            version: Some(tx_builder::Version(tx.version)),
-            recipients: tx
-                .output
-                .into_iter()
-                .map(|txout| (txout.script_pubkey, txout.value))
-                .collect(),
-            utxos: original_utxos,
-            bumping_fee: Some(tx_builder::PreviousFee {
-                absolute: details.fee.ok_or(Error::FeeRateUnavailable)?,
-                rate: feerate,
-            }),
-            ..Default::default()
+        let previous_fee = tx_builder::PreviousFee {
+            absolute: details.fee.ok_or(Error::FeeRateUnavailable)?,
+            rate: feerate,
+            merged: false,
         };
 
-        Ok(TxBuilder {
-            wallet: self,
-            params,
-            coin_selection: DefaultCoinSelectionAlgorithm::default(),
-            phantom: core::marker::PhantomData,
-        })
+        Ok((tx, original_utxos, previous_fee))
     }
 
     /// Sign a transaction with all the wallet's signers, in the order specified by every signer's
@@ -901,43 +1447,10 @@ where
         // this helps us doing our job later
         self.add_input_hd_keypaths(psbt)?;
 
-        // If we aren't allowed to use `witness_utxo`, ensure that every input but finalized one
-        // has the `non_witness_utxo`
-        if !sign_options.trust_witness_utxo
-            && psbt
-                .inputs
-                .iter()
-                .filter(|i| i.final_script_witness.is_none() && i.final_script_sig.is_none())
-                .any(|i| i.non_witness_utxo.is_none())
-        {
-            return Err(Error::Signer(signer::SignerError::MissingNonWitnessUtxo));
-        }
-
-        // If the user hasn't explicitly opted-in, refuse to sign the transaction unless every input
-        // is using `SIGHASH_ALL`
-        if !sign_options.allow_all_sighashes
-            && !psbt
-                .inputs
-                .iter()
-                .all(|i| i.sighash_type.is_none() || i.sighash_type == Some(SigHashType::All))
-        {
-            return Err(Error::Signer(signer::SignerError::NonStandardSighash));
-        }
+        sign_options.check_psbt(psbt)?;
 
-        for signer in self
-            .signers
-            .signers()
-            .iter()
-            .chain(self.change_signers.signers().iter())
-        {
-            if signer.sign_whole_tx() {
-                signer.sign(psbt, None, &self.secp)?;
-            } else {
-                for index in 0..psbt.inputs.len() {
-                    signer.sign(psbt, Some(index), &self.secp)?;
-                }
-            }
-        }
+        self.signers.sign(psbt, &self.secp)?;
+        self.change_signers.sign(psbt, &self.secp)?;
 
         // attempt to finalize
         self.finalize_psbt(psbt, sign_options)
@@ -960,10 +1473,152 @@ where
         }
     }
 
-    /// Return the "public" version of the wallet's descriptor, meaning a new descriptor that has
-    /// the same structure but with every secret key removed
+    /// Return a summary of the inputs and outputs of a PSBT, marking the ones that belong to the
+    /// wallet
     ///
-    /// This can be used to build a watch-only version of a wallet
+    /// The summary can be printed to show the user a human-readable description of the
+    /// transaction before signing it.
+    pub fn psbt_summary(&self, psbt: &Psbt) -> Result<PsbtSummary, Error> {
+        let database = self.database.borrow();
+        let ownership = |script: &Script| -> Result<Ownership, Error> {
+            Ok(database
+                .get_path_from_script_pubkey(script)?
+                .map(|(keychain, _)| keychain)
+                .into())
+        };
+
+        let inputs = psbt
+            .global
+            .unsigned_tx
+            .input
+            .iter()
+            .enumerate()
+            .map(|(n, txin)| {
+                let utxo = psbt.get_utxo_for(n);
+                let is_final = matches!(
+                    psbt.inputs.get(n),
+                    Some(input)
+                        if input.final_script_sig.is_some() || input.final_script_witness.is_some()
+                );
+
+                Ok(InputSummary {
+                    previous_output: txin.previous_output,
+                    value: utxo.as_ref().map(|txout| txout.value),
+                    address: utxo
+                        .as_ref()
+                        .and_then(|txout| Address::from_script(&txout.script_pubkey, self.network)),
+                    ownership: match &utxo {
+                        Some(txout) => ownership(&txout.script_pubkey)?,
+                        None => Ownership::External,
+                    },
+                    is_final,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let outputs = psbt
+            .global
+            .unsigned_tx
+            .output
+            .iter()
+            .map(|txout| {
+                Ok(OutputSummary {
+                    value: txout.value,
+                    script_pubkey: txout.script_pubkey.clone(),
+                    address: Address::from_script(&txout.script_pubkey, self.network),
+                    ownership: ownership(&txout.script_pubkey)?,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(PsbtSummary {
+            inputs,
+            outputs,
+            fee: psbt.fee_amount(),
+            fee_rate: psbt.fee_rate(),
+        })
+    }
+
+    /// Return the inputs of a PSBT owned by the wallet that don't have enough signatures yet to
+    /// satisfy the wallet's spending policy
+    ///
+    /// Inputs that are already finalized or that don't belong to the wallet are not returned.
+    pub fn psbt_missing_signatures(&self, psbt: &Psbt) -> Result<Vec<MissingSignatures>, Error> {
+        let mut missing = vec![];
+
+        for (n, input) in psbt.inputs.iter().enumerate() {
+            if input.final_script_sig.is_some() || input.final_script_witness.is_some() {
+                continue;
+            }
+            let (keychain, child, policy) = match self.psbt_input_policy(psbt, n)? {
+                Some(input_policy) => input_policy,
+                None => continue,
+            };
+            if matches!(
+                policy.satisfaction,
+                Satisfaction::Complete { .. } | Satisfaction::PartialComplete { .. }
+            ) {
+                continue;
+            }
+
+            let missing_keys = self
+                .get_descriptor_for_keychain(keychain)
+                .as_derived(child, &self.secp)
+                .get_hd_keypaths(&self.secp)?
+                .into_iter()
+                .filter(|(pubkey, _)| !input.partial_sigs.contains_key(pubkey))
+                .collect();
+            missing.push(MissingSignatures {
+                input_index: n,
+                keychain,
+                satisfaction: policy.satisfaction,
+                missing_keys,
+            });
+        }
+
+        Ok(missing)
+    }
+
+    /// Return the keychain and derivation index of the output spent by the input at `input_index`
+    /// of a PSBT, together with the spending policy of the wallet with the `satisfaction` computed
+    /// from the signatures in that input
+    ///
+    /// Returns `None` if the input doesn't belong to the wallet.
+    pub(crate) fn psbt_input_policy(
+        &self,
+        psbt: &Psbt,
+        input_index: usize,
+    ) -> Result<Option<(KeychainKind, u32, Policy)>, Error> {
+        let (keychain, child) = match psbt.get_utxo_for(input_index).and_then(|txout| {
+            self.database
+                .borrow()
+                .get_path_from_script_pubkey(&txout.script_pubkey)
+                .transpose()
+        }) {
+            Some(path) => path?,
+            None => return Ok(None),
+        };
+        let (descriptor, signers) = match keychain {
+            KeychainKind::External => (&self.descriptor, &self.signers),
+            KeychainKind::Internal => match &self.change_descriptor {
+                Some(descriptor) => (descriptor, &self.change_signers),
+                None => return Ok(None),
+            },
+        };
+
+        // the policy looks for the signatures in every input of the psbt, so we only leave the
+        // one we are checking
+        let mut single_input = psbt.clone();
+        single_input.inputs = psbt.inputs.get(input_index).cloned().into_iter().collect();
+
+        Ok(descriptor
+            .extract_policy(signers, BuildSatisfaction::Psbt(&single_input), &self.secp)?
+            .map(|policy| (keychain, child, policy)))
+    }
+
+    /// Return the "public" version of the wallet's descriptor, meaning a new descriptor that has
+    /// the same structure but with every secret key removed
+    ///
+    /// This can be used to build a watch-only version of a wallet
     pub fn public_descriptor(
         &self,
         keychain: KeychainKind,
@@ -997,7 +1652,7 @@ where
                 .borrow()
                 .get_tx(&input.previous_output.txid, false)?
                 .map(|tx| tx.confirmation_time.map(|c| c.height).unwrap_or(u32::MAX));
-            let current_height = sign_options.assume_height.or(self.current_height);
+            let current_height = sign_options.assume_height.or(self.current_height.get());
 
             debug!(
                 "Input #{} - {}, using `create_height` = {:?}, `current_height` = {:?}",
@@ -1203,9 +1858,22 @@ where
             .collect())
     }
 
+    /// Whether the coinbase transaction described by `details` can be spent in the block after
+    /// `current_height`
+    ///
+    /// When the current height is not known the outputs are always considered immature.
+    fn is_mature(&self, details: &TransactionDetails, current_height: Option<u32>) -> bool {
+        match (&details.confirmation_time, current_height) {
+            (Some(confirmation_time), Some(current_height)) => {
+                current_height + 1 >= confirmation_time.height + COINBASE_MATURITY
+            }
+            _ => false,
+        }
+    }
+
     /// Given the options returns the list of utxos that must be used to form the
     /// transaction and any further that may be used if needed.
-    #[allow(clippy::type_complexity)]
+    #[allow(clippy::type_complexity, clippy::too_many_arguments)]
     fn preselect_utxos(
         &self,
         change_policy: tx_builder::ChangeSpendPolicy,
@@ -1214,6 +1882,7 @@ where
         must_use_all_available: bool,
         manual_only: bool,
         must_only_use_confirmed_tx: bool,
+        current_height: Option<u32>,
     ) -> Result<(Vec<WeightedUtxo>, Vec<WeightedUtxo>), Error> {
         //    must_spend <- manually selected utxos
         //    may_spend  <- all other available utxos
@@ -1250,11 +1919,30 @@ where
             false => vec![true; may_spend.len()],
         };
 
+        // coinbase outputs can only be spent once they reach maturity
+        let satisfies_mature = {
+            let database = self.database.borrow();
+            may_spend
+                .iter()
+                .map(|u| -> Result<_, Error> {
+                    if !u.0.is_coinbase {
+                        return Ok(true);
+                    }
+
+                    Ok(match database.get_tx(&u.0.outpoint.txid, false)? {
+                        None => false,
+                        Some(details) => self.is_mature(&details, current_height),
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
         let mut i = 0;
         may_spend.retain(|u| {
             let retain = change_policy.is_satisfied_by(&u.0)
                 && !unspendable.contains(&u.0.outpoint)
-                && satisfies_confirmed[i];
+                && satisfies_confirmed[i]
+                && satisfies_mature[i];
             i += 1;
             retain
         });
@@ -1522,6 +2210,12 @@ where
             }
         }
 
+        // only take the snapshot when somebody is going to receive the events
+        let before = match self.event_listeners.is_empty() {
+            true => None,
+            false => Some(WalletSnapshot::take(self.database.borrow().deref())?),
+        };
+
         debug!("run_setup: {}", run_setup);
         // TODO: what if i generate an address first and cache some addresses?
         // TODO: we should sync if generating an address triggers a new batch to be stored
@@ -1538,6 +2232,8 @@ where
                 progress_update,
             ))?;
         }
+        self.current_height
+            .set(Some(maybe_await!(self.client.get_height())?));
 
         #[cfg(feature = "verify")]
         {
@@ -1556,6 +2252,15 @@ where
             }
         }
 
+        if let Some(before) = before {
+            let after = WalletSnapshot::take(self.database.borrow().deref())?;
+            for event in before.diff(&after) {
+                for listener in &self.event_listeners {
+                    listener.on_event(&event);
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -1584,6 +2289,7 @@ pub(crate) mod test {
 
     use bitcoin::{util::psbt, Network};
 
+    use crate::blockchain::{noop_progress, Capability};
     use crate::database::memory::MemoryDatabase;
     use crate::database::Database;
     use crate::types::KeychainKind;
@@ -2023,6 +2729,140 @@ pub(crate) mod test {
         assert_fee_rate!(psbt.extract_tx(), details.fee.unwrap_or(0), FeeRate::default(), @add_signature);
     }
 
+    #[test]
+    fn test_create_tx_waste_metric_coin_selection() {
+        use crate::wallet::coin_selection::WasteMetricCoinSelection;
+
+        let (wallet, _, _) = get_funded_wallet(get_test_wpkh());
+        let addr = wallet.get_address(New).unwrap();
+        let mut builder = wallet
+            .build_tx()
+            .coin_selection(WasteMetricCoinSelection::new(FeeRate::from_sat_per_vb(
+                10.0,
+            )));
+        builder.add_recipient(addr.script_pubkey(), 25_000);
+        let (psbt, details) = builder.finish().unwrap();
+
+        assert_eq!(psbt.global.unsigned_tx.input.len(), 1);
+        assert_fee_rate!(psbt.extract_tx(), details.fee.unwrap_or(0), FeeRate::default(), @add_signature);
+    }
+
+    // funded wallet whose only utxo is an output of an unconfirmed transaction that doesn't pay
+    // any fee
+    fn get_wallet_with_unconfirmed_utxo() -> (Wallet<(), MemoryDatabase>, Transaction) {
+        let (wallet, descriptors, funding_txid) = get_funded_wallet(get_test_wpkh());
+        wallet
+            .database
+            .borrow_mut()
+            .del_utxo(&OutPoint::new(funding_txid, 0))
+            .unwrap();
+        let parent_txid = crate::populate_test_db!(
+            wallet.database.borrow_mut(),
+            testutils! (@tx ( (@external descriptors, 0) => 25_000 )),
+            Some(100),
+        );
+        let parent = wallet
+            .database
+            .borrow()
+            .get_raw_tx(&parent_txid)
+            .unwrap()
+            .unwrap();
+
+        (wallet, parent)
+    }
+
+    #[test]
+    fn test_create_tx_pay_for_unconfirmed_ancestors() {
+        let (wallet, parent) = get_wallet_with_unconfirmed_utxo();
+        let addr = Address::from_str("2N1Ffz3WaNzbeLFBb51xyFMHYSEUXcbiSoX").unwrap();
+        let mut builder = wallet.build_tx();
+        builder
+            .add_recipient(addr.script_pubkey(), 10_000)
+            .fee_rate(FeeRate::from_sat_per_vb(5.0))
+            .pay_for_unconfirmed_ancestors();
+        let (psbt, details) = builder.finish().unwrap();
+
+        let parent_fee = (parent.get_weight() as f32 / 4.0 * 5.0).ceil() as u64;
+        assert_eq!(details.ancestors_fee, Some(parent_fee));
+
+        let tx = psbt.extract_tx();
+        assert_eq!(tx.input[0].previous_output.txid, parent.txid());
+        assert_fee_rate!(tx, details.fee.unwrap_or(0) - parent_fee, FeeRate::from_sat_per_vb(5.0), @add_signature);
+
+        let mut signed_tx = tx.clone();
+        for txin in &mut signed_tx.input {
+            txin.witness.push([0x00; 108].to_vec()); // fake signature
+        }
+        let package_vbytes = (parent.get_weight() + signed_tx.get_weight()) as f32 / 4.0;
+        let package_fee_rate = details.fee.unwrap_or(0) as f32 / package_vbytes;
+        assert!((package_fee_rate - 5.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_create_tx_pay_for_ancestors_unknown_fee() {
+        let (wallet, parent) = get_wallet_with_unconfirmed_utxo();
+        {
+            let mut database = wallet.database.borrow_mut();
+            let mut details = database.get_tx(&parent.txid(), true).unwrap().unwrap();
+            details.fee = None;
+            database.set_tx(&details).unwrap();
+        }
+        let addr = Address::from_str("2N1Ffz3WaNzbeLFBb51xyFMHYSEUXcbiSoX").unwrap();
+        let mut builder = wallet.build_tx();
+        builder
+            .add_recipient(addr.script_pubkey(), 10_000)
+            .fee_rate(FeeRate::from_sat_per_vb(5.0))
+            .pay_for_unconfirmed_ancestors();
+        let (_, details) = builder.finish().unwrap();
+
+        let parent_fee = (parent.get_weight() as f32 / 4.0 * 5.0).ceil() as u64;
+        assert_eq!(details.ancestors_fee, Some(parent_fee));
+    }
+
+    #[test]
+    fn test_create_tx_unconfirmed_ancestors_not_paid_by_default() {
+        let (wallet, _) = get_wallet_with_unconfirmed_utxo();
+        let addr = Address::from_str("2N1Ffz3WaNzbeLFBb51xyFMHYSEUXcbiSoX").unwrap();
+        let mut builder = wallet.build_tx();
+        builder
+            .add_recipient(addr.script_pubkey(), 10_000)
+            .fee_rate(FeeRate::from_sat_per_vb(5.0));
+        let (psbt, details) = builder.finish().unwrap();
+
+        assert_eq!(details.ancestors_fee, None);
+        assert_fee_rate!(psbt.extract_tx(), details.fee.unwrap_or(0), FeeRate::from_sat_per_vb(5.0), @add_signature);
+    }
+
+    #[test]
+    fn test_create_tx_pay_for_confirmed_ancestors() {
+        let (wallet, _, _) = get_funded_wallet(get_test_wpkh());
+        let addr = Address::from_str("2N1Ffz3WaNzbeLFBb51xyFMHYSEUXcbiSoX").unwrap();
+        let mut builder = wallet.build_tx();
+        builder
+            .add_recipient(addr.script_pubkey(), 10_000)
+            .fee_rate(FeeRate::from_sat_per_vb(5.0))
+            .pay_for_unconfirmed_ancestors();
+        let (psbt, details) = builder.finish().unwrap();
+
+        assert_eq!(details.ancestors_fee, Some(0));
+        assert_fee_rate!(psbt.extract_tx(), details.fee.unwrap_or(0), FeeRate::from_sat_per_vb(5.0), @add_signature);
+    }
+
+    #[test]
+    fn test_create_tx_pay_for_unconfirmed_ancestors_absolute_fee() {
+        let (wallet, _) = get_wallet_with_unconfirmed_utxo();
+        let addr = Address::from_str("2N1Ffz3WaNzbeLFBb51xyFMHYSEUXcbiSoX").unwrap();
+        let mut builder = wallet.build_tx();
+        builder
+            .add_recipient(addr.script_pubkey(), 10_000)
+            .fee_absolute(1_000)
+            .pay_for_unconfirmed_ancestors();
+        let (_, details) = builder.finish().unwrap();
+
+        assert_eq!(details.ancestors_fee, None);
+        assert_eq!(details.fee, Some(1_000));
+    }
+
     #[test]
     fn test_create_tx_custom_fee_rate() {
         let (wallet, _, _) = get_funded_wallet(get_test_wpkh());
@@ -2787,6 +3627,7 @@ pub(crate) mod test {
         details.confirmation_time = Some(ConfirmationTime {
             timestamp: 12345678,
             height: 42,
+            block_hash: None,
         });
         wallet.database.borrow_mut().set_tx(&details).unwrap();
 
@@ -3240,6 +4081,298 @@ pub(crate) mod test {
         assert_fee_rate!(psbt.extract_tx(), details.fee.unwrap_or(0), FeeRate::from_sat_per_vb(50.0), @add_signature);
     }
 
+    // create an unconfirmed rbf transaction and store it in the database with a fake signature,
+    // without saving the new utxos
+    fn create_stored_rbf_tx(
+        wallet: &Wallet<(), MemoryDatabase>,
+        script_pubkey: Script,
+        amount: u64,
+    ) -> TransactionDetails {
+        let mut builder = wallet.build_tx();
+        builder.add_recipient(script_pubkey, amount).enable_rbf();
+        let (psbt, mut details) = builder.finish().unwrap();
+        let mut tx = psbt.extract_tx();
+        for txin in &mut tx.input {
+            txin.witness.push([0x00; 108].to_vec()); // fake signature
+            wallet
+                .database
+                .borrow_mut()
+                .del_utxo(&txin.previous_output)
+                .unwrap();
+        }
+        details.transaction = Some(tx);
+        wallet.database.borrow_mut().set_tx(&details).unwrap();
+
+        details
+    }
+
+    #[test]
+    fn test_batch_fee_bump() {
+        let (wallet, descriptors, _) = get_funded_wallet(get_test_wpkh());
+        crate::populate_test_db!(
+            wallet.database.borrow_mut(),
+            testutils! (@tx ( (@external descriptors, 0) => 25_000 ) (@confirmations 1)),
+            Some(100),
+        );
+
+        let addr1 = Address::from_str("2N1Ffz3WaNzbeLFBb51xyFMHYSEUXcbiSoX").unwrap();
+        let addr2 = Address::from_str("2N4eQYCbKUHCCTUjBJeHcJp9ok6J2GZsTDt").unwrap();
+        let original1 = create_stored_rbf_tx(&wallet, addr1.script_pubkey(), 20_000);
+        let original2 = create_stored_rbf_tx(&wallet, addr2.script_pubkey(), 10_000);
+
+        let mut builder = wallet
+            .build_batch_fee_bump(&[original1.txid, original2.txid])
+            .unwrap();
+        builder.fee_rate(FeeRate::from_sat_per_vb(5.0));
+        let (psbt, details) = builder.finish().unwrap();
+
+        assert_eq!(details.sent, original1.sent + original2.sent);
+        assert_eq!(details.sent, 75_000);
+        assert_eq!(details.fee.unwrap_or(0) + details.received, 45_000);
+        assert!(details.fee.unwrap_or(0) > original1.fee.unwrap_or(0) + original2.fee.unwrap_or(0));
+
+        let tx = &psbt.global.unsigned_tx;
+        assert_eq!(tx.input.len(), 2);
+        assert_eq!(tx.output.len(), 3);
+        for (addr, value) in &[(&addr1, 20_000), (&addr2, 10_000)] {
+            assert_eq!(
+                tx.output
+                    .iter()
+                    .find(|txout| txout.script_pubkey == addr.script_pubkey())
+                    .unwrap()
+                    .value,
+                *value
+            );
+        }
+
+        assert_fee_rate!(psbt.extract_tx(), details.fee.unwrap_or(0), FeeRate::from_sat_per_vb(5.0), @add_signature);
+    }
+
+    #[test]
+    #[should_panic(expected = "FeeTooLow")]
+    fn test_batch_fee_bump_below_incremental_relay_fee() {
+        let (wallet, descriptors, _) = get_funded_wallet(get_test_wpkh());
+        crate::populate_test_db!(
+            wallet.database.borrow_mut(),
+            testutils! (@tx ( (@external descriptors, 0) => 25_000 ) (@confirmations 1)),
+            Some(100),
+        );
+
+        let addr1 = Address::from_str("2N1Ffz3WaNzbeLFBb51xyFMHYSEUXcbiSoX").unwrap();
+        let addr2 = Address::from_str("2N4eQYCbKUHCCTUjBJeHcJp9ok6J2GZsTDt").unwrap();
+        let original1 = create_stored_rbf_tx(&wallet, addr1.script_pubkey(), 20_000);
+        let original2 = create_stored_rbf_tx(&wallet, addr2.script_pubkey(), 10_000);
+
+        // higher than the fee rate of both the originals, but the replacement is smaller than the
+        // two of them and doesn't pay for their fees and its own relay
+        let mut builder = wallet
+            .build_batch_fee_bump(&[original1.txid, original2.txid])
+            .unwrap();
+        builder.fee_rate(FeeRate::from_sat_per_vb(2.0));
+        builder.finish().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "FeeTooLow")]
+    fn test_batch_fee_bump_absolute_below_originals() {
+        let (wallet, descriptors, _) = get_funded_wallet(get_test_wpkh());
+        crate::populate_test_db!(
+            wallet.database.borrow_mut(),
+            testutils! (@tx ( (@external descriptors, 0) => 25_000 ) (@confirmations 1)),
+            Some(100),
+        );
+
+        let addr1 = Address::from_str("2N1Ffz3WaNzbeLFBb51xyFMHYSEUXcbiSoX").unwrap();
+        let addr2 = Address::from_str("2N4eQYCbKUHCCTUjBJeHcJp9ok6J2GZsTDt").unwrap();
+        let original1 = create_stored_rbf_tx(&wallet, addr1.script_pubkey(), 20_000);
+        let original2 = create_stored_rbf_tx(&wallet, addr2.script_pubkey(), 10_000);
+
+        let mut builder = wallet
+            .build_batch_fee_bump(&[original1.txid, original2.txid])
+            .unwrap();
+        builder.fee_absolute(original1.fee.unwrap_or(0) + original2.fee.unwrap_or(0) - 1);
+        builder.finish().unwrap();
+    }
+
+    #[test]
+    fn test_batch_fee_bump_dependent_transactions() {
+        let (wallet, _, _) = get_funded_wallet(get_test_wpkh());
+
+        let addr = Address::from_str("2N1Ffz3WaNzbeLFBb51xyFMHYSEUXcbiSoX").unwrap();
+        let original1 = create_stored_rbf_tx(&wallet, addr.script_pubkey(), 20_000);
+        // make the change of the first transaction spendable, so that the second one spends it
+        let tx1 = original1.transaction.as_ref().unwrap();
+        let (vout, change) = tx1
+            .output
+            .iter()
+            .enumerate()
+            .find(|(_, txout)| txout.script_pubkey != addr.script_pubkey())
+            .unwrap();
+        wallet
+            .database
+            .borrow_mut()
+            .set_utxo(&LocalUtxo {
+                outpoint: OutPoint::new(original1.txid, vout as u32),
+                txout: change.clone(),
+                keychain: KeychainKind::External,
+                is_coinbase: false,
+            })
+            .unwrap();
+        let original2 = create_stored_rbf_tx(&wallet, addr.script_pubkey(), 10_000);
+
+        let result = wallet.build_batch_fee_bump(&[original1.txid, original2.txid]);
+        assert!(matches!(result, Err(Error::Generic(_))));
+    }
+
+    #[test]
+    fn test_batch_fee_bump_no_transactions() {
+        let (wallet, _, _) = get_funded_wallet(get_test_wpkh());
+
+        assert!(matches!(
+            wallet.build_batch_fee_bump(&[]),
+            Err(Error::Generic(_))
+        ));
+    }
+
+    #[test]
+    fn test_cpfp() {
+        let (wallet, descriptors, _) = get_funded_wallet(get_test_wpkh());
+        let parent_txid = crate::populate_test_db!(
+            wallet.database.borrow_mut(),
+            testutils! (@tx ( (@external descriptors, 0) => 25_000 )),
+            Some(100),
+        );
+        let parent = wallet
+            .database
+            .borrow()
+            .get_raw_tx(&parent_txid)
+            .unwrap()
+            .unwrap();
+
+        let builder = wallet
+            .build_cpfp(parent_txid, FeeRate::from_sat_per_vb(5.0))
+            .unwrap();
+        let (psbt, details) = builder.finish().unwrap();
+
+        let tx = &psbt.global.unsigned_tx;
+        assert_eq!(tx.input.len(), 1);
+        assert_eq!(tx.input[0].previous_output.txid, parent_txid);
+        assert_eq!(tx.output.len(), 1);
+        assert_eq!(details.sent, 25_000);
+        assert_eq!(details.received + details.fee.unwrap_or(0), 25_000);
+
+        // the parent doesn't pay any fee, so the child pays for both of them
+        let mut child = psbt.extract_tx();
+        child.input[0].witness.push([0x00; 108].to_vec()); // fake signature
+        let package_vbytes = (parent.get_weight() + child.get_weight()) as f32 / 4.0;
+        let package_fee_rate = details.fee.unwrap_or(0) as f32 / package_vbytes;
+        assert!((package_fee_rate - 5.0).abs() < 0.5);
+        assert!(details.fee.unwrap_or(0) as f32 / (child.get_weight() as f32 / 4.0) > 5.0);
+    }
+
+    #[test]
+    fn test_cpfp_parent_above_target() {
+        let (wallet, descriptors, _) = get_funded_wallet(get_test_wpkh());
+        let parent_txid = crate::populate_test_db!(
+            wallet.database.borrow_mut(),
+            testutils! (@tx ( (@external descriptors, 0) => 25_000 )),
+            Some(100),
+        );
+        let mut parent = wallet
+            .database
+            .borrow()
+            .get_tx(&parent_txid, true)
+            .unwrap()
+            .unwrap();
+        parent.fee = Some(10_000);
+        wallet.database.borrow_mut().set_tx(&parent).unwrap();
+
+        let builder = wallet
+            .build_cpfp(parent_txid, FeeRate::from_sat_per_vb(5.0))
+            .unwrap();
+        let (psbt, details) = builder.finish().unwrap();
+
+        assert_fee_rate!(psbt.extract_tx(), details.fee.unwrap_or(0), FeeRate::from_sat_per_vb(5.0), @add_signature);
+    }
+
+    #[test]
+    fn test_cpfp_unconfirmed_ancestors() {
+        let (wallet, descriptors, funding_txid) = get_funded_wallet(get_test_wpkh());
+        let grandparent_txid = crate::populate_test_db!(
+            wallet.database.borrow_mut(),
+            testutils! (@tx ( (@external descriptors, 0) => 25_000 )),
+            Some(100),
+        );
+        // only leave the output of the unconfirmed grandparent to the parent
+        wallet
+            .database
+            .borrow_mut()
+            .del_utxo(&OutPoint::new(funding_txid, 0))
+            .unwrap();
+
+        let addr = Address::from_str("2N1Ffz3WaNzbeLFBb51xyFMHYSEUXcbiSoX").unwrap();
+        let parent = create_stored_rbf_tx(&wallet, addr.script_pubkey(), 10_000);
+        let parent_tx = parent.transaction.as_ref().unwrap();
+        assert_eq!(parent_tx.input[0].previous_output.txid, grandparent_txid);
+        let (vout, change) = parent_tx
+            .output
+            .iter()
+            .enumerate()
+            .find(|(_, txout)| txout.script_pubkey != addr.script_pubkey())
+            .unwrap();
+        wallet
+            .database
+            .borrow_mut()
+            .set_utxo(&LocalUtxo {
+                outpoint: OutPoint::new(parent.txid, vout as u32),
+                txout: change.clone(),
+                keychain: KeychainKind::External,
+                is_coinbase: false,
+            })
+            .unwrap();
+        let grandparent = wallet
+            .database
+            .borrow()
+            .get_raw_tx(&grandparent_txid)
+            .unwrap()
+            .unwrap();
+
+        let builder = wallet
+            .build_cpfp(parent.txid, FeeRate::from_sat_per_vb(5.0))
+            .unwrap();
+        let (psbt, details) = builder.finish().unwrap();
+
+        let mut child = psbt.extract_tx();
+        child.input[0].witness.push([0x00; 108].to_vec()); // fake signature
+        let package_vbytes =
+            (grandparent.get_weight() + parent_tx.get_weight() + child.get_weight()) as f32 / 4.0;
+        let package_fee_rate =
+            (parent.fee.unwrap_or(0) + details.fee.unwrap_or(0)) as f32 / package_vbytes;
+        assert!((package_fee_rate - 5.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_cpfp_confirmed_parent() {
+        let (wallet, _, txid) = get_funded_wallet(get_test_wpkh());
+
+        assert!(matches!(
+            wallet.build_cpfp(txid, FeeRate::from_sat_per_vb(5.0)),
+            Err(Error::TransactionConfirmed)
+        ));
+    }
+
+    #[test]
+    fn test_cpfp_no_spendable_output() {
+        let (wallet, _, _) = get_funded_wallet(get_test_wpkh());
+        let addr = Address::from_str("2N1Ffz3WaNzbeLFBb51xyFMHYSEUXcbiSoX").unwrap();
+        let parent = create_stored_rbf_tx(&wallet, addr.script_pubkey(), 10_000);
+
+        assert!(matches!(
+            wallet.build_cpfp(parent.txid, FeeRate::from_sat_per_vb(5.0)),
+            Err(Error::Generic(_))
+        ));
+    }
+
     #[test]
     fn test_bump_fee_absolute_add_input() {
         let (wallet, descriptors, _) = get_funded_wallet(get_test_wpkh());
@@ -3998,4 +5131,297 @@ pub(crate) mod test {
             }
         );
     }
+
+    #[test]
+    fn test_import_export_labels() {
+        let (wallet, _, txid) = get_funded_wallet(get_test_wpkh());
+        let address = wallet.get_address(New).unwrap();
+
+        wallet.set_label(Label::tx(&txid, "Funding")).unwrap();
+        wallet
+            .set_label(Label::address(&address, "Donations"))
+            .unwrap();
+        assert_eq!(
+            wallet
+                .get_label(LabelType::Addr, &address.to_string())
+                .unwrap()
+                .map(|l| l.label),
+            Some("Donations".to_string())
+        );
+
+        let exported = wallet.export_labels().unwrap();
+        assert_eq!(exported.lines().count(), 2);
+
+        let (other_wallet, _, _) = get_funded_wallet(get_test_wpkh());
+        let imported = other_wallet
+            .import_labels(&format!(
+                "{}\n\n{}",
+                exported, r#"{"type":"unknown","ref":"foo","label":"bar"}"#
+            ))
+            .unwrap();
+        assert_eq!(imported, 2);
+        assert_eq!(
+            other_wallet.list_labels().unwrap(),
+            wallet.list_labels().unwrap()
+        );
+
+        assert!(other_wallet
+            .del_label(LabelType::Tx, &txid.to_string())
+            .unwrap()
+            .is_some());
+        assert_eq!(other_wallet.list_labels().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_get_balance() {
+        let (wallet, descriptors, _) = get_funded_wallet(get_test_wpkh());
+        assert_eq!(
+            wallet.get_balance().unwrap(),
+            Balance {
+                confirmed: 50_000,
+                ..Default::default()
+            }
+        );
+
+        // send everything back to ourselves, leaving the tx unconfirmed
+        let addr = wallet.get_address(New).unwrap();
+        let mut builder = wallet.build_tx();
+        builder
+            .drain_wallet()
+            .set_single_recipient(addr.script_pubkey());
+        let (psbt, mut details) = builder.finish().unwrap();
+        let tx = psbt.extract_tx();
+        {
+            let mut database = wallet.database.borrow_mut();
+            for input in &tx.input {
+                database.del_utxo(&input.previous_output).unwrap();
+            }
+            database
+                .set_utxo(&LocalUtxo {
+                    outpoint: OutPoint::new(tx.txid(), 0),
+                    txout: tx.output[0].clone(),
+                    keychain: KeychainKind::External,
+                    is_coinbase: false,
+                })
+                .unwrap();
+            details.transaction = Some(tx);
+            database.set_tx(&details).unwrap();
+        }
+
+        // unconfirmed tx received from someone else
+        let tx_meta = testutils! {
+            @tx ( (@external descriptors, 0) => 25_000 )
+        };
+        crate::populate_test_db!(wallet.database.borrow_mut(), tx_meta, Some(100));
+
+        // coinbase tx, immature since the wallet doesn't know the current height
+        let coinbase = Transaction {
+            version: 1,
+            lock_time: 0,
+            input: vec![bitcoin::TxIn::default()],
+            output: vec![TxOut {
+                value: 10_000,
+                script_pubkey: addr.script_pubkey(),
+            }],
+        };
+        {
+            let mut database = wallet.database.borrow_mut();
+            database
+                .set_utxo(&LocalUtxo {
+                    outpoint: OutPoint::new(coinbase.txid(), 0),
+                    txout: coinbase.output[0].clone(),
+                    keychain: KeychainKind::External,
+                    is_coinbase: true,
+                })
+                .unwrap();
+            database
+                .set_tx(&TransactionDetails {
+                    txid: coinbase.txid(),
+                    transaction: Some(coinbase),
+                    received: 10_000,
+                    confirmation_time: Some(ConfirmationTime {
+                        height: 100,
+                        timestamp: 0,
+                        block_hash: None,
+                    }),
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+
+        let balance = wallet.get_balance().unwrap();
+        assert_eq!(balance.confirmed, 0);
+        assert_eq!(balance.trusted_pending, details.received);
+        assert_eq!(balance.untrusted_pending, 25_000);
+        assert_eq!(balance.immature, 10_000);
+        assert_eq!(balance.get_spendable(), details.received);
+        assert_eq!(balance.get_total(), details.received + 35_000);
+    }
+
+    #[test]
+    fn test_get_balance_missing_tx() {
+        let (wallet, _, _) = get_funded_wallet(get_test_wpkh());
+        let addr = wallet.get_address(New).unwrap();
+        wallet
+            .database
+            .borrow_mut()
+            .set_utxo(&LocalUtxo {
+                outpoint: OutPoint::new(Txid::default(), 0),
+                txout: TxOut {
+                    value: 10_000,
+                    script_pubkey: addr.script_pubkey(),
+                },
+                keychain: KeychainKind::External,
+                is_coinbase: false,
+            })
+            .unwrap();
+
+        assert_eq!(
+            wallet.get_balance().unwrap(),
+            Balance {
+                confirmed: 50_000,
+                untrusted_pending: 10_000,
+                ..Default::default()
+            }
+        );
+    }
+
+    fn add_coinbase_utxo<B>(
+        wallet: &Wallet<B, MemoryDatabase>,
+        script_pubkey: Script,
+        height: u32,
+    ) {
+        let coinbase = Transaction {
+            version: 1,
+            lock_time: 0,
+            input: vec![bitcoin::TxIn::default()],
+            output: vec![TxOut {
+                value: 10_000,
+                script_pubkey,
+            }],
+        };
+        let mut database = wallet.database.borrow_mut();
+        database
+            .set_utxo(&LocalUtxo {
+                outpoint: OutPoint::new(coinbase.txid(), 0),
+                txout: coinbase.output[0].clone(),
+                keychain: KeychainKind::External,
+                is_coinbase: true,
+            })
+            .unwrap();
+        database
+            .set_tx(&TransactionDetails {
+                txid: coinbase.txid(),
+                transaction: Some(coinbase),
+                received: 10_000,
+                confirmation_time: Some(ConfirmationTime {
+                    height,
+                    timestamp: 0,
+                    block_hash: None,
+                }),
+                ..Default::default()
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_create_tx_skip_immature_coinbase() {
+        let (wallet, _, _) = get_funded_wallet(get_test_wpkh());
+        let addr = wallet.get_address(New).unwrap();
+        add_coinbase_utxo(&wallet, addr.script_pubkey(), 100);
+
+        for (current_height, expected_inputs) in &[(None, 1), (Some(198), 1), (Some(199), 2)] {
+            wallet.current_height.set(*current_height);
+
+            let mut builder = wallet.build_tx();
+            builder
+                .drain_wallet()
+                .set_single_recipient(addr.script_pubkey());
+            let (psbt, _) = builder.finish().unwrap();
+
+            assert_eq!(psbt.global.unsigned_tx.input.len(), *expected_inputs);
+        }
+    }
+
+    #[test]
+    fn test_create_tx_coinbase_current_height() {
+        let (wallet, _, _) = get_funded_wallet(get_test_wpkh());
+        let addr = wallet.get_address(New).unwrap();
+        add_coinbase_utxo(&wallet, addr.script_pubkey(), 100);
+
+        let mut builder = wallet.build_tx();
+        builder
+            .drain_wallet()
+            .set_single_recipient(addr.script_pubkey())
+            .current_height(199);
+        let (psbt, _) = builder.finish().unwrap();
+
+        assert_eq!(psbt.global.unsigned_tx.input.len(), 2);
+    }
+
+    struct HeightBlockchain {
+        height: Cell<u32>,
+    }
+
+    impl Blockchain for HeightBlockchain {
+        fn get_capabilities(&self) -> HashSet<Capability> {
+            Default::default()
+        }
+        fn setup<D: BatchDatabase, P: 'static + Progress>(
+            &self,
+            _stop_gap: Option<usize>,
+            _database: &mut D,
+            _progress_update: P,
+        ) -> Result<(), Error> {
+            Ok(())
+        }
+        fn get_tx(&self, _txid: &Txid) -> Result<Option<Transaction>, Error> {
+            Ok(None)
+        }
+        fn broadcast(&self, _tx: &Transaction) -> Result<(), Error> {
+            Ok(())
+        }
+        fn get_height(&self) -> Result<u32, Error> {
+            Ok(self.height.get())
+        }
+        fn estimate_fee(&self, _target: usize) -> Result<FeeRate, Error> {
+            Ok(FeeRate::default_min_relay_fee())
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "async-interface"))]
+    fn test_sync_coinbase_maturity() {
+        let blockchain = HeightBlockchain {
+            height: Cell::new(150),
+        };
+        let wallet = Wallet::new(
+            get_test_wpkh(),
+            None,
+            Network::Regtest,
+            MemoryDatabase::new(),
+            blockchain,
+        )
+        .unwrap();
+        let addr = wallet.get_address(New).unwrap();
+        add_coinbase_utxo(&wallet, addr.script_pubkey(), 100);
+        assert_eq!(wallet.get_balance().unwrap().immature, 10_000);
+
+        wallet.client.height.set(199);
+        wallet.sync(noop_progress(), None).unwrap();
+
+        assert_eq!(
+            wallet.get_balance().unwrap(),
+            Balance {
+                confirmed: 10_000,
+                ..Default::default()
+            }
+        );
+        let mut builder = wallet.build_tx();
+        builder
+            .drain_wallet()
+            .set_single_recipient(addr.script_pubkey());
+        let (psbt, _) = builder.finish().unwrap();
+        assert_eq!(psbt.global.unsigned_tx.input.len(), 1);
+    }
 }
@@ -0,0 +1,1024 @@
+// Bitcoin Dev Kit
+// Written in 2021 by the Bitcoin Dev Kit Developers
+//
+// Copyright (c) 2020-2021 Bitcoin Dev Kit Developers
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! Payjoin
+//!
+//! This module implements both sides of a [BIP-78](https://github.com/bitcoin/bips/blob/master/bip-0078.mediawiki)
+//! payjoin, a payment where the receiver also contributes an input so that the transaction
+//! doesn't look like it's spent by a single party.
+//!
+//! The [`PayjoinSender`] posts the original transaction created and signed by the wallet to the
+//! receiver's endpoint, validates the proposal it gets back and signs it. The [`PayjoinReceiver`]
+//! runs on the other side and adds one of the receiver's utxos to the original transaction: it
+//! doesn't implement the HTTP server, which is left to the application.
+//!
+//! ## Example
+//!
+//! ```no_run
+//! # use std::str::FromStr;
+//! # use bitcoin::*;
+//! # use bdk::database::*;
+//! # use bdk::wallet::payjoin::*;
+//! # use bdk::*;
+//! # let wallet = doctest_wallet!();
+//! # let to_address = Address::from_str("2N4eQYCbKUHCCTUjBJeHcJp9ok6J2GZsTDt").unwrap();
+//! let (mut psbt, _) = {
+//!     let mut builder = wallet.build_tx();
+//!     builder.add_recipient(to_address.script_pubkey(), 50_000);
+//!     builder.finish()?
+//! };
+//! wallet.sign(&mut psbt, SignOptions::default())?;
+//!
+//! let sender = PayjoinSender::new(&wallet, psbt, PayjoinParams::default())?;
+//! let payjoin = sender.send("https://example.com/payjoin", SignOptions::default())?;
+//! // broadcast `payjoin.extract_tx()`, or the original transaction if anything went wrong
+//! # Ok::<(), bdk::Error>(())
+//! ```
+
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use bitcoin::util::psbt::{Input, PartiallySignedTransaction as Psbt};
+use bitcoin::{OutPoint, Script, TxIn};
+
+use miniscript::descriptor::DescriptorTrait;
+
+use rand::{thread_rng, Rng};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace};
+
+use crate::database::BatchDatabase;
+use crate::error::Error;
+use crate::psbt::PsbtUtils;
+use crate::signer::SignOptions;
+use crate::types::{FeeRate, KeychainKind, LocalUtxo};
+use crate::wallet::coin_selection::TXIN_BASE_WEIGHT;
+use crate::wallet::Wallet;
+
+/// Version of the payjoin protocol implemented by this module
+pub const PAYJOIN_VERSION: u32 = 1;
+
+/// Errors while sending or receiving a payjoin
+#[derive(Debug)]
+pub enum PayjoinError {
+    /// The original transaction can't be used for a payjoin
+    InvalidOriginal(String),
+    /// The requested version of the protocol is not supported
+    VersionUnsupported(u32),
+    /// The receiver doesn't have any utxo to contribute
+    Unavailable,
+    /// The endpoint of the receiver is neither `https` nor a `.onion` address
+    InsecureEndpoint(String),
+    /// The receiver replied with an error
+    Receiver {
+        /// The error code, e.g. `unavailable` or `original-psbt-rejected`
+        error_code: String,
+        /// Message describing the error
+        message: String,
+    },
+    /// The proposal returned by the receiver is not acceptable
+    InvalidProposal(String),
+    /// Error while talking to the receiver
+    Http(reqwest::Error),
+}
+
+impl PayjoinError {
+    /// Return the well-known BIP-78 error code that the receiver should reply with
+    pub fn error_code(&self) -> &str {
+        match self {
+            PayjoinError::InvalidOriginal(_) => "original-psbt-rejected",
+            PayjoinError::VersionUnsupported(_) => "version-unsupported",
+            PayjoinError::Receiver { error_code, .. } => error_code,
+            _ => "unavailable",
+        }
+    }
+}
+
+impl fmt::Display for PayjoinError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PayjoinError::InvalidOriginal(reason) => {
+                write!(f, "Invalid original transaction: {}", reason)
+            }
+            PayjoinError::VersionUnsupported(version) => {
+                write!(f, "Unsupported payjoin version {}", version)
+            }
+            PayjoinError::Unavailable => write!(f, "No utxo available to contribute"),
+            PayjoinError::InsecureEndpoint(endpoint) => {
+                write!(f, "The endpoint `{}` is not secure", endpoint)
+            }
+            PayjoinError::Receiver {
+                error_code,
+                message,
+            } => write!(f, "The receiver replied `{}`: {}", error_code, message),
+            PayjoinError::InvalidProposal(reason) => write!(f, "Invalid proposal: {}", reason),
+            PayjoinError::Http(e) => write!(f, "HTTP error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PayjoinError {}
+
+impl_error!(reqwest::Error, Http, PayjoinError);
+
+/// Parameters of a payjoin, sent by the sender in the query string of the request
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PayjoinParams {
+    /// Forbid the receiver from changing the script or decreasing the value of its output
+    pub disable_output_substitution: bool,
+    /// Index of the output of the sender that can be decreased to pay for the fee of the inputs
+    /// added by the receiver
+    pub additional_fee_output_index: Option<usize>,
+    /// Maximum amount the output at `additional_fee_output_index` can be decreased by
+    pub max_additional_fee_contribution: u64,
+    /// Minimum fee rate of the proposal
+    pub min_fee_rate: Option<FeeRate>,
+}
+
+impl PayjoinParams {
+    /// Serialize the parameters as the query string of a request, without the leading `?`
+    pub fn to_query(&self) -> String {
+        let mut query = format!("v={}", PAYJOIN_VERSION);
+        if let Some(index) = self.additional_fee_output_index {
+            query += &format!(
+                "&additionalfeeoutputindex={}&maxadditionalfeecontribution={}",
+                index, self.max_additional_fee_contribution
+            );
+        }
+        if let Some(min_fee_rate) = self.min_fee_rate {
+            query += &format!("&minfeerate={}", min_fee_rate.as_sat_vb());
+        }
+        if self.disable_output_substitution {
+            query += "&disableoutputsubstitution=true";
+        }
+
+        query
+    }
+
+    /// Parse the parameters from the query string of a request, ignoring the unknown ones
+    pub fn from_query(query: &str) -> Result<Self, PayjoinError> {
+        fn parse<T: FromStr>(key: &str, value: &str) -> Result<T, PayjoinError> {
+            value.parse().map_err(|_| {
+                PayjoinError::InvalidOriginal(format!("invalid value `{}` for `{}`", value, key))
+            })
+        }
+
+        let mut params = PayjoinParams::default();
+        let mut version = None;
+        for pair in query.trim_start_matches('?').split('&') {
+            let mut pair = pair.splitn(2, '=');
+            let (key, value) = match (pair.next(), pair.next()) {
+                (Some(key), Some(value)) => (key, value),
+                _ => continue,
+            };
+
+            match key {
+                "v" => version = Some(parse(key, value)?),
+                "additionalfeeoutputindex" => {
+                    params.additional_fee_output_index = Some(parse(key, value)?)
+                }
+                "maxadditionalfeecontribution" => {
+                    params.max_additional_fee_contribution = parse(key, value)?
+                }
+                "minfeerate" => {
+                    params.min_fee_rate = Some(FeeRate::from_sat_per_vb(parse(key, value)?))
+                }
+                "disableoutputsubstitution" => {
+                    params.disable_output_substitution = parse(key, value)?
+                }
+                _ => {}
+            }
+        }
+
+        match version {
+            Some(PAYJOIN_VERSION) => Ok(params),
+            Some(version) => Err(PayjoinError::VersionUnsupported(version)),
+            None => Err(PayjoinError::InvalidOriginal("missing version".into())),
+        }
+    }
+}
+
+// Broad type of a script, the receiver should contribute inputs of the same type as the sender's
+fn script_type(script: &Script) -> &'static str {
+    if script.is_p2pkh() {
+        "p2pkh"
+    } else if script.is_p2sh() {
+        "p2sh"
+    } else if script.is_v0_p2wpkh() {
+        "p2wpkh"
+    } else if script.is_v0_p2wsh() {
+        "p2wsh"
+    } else {
+        "other"
+    }
+}
+
+fn is_finalized(input: &Input) -> bool {
+    input.final_script_sig.is_some() || input.final_script_witness.is_some()
+}
+
+/// Sender of a payjoin
+///
+/// For a usage example see [this module](crate::wallet::payjoin)'s documentation.
+#[derive(Debug)]
+pub struct PayjoinSender<'w, B, D> {
+    wallet: &'w Wallet<B, D>,
+    original: Psbt,
+    params: PayjoinParams,
+}
+
+impl<'w, B, D: BatchDatabase> PayjoinSender<'w, B, D> {
+    /// Create a sender for the `original` PSBT, as returned by [`TxBuilder::finish`](crate::wallet::tx_builder::TxBuilder::finish)
+    ///
+    /// The original must be signed and finalized, since the receiver can broadcast it instead of
+    /// the payjoin.
+    pub fn new(
+        wallet: &'w Wallet<B, D>,
+        original: Psbt,
+        params: PayjoinParams,
+    ) -> Result<Self, Error> {
+        for (n, input) in original.inputs.iter().enumerate() {
+            if !is_finalized(input) {
+                return Err(PayjoinError::InvalidOriginal(format!(
+                    "input #{} is not finalized",
+                    n
+                ))
+                .into());
+            }
+        }
+        if let Some(index) = params.additional_fee_output_index {
+            match original.global.unsigned_tx.output.get(index) {
+                Some(txout) if wallet.is_mine(&txout.script_pubkey)? => {}
+                _ => {
+                    return Err(PayjoinError::InvalidOriginal(format!(
+                        "output #{} doesn't belong to the wallet",
+                        index
+                    ))
+                    .into())
+                }
+            }
+        }
+
+        Ok(PayjoinSender {
+            wallet,
+            original,
+            params,
+        })
+    }
+
+    /// Return the body of the request: the original PSBT encoded in base64
+    pub fn request_body(&self) -> String {
+        self.original.to_string()
+    }
+
+    /// Return the URL of the request to `endpoint`, including the parameters of the payjoin
+    ///
+    /// The `endpoint` must use `https` or be a `.onion` address. Plain `http` is also accepted for
+    /// loopback addresses, to test against a local receiver.
+    pub fn request_url(&self, endpoint: &str) -> Result<String, Error> {
+        let url = reqwest::Url::parse(endpoint).map_err(|_| {
+            PayjoinError::InvalidOriginal(format!("invalid endpoint `{}`", endpoint))
+        })?;
+        let host = url.host_str().unwrap_or_default();
+        let secure = url.scheme() == "https"
+            || host.ends_with(".onion")
+            || host == "localhost"
+            || host == "127.0.0.1"
+            || host == "[::1]";
+        if !secure {
+            return Err(PayjoinError::InsecureEndpoint(endpoint.to_string()).into());
+        }
+
+        let separator = if url.query().is_some() { '&' } else { '?' };
+        Ok(format!("{}{}{}", url, separator, self.params.to_query()))
+    }
+
+    /// Post the original transaction to `endpoint` and return the signed payjoin
+    ///
+    /// This is a blocking call: it must not be made from within an async runtime.
+    pub fn send(&self, endpoint: &str, sign_options: SignOptions) -> Result<Psbt, Error> {
+        let url = self.request_url(endpoint)?;
+        debug!("Sending payjoin request to {}", url);
+
+        let response = reqwest::blocking::Client::new()
+            .post(&url)
+            .header("Content-Type", "text/plain")
+            .body(self.request_body())
+            .send()
+            .map_err(PayjoinError::Http)?;
+        let status = response.status();
+        let body = response.text().map_err(PayjoinError::Http)?;
+
+        if !status.is_success() {
+            let error: serde_json::Value = serde_json::from_str(&body).unwrap_or_default();
+            return Err(PayjoinError::Receiver {
+                error_code: error["errorCode"]
+                    .as_str()
+                    .unwrap_or("unavailable")
+                    .to_string(),
+                message: error["message"].as_str().unwrap_or(&body).to_string(),
+            }
+            .into());
+        }
+
+        let proposal = Psbt::from_str(body.trim())?;
+        self.process_proposal(proposal, sign_options)
+    }
+
+    /// Validate the `proposal` returned by the receiver and sign it
+    ///
+    /// The proposal is rejected if it removes or changes any of the sender's inputs, adds an input
+    /// that belongs to the wallet, reduces the sender's outputs by more than the allowed fee
+    /// contribution, or doesn't reach the minimum fee rate.
+    pub fn process_proposal(
+        &self,
+        proposal: Psbt,
+        sign_options: SignOptions,
+    ) -> Result<Psbt, Error> {
+        let invalid = |reason: String| -> Error { PayjoinError::InvalidProposal(reason).into() };
+
+        let original_tx = &self.original.global.unsigned_tx;
+        let proposal_tx = &proposal.global.unsigned_tx;
+        if proposal_tx.version != original_tx.version
+            || proposal_tx.lock_time != original_tx.lock_time
+        {
+            return Err(invalid("the version or locktime changed".into()));
+        }
+        if proposal.inputs.len() != proposal_tx.input.len()
+            || proposal.outputs.len() != proposal_tx.output.len()
+        {
+            return Err(invalid("inconsistent number of inputs or outputs".into()));
+        }
+
+        let original_types = (0..original_tx.input.len())
+            .filter_map(|n| self.original.get_utxo_for(n))
+            .map(|txout| script_type(&txout.script_pubkey))
+            .collect::<HashSet<_>>();
+        let sequence = original_tx.input[0].sequence;
+
+        // put back the original inputs, finalized, to estimate the final fee rate
+        let mut psbt = proposal.clone();
+        let mut original_inputs = HashSet::new();
+        for (n, txin) in proposal_tx.input.iter().enumerate() {
+            if txin.sequence != sequence {
+                return Err(invalid(format!("the sequence of input #{} changed", n)));
+            }
+
+            match original_tx
+                .input
+                .iter()
+                .position(|original| original.previous_output == txin.previous_output)
+            {
+                Some(index) => {
+                    psbt.inputs[n] = self.original.inputs[index].clone();
+                    original_inputs.insert(n);
+                }
+                None => {
+                    let txout = proposal
+                        .get_utxo_for(n)
+                        .ok_or_else(|| invalid(format!("missing utxo for input #{}", n)))?;
+                    if !is_finalized(&proposal.inputs[n]) {
+                        return Err(invalid(format!("input #{} is not finalized", n)));
+                    }
+                    if self.wallet.is_mine(&txout.script_pubkey)? {
+                        return Err(invalid(format!("input #{} belongs to the wallet", n)));
+                    }
+                    if original_types.len() == 1
+                        && !original_types.contains(script_type(&txout.script_pubkey))
+                    {
+                        return Err(invalid(format!("input #{} has a different script type", n)));
+                    }
+                }
+            }
+        }
+        if original_inputs.len() != original_tx.input.len() {
+            return Err(invalid("an original input was removed".into()));
+        }
+        let added_inputs = proposal_tx.input.len() - original_inputs.len();
+
+        // every output of the sender must be kept, only the one chosen to pay for the additional
+        // fee can be decreased
+        let mut used_outputs = HashSet::new();
+        let mut contribution = 0;
+        for (index, original) in original_tx.output.iter().enumerate() {
+            let is_mine = self.wallet.is_mine(&original.script_pubkey)?;
+            if !is_mine && !self.params.disable_output_substitution {
+                continue;
+            }
+
+            let position = proposal_tx
+                .output
+                .iter()
+                .enumerate()
+                .position(|(n, txout)| {
+                    txout.script_pubkey == original.script_pubkey && !used_outputs.contains(&n)
+                })
+                .ok_or_else(|| invalid(format!("original output #{} was removed", index)))?;
+            used_outputs.insert(position);
+            psbt.outputs[position] = self.original.outputs[index].clone();
+
+            let value = proposal_tx.output[position].value;
+            if value >= original.value {
+                continue;
+            }
+            if !is_mine || Some(index) != self.params.additional_fee_output_index {
+                return Err(invalid(format!(
+                    "the value of original output #{} was decreased",
+                    index
+                )));
+            }
+            contribution = original.value - value;
+        }
+        if contribution > self.params.max_additional_fee_contribution {
+            return Err(invalid(format!(
+                "the fee contribution of {} sat is too high",
+                contribution
+            )));
+        }
+        // the contribution can only pay for the inputs added by the receiver, estimated like the
+        // inputs of the sender since they must be of the same type
+        if contribution > 0 {
+            let original_fee_rate = self
+                .original
+                .fee_rate()
+                .ok_or_else(|| invalid("the original outputs exceed the inputs".into()))?;
+            let input_weight = TXIN_BASE_WEIGHT + self.input_satisfaction_weight()?;
+            let max_contribution =
+                (original_fee_rate.as_sat_vb() * (input_weight * added_inputs) as f32 / 4.0).ceil()
+                    as u64;
+            if contribution > max_contribution {
+                return Err(invalid(format!(
+                    "the fee contribution of {} sat exceeds the fee of the added inputs",
+                    contribution
+                )));
+            }
+        }
+
+        let original_fee = self.original.fee_amount().unwrap_or(0);
+        let fee = psbt
+            .fee_amount()
+            .ok_or_else(|| invalid("the outputs exceed the inputs".into()))?;
+        if fee < original_fee + contribution {
+            return Err(invalid(format!(
+                "the fee contribution of {} sat was not used for the fee",
+                contribution
+            )));
+        }
+        match (psbt.fee_rate(), self.params.min_fee_rate) {
+            (Some(fee_rate), Some(min_fee_rate)) if fee_rate < min_fee_rate => {
+                return Err(invalid(format!(
+                    "the fee rate of {} sat/vbyte is below the minimum",
+                    fee_rate.as_sat_vb()
+                )))
+            }
+            _ => {}
+        }
+
+        // the txid changed, the sender's inputs must be signed again
+        for n in original_inputs {
+            let input = &mut psbt.inputs[n];
+            input.final_script_sig = None;
+            input.final_script_witness = None;
+            input.partial_sigs.clear();
+        }
+        self.wallet.sign(&mut psbt, sign_options)?;
+
+        Ok(psbt)
+    }
+
+    // maximum satisfaction weight of the first input of the original transaction
+    fn input_satisfaction_weight(&self) -> Result<usize, Error> {
+        let txout = self
+            .original
+            .get_utxo_for(0)
+            .ok_or_else(|| PayjoinError::InvalidOriginal("missing utxo for input #0".into()))?;
+        let weight = match self.wallet.get_descriptor_for_txout(&txout)? {
+            Some(descriptor) => descriptor.max_satisfaction_weight()?,
+            None => self
+                .wallet
+                .get_descriptor_for_keychain(KeychainKind::External)
+                .max_satisfaction_weight()?,
+        };
+
+        Ok(weight)
+    }
+}
+
+/// Receiver of a payjoin
+///
+/// The receiver is meant to be used by the handler of the requests of the HTTP server: it parses
+/// the original PSBT and the parameters, and replies with the proposal returned by
+/// [`PayjoinReceiver::contribute`] or the [error code](PayjoinError::error_code) of the error.
+#[derive(Debug)]
+pub struct PayjoinReceiver<'w, B, D> {
+    wallet: &'w Wallet<B, D>,
+}
+
+impl<'w, B, D: BatchDatabase> PayjoinReceiver<'w, B, D> {
+    /// Create a receiver for `wallet`
+    pub fn new(wallet: &'w Wallet<B, D>) -> Self {
+        PayjoinReceiver { wallet }
+    }
+
+    /// Contribute one of the wallet's confirmed utxos to the `original` transaction and sign it
+    ///
+    /// If `utxo` is `None` a random confirmed utxo is picked. Its value is added to the first
+    /// output that belongs to the wallet, minus the fee for the additional input at the fee rate
+    /// of the original transaction: the sender pays for it, up to the maximum contribution in the
+    /// `params`, and the receiver for the rest.
+    pub fn contribute(
+        &self,
+        original: &Psbt,
+        params: &PayjoinParams,
+        utxo: Option<OutPoint>,
+    ) -> Result<Psbt, Error> {
+        let invalid = |reason: String| -> Error { PayjoinError::InvalidOriginal(reason).into() };
+
+        let original_tx = &original.global.unsigned_tx;
+        if original_tx.input.is_empty() || original.inputs.len() != original_tx.input.len() {
+            return Err(invalid("inconsistent number of inputs".into()));
+        }
+        for n in 0..original_tx.input.len() {
+            let txout = original
+                .get_utxo_for(n)
+                .ok_or_else(|| invalid(format!("missing utxo for input #{}", n)))?;
+            if !is_finalized(&original.inputs[n]) {
+                return Err(invalid(format!("input #{} is not finalized", n)));
+            }
+            if self.wallet.is_mine(&txout.script_pubkey)? {
+                return Err(invalid(format!("input #{} belongs to the wallet", n)));
+            }
+        }
+        let original_fee_rate = original
+            .fee_rate()
+            .ok_or_else(|| invalid("the outputs exceed the inputs".into()))?;
+
+        let mut receiver_output = None;
+        for (n, txout) in original_tx.output.iter().enumerate() {
+            if self.wallet.is_mine(&txout.script_pubkey)? {
+                receiver_output = Some(n);
+                break;
+            }
+        }
+        let receiver_output =
+            receiver_output.ok_or_else(|| invalid("no output pays the wallet".into()))?;
+        let fee_output = match params.additional_fee_output_index {
+            Some(index) if index == receiver_output || index >= original_tx.output.len() => {
+                return Err(invalid(format!("invalid fee output #{}", index)))
+            }
+            index => index,
+        };
+
+        let utxo = self.select_utxo(original, utxo)?;
+        let satisfaction_weight = self
+            .wallet
+            .get_descriptor_for_keychain(utxo.keychain)
+            .max_satisfaction_weight()?;
+        let input_fee =
+            (original_fee_rate.as_sat_vb() * (TXIN_BASE_WEIGHT + satisfaction_weight) as f32 / 4.0)
+                .ceil() as u64;
+
+        let mut psbt = original.clone();
+        let index = thread_rng().gen_range(0, original_tx.input.len() + 1);
+        psbt.global.unsigned_tx.input.insert(
+            index,
+            TxIn {
+                previous_output: utxo.outpoint,
+                script_sig: Script::default(),
+                sequence: original_tx.input[0].sequence,
+                witness: vec![],
+            },
+        );
+        psbt.inputs.insert(
+            index,
+            self.wallet.get_psbt_input(utxo.clone(), None, false)?,
+        );
+
+        // the sender pays for the fee of the new input up to its maximum contribution, the
+        // receiver pays for the rest
+        let sender_fee = match fee_output {
+            Some(fee_output) => {
+                let sender_fee = input_fee
+                    .min(params.max_additional_fee_contribution)
+                    .min(original_tx.output[fee_output].value);
+                psbt.global.unsigned_tx.output[fee_output].value -= sender_fee;
+                sender_fee
+            }
+            None => 0,
+        };
+        let receiver_txout = &mut psbt.global.unsigned_tx.output[receiver_output];
+        receiver_txout.value = (receiver_txout.value + utxo.txout.value + sender_fee)
+            .checked_sub(input_fee)
+            .ok_or(PayjoinError::Unavailable)?;
+
+        if !self.wallet.sign(&mut psbt, SignOptions::default())?
+            && !is_finalized(&psbt.inputs[index])
+        {
+            return Err(Error::Generic(format!(
+                "Unable to sign the contributed input {}",
+                utxo.outpoint
+            )));
+        }
+
+        // only leave the previous output and the signature of the new input, the sender will fill
+        // in its own inputs again
+        for (n, input) in psbt.inputs.iter_mut().enumerate() {
+            *input = match n == index {
+                true => Input {
+                    witness_utxo: input.witness_utxo.take(),
+                    non_witness_utxo: input.non_witness_utxo.take(),
+                    final_script_sig: input.final_script_sig.take(),
+                    final_script_witness: input.final_script_witness.take(),
+                    ..Default::default()
+                },
+                false => Input::default(),
+            };
+        }
+        for output in psbt.outputs.iter_mut() {
+            *output = Default::default();
+        }
+
+        Ok(psbt)
+    }
+
+    fn select_utxo(&self, original: &Psbt, outpoint: Option<OutPoint>) -> Result<LocalUtxo, Error> {
+        let database = self.wallet.database.borrow();
+        let spent = original
+            .global
+            .unsigned_tx
+            .input
+            .iter()
+            .map(|txin| txin.previous_output)
+            .collect::<HashSet<_>>();
+
+        let mut candidates = vec![];
+        for utxo in self.wallet.list_unspent()? {
+            let confirmed = matches!(
+                database.get_tx(&utxo.outpoint.txid, false)?,
+                Some(details) if details.confirmation_time.is_some()
+            );
+            if !confirmed || utxo.is_coinbase || spent.contains(&utxo.outpoint) {
+                continue;
+            }
+            match outpoint {
+                Some(outpoint) if outpoint != utxo.outpoint => {}
+                _ => candidates.push(utxo),
+            }
+        }
+
+        match candidates.len() {
+            0 => Err(PayjoinError::Unavailable.into()),
+            len => Ok(candidates.swap_remove(thread_rng().gen_range(0, len))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
+    use bitcoin::{Address, Network};
+
+    use super::*;
+    use crate::database::MemoryDatabase;
+    use crate::wallet::test::{get_funded_wallet, get_test_wpkh};
+    use crate::wallet::AddressIndex;
+
+    const RECEIVER_DESCRIPTOR: &str = "wpkh(tprv8ZgxMBicQKsPd3EupYiPRhaMooHKUHJxNsTfYuScep13go8QFfHdtkG9nRkFGb7busX4isf6X9dURGCoKgitaApQ6MupRhZMcELAxTBRJgS/0/*)";
+
+    type TestWallet = Wallet<(), MemoryDatabase>;
+
+    // return a funded receiver and the address it's paid to
+    fn get_receiver() -> (TestWallet, Address) {
+        let (receiver, _, _) = get_funded_wallet(RECEIVER_DESCRIPTOR);
+        let address = receiver.get_address(AddressIndex::New).unwrap().address;
+
+        (receiver, address)
+    }
+
+    // return the sender, the receiver, the original psbt paying 25_000 sat to the receiver and
+    // the index of the change output
+    fn setup() -> (TestWallet, TestWallet, Psbt, usize) {
+        let (sender, _, _) = get_funded_wallet(get_test_wpkh());
+        let (receiver, address) = get_receiver();
+
+        let mut builder = sender.build_tx();
+        builder
+            .add_recipient(address.script_pubkey(), 25_000)
+            .fee_rate(FeeRate::from_sat_per_vb(2.0));
+        let (mut original, _) = builder.finish().unwrap();
+        assert!(sender.sign(&mut original, SignOptions::default()).unwrap());
+
+        let change_index = original
+            .global
+            .unsigned_tx
+            .output
+            .iter()
+            .position(|txout| sender.is_mine(&txout.script_pubkey).unwrap())
+            .unwrap();
+
+        (sender, receiver, original, change_index)
+    }
+
+    fn fee_params(change_index: usize) -> PayjoinParams {
+        PayjoinParams {
+            additional_fee_output_index: Some(change_index),
+            max_additional_fee_contribution: 1_000,
+            ..Default::default()
+        }
+    }
+
+    // serve a single payjoin request with the wallet returned by `receiver`, returning the url of
+    // the endpoint
+    fn serve_once<F: FnOnce() -> TestWallet + Send + 'static>(receiver: F) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/payjoin", listener.local_addr().unwrap());
+
+        thread::spawn(move || {
+            let receiver = receiver();
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let target = request_line.split(' ').nth(1).unwrap().to_string();
+            let mut content_length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                let header = header.to_lowercase();
+                if let Some(value) = header.strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            let original = Psbt::from_str(&String::from_utf8(body).unwrap()).unwrap();
+            let params = PayjoinParams::from_query(target.split('?').nth(1).unwrap()).unwrap();
+            let (status, response) = match PayjoinReceiver::new(&receiver)
+                .contribute(&original, &params, None)
+            {
+                Ok(proposal) => ("200 OK", proposal.to_string()),
+                Err(Error::Payjoin(e)) => (
+                    "400 Bad Request",
+                    serde_json::json!({ "errorCode": e.error_code(), "message": e.to_string() })
+                        .to_string(),
+                ),
+                Err(e) => panic!("{:?}", e),
+            };
+
+            let mut stream = stream;
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                response.len(),
+                response
+            )
+            .unwrap();
+        });
+
+        url
+    }
+
+    #[test]
+    fn test_payjoin() {
+        let (sender, receiver, original, change_index) = setup();
+        let params = fee_params(change_index);
+
+        let proposal = PayjoinReceiver::new(&receiver)
+            .contribute(&original, &params, None)
+            .unwrap();
+        assert_eq!(proposal.global.unsigned_tx.input.len(), 2);
+
+        let sender = PayjoinSender::new(&sender, original.clone(), params).unwrap();
+        let payjoin = sender
+            .process_proposal(proposal, SignOptions::default())
+            .unwrap();
+        assert!(payjoin.inputs.iter().all(is_finalized));
+
+        let original_tx = &original.global.unsigned_tx;
+        let payjoin_tx = &payjoin.global.unsigned_tx;
+        let receiver_index = 1 - change_index;
+        let change = payjoin_tx
+            .output
+            .iter()
+            .find(|txout| txout.script_pubkey == original_tx.output[change_index].script_pubkey)
+            .unwrap();
+        let payment = payjoin_tx
+            .output
+            .iter()
+            .find(|txout| txout.script_pubkey == original_tx.output[receiver_index].script_pubkey)
+            .unwrap();
+        let contribution = original_tx.output[change_index].value - change.value;
+        assert!(contribution > 0 && contribution <= 1_000);
+        assert!(payment.value > 25_000 + 50_000 - 1_000);
+        assert_eq!(
+            payjoin.fee_amount().unwrap(),
+            original.fee_amount().unwrap() + contribution + (25_000 + 50_000 - payment.value)
+        );
+        assert!(
+            (payjoin.fee_rate().unwrap().as_sat_vb() - original.fee_rate().unwrap().as_sat_vb())
+                .abs()
+                < 0.5
+        );
+    }
+
+    #[test]
+    fn test_payjoin_http() {
+        let (sender, _, original, change_index) = setup();
+        let url = serve_once(|| get_receiver().0);
+
+        let sender = PayjoinSender::new(&sender, original, fee_params(change_index)).unwrap();
+        let payjoin = sender.send(&url, SignOptions::default()).unwrap();
+        assert_eq!(payjoin.global.unsigned_tx.input.len(), 2);
+        assert!(payjoin.inputs.iter().all(is_finalized));
+    }
+
+    #[test]
+    fn test_payjoin_http_receiver_error() {
+        let (sender, _, original, change_index) = setup();
+        // a receiver with the same descriptor, but no utxo to contribute
+        let url = serve_once(|| {
+            let receiver = Wallet::new_offline(
+                RECEIVER_DESCRIPTOR,
+                None,
+                Network::Regtest,
+                MemoryDatabase::default(),
+            )
+            .unwrap();
+            receiver.get_address(AddressIndex::New).unwrap();
+            receiver
+        });
+
+        let sender = PayjoinSender::new(&sender, original, fee_params(change_index)).unwrap();
+        assert!(matches!(
+            sender.send(&url, SignOptions::default()),
+            Err(Error::Payjoin(PayjoinError::Receiver { error_code, .. })) if error_code == "unavailable"
+        ));
+    }
+
+    #[test]
+    fn test_payjoin_contribution_too_high() {
+        let (sender, receiver, original, change_index) = setup();
+        let params = fee_params(change_index);
+        let mut proposal = PayjoinReceiver::new(&receiver)
+            .contribute(&original, &params, None)
+            .unwrap();
+
+        let change_script = &original.global.unsigned_tx.output[change_index].script_pubkey;
+        for txout in &mut proposal.global.unsigned_tx.output {
+            if &txout.script_pubkey == change_script {
+                txout.value -= 1_001;
+            }
+        }
+
+        let sender = PayjoinSender::new(&sender, original, params).unwrap();
+        assert!(matches!(
+            sender.process_proposal(proposal, SignOptions::default()),
+            Err(Error::Payjoin(PayjoinError::InvalidProposal(_)))
+        ));
+    }
+
+    #[test]
+    fn test_payjoin_contribution_without_input() {
+        let (sender, _, original, change_index) = setup();
+        let params = fee_params(change_index);
+
+        // the receiver only takes part of the change as additional fee
+        let mut proposal = original.clone();
+        proposal.global.unsigned_tx.output[change_index].value -= 500;
+        for input in &mut proposal.inputs {
+            *input = Default::default();
+        }
+
+        let sender = PayjoinSender::new(&sender, original, params).unwrap();
+        assert!(matches!(
+            sender.process_proposal(proposal, SignOptions::default()),
+            Err(Error::Payjoin(PayjoinError::InvalidProposal(reason))) if reason.contains("added inputs")
+        ));
+    }
+
+    #[test]
+    fn test_payjoin_removed_input() {
+        let (sender, receiver, original, change_index) = setup();
+        let params = fee_params(change_index);
+        let mut proposal = PayjoinReceiver::new(&receiver)
+            .contribute(&original, &params, None)
+            .unwrap();
+
+        let sender_outpoint = original.global.unsigned_tx.input[0].previous_output;
+        let index = proposal
+            .global
+            .unsigned_tx
+            .input
+            .iter()
+            .position(|txin| txin.previous_output == sender_outpoint)
+            .unwrap();
+        proposal.global.unsigned_tx.input.remove(index);
+        proposal.inputs.remove(index);
+
+        let sender = PayjoinSender::new(&sender, original, params).unwrap();
+        assert!(matches!(
+            sender.process_proposal(proposal, SignOptions::default()),
+            Err(Error::Payjoin(PayjoinError::InvalidProposal(_)))
+        ));
+    }
+
+    #[test]
+    fn test_payjoin_min_fee_rate() {
+        let (sender, receiver, original, change_index) = setup();
+        let params = PayjoinParams {
+            min_fee_rate: Some(FeeRate::from_sat_per_vb(5.0)),
+            ..fee_params(change_index)
+        };
+        let proposal = PayjoinReceiver::new(&receiver)
+            .contribute(&original, &params, None)
+            .unwrap();
+
+        let sender = PayjoinSender::new(&sender, original, params).unwrap();
+        assert!(matches!(
+            sender.process_proposal(proposal, SignOptions::default()),
+            Err(Error::Payjoin(PayjoinError::InvalidProposal(_)))
+        ));
+    }
+
+    #[test]
+    fn test_payjoin_original_not_finalized() {
+        let (sender, receiver, _, _) = setup();
+        let address = receiver.get_address(AddressIndex::New).unwrap();
+        let mut builder = sender.build_tx();
+        builder.add_recipient(address.script_pubkey(), 25_000);
+        let (original, _) = builder.finish().unwrap();
+
+        assert!(matches!(
+            PayjoinSender::new(&sender, original.clone(), PayjoinParams::default()),
+            Err(Error::Payjoin(PayjoinError::InvalidOriginal(_)))
+        ));
+        match PayjoinReceiver::new(&receiver).contribute(&original, &PayjoinParams::default(), None)
+        {
+            Err(Error::Payjoin(e)) => assert_eq!(e.error_code(), "original-psbt-rejected"),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_payjoin_params_query() {
+        let params = PayjoinParams {
+            disable_output_substitution: true,
+            additional_fee_output_index: Some(1),
+            max_additional_fee_contribution: 500,
+            min_fee_rate: Some(FeeRate::from_sat_per_vb(2.5)),
+        };
+        assert_eq!(
+            params.to_query(),
+            "v=1&additionalfeeoutputindex=1&maxadditionalfeecontribution=500&minfeerate=2.5&disableoutputsubstitution=true"
+        );
+        assert_eq!(
+            PayjoinParams::from_query(&params.to_query()).unwrap(),
+            params
+        );
+
+        assert!(matches!(
+            PayjoinParams::from_query("v=2"),
+            Err(PayjoinError::VersionUnsupported(2))
+        ));
+    }
+
+    #[test]
+    fn test_payjoin_insecure_endpoint() {
+        let (sender, _, original, _) = setup();
+        let sender = PayjoinSender::new(&sender, original, PayjoinParams::default()).unwrap();
+
+        assert!(matches!(
+            sender.request_url("http://example.com/payjoin"),
+            Err(Error::Payjoin(PayjoinError::InsecureEndpoint(_)))
+        ));
+        assert_eq!(
+            sender
+                .request_url("https://example.com/payjoin?a=b")
+                .unwrap(),
+            "https://example.com/payjoin?a=b&v=1"
+        );
+        assert!(sender
+            .request_url("http://bdkpayjoinexampleaddressxxxxxxxxxxxxxxxxxxxxxxxxxxxx.onion/pj")
+            .is_ok());
+    }
+}
@@ -0,0 +1,702 @@
+// Bitcoin Dev Kit
+// Written in 2021 by the Bitcoin Dev Kit Developers
+//
+// Copyright (c) 2020-2021 Bitcoin Dev Kit Developers
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE
+// or http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option.
+// You may not use this file except in accordance with one or both of these
+// licenses.
+
+//! Remote signers
+//!
+//! This module provides a [`RemoteSigner`] that sends the PSBTs to a signing daemon over TCP or
+//! a Unix socket, and the [`RemoteSignerServer`] that runs on the other side and signs them with
+//! a [`SignersContainer`]. This allows keeping the keys on an isolated host, while a watch-only
+//! [`Wallet`](super::Wallet) takes care of everything else.
+//!
+//! ## Protocol
+//!
+//! Every message is framed by its length, encoded as a 4-byte big-endian integer. The client
+//! sends the PSBT serialized in the binary format defined by BIP-174, the server replies with a
+//! status byte followed by either the signed PSBT (status `0`) or an UTF-8 error message
+//! (status `1`). A connection can be used to sign multiple PSBTs in sequence.
+//!
+//! When the server is configured with a [shared secret](RemoteSignerServer::shared_secret), it
+//! starts every connection by sending a status byte `2` followed by a random 32-byte challenge.
+//! The client replies with the HMAC-SHA256 of the challenge keyed with the secret, and the server
+//! confirms with a status byte `0` before the client sends any PSBT. Otherwise the server replies
+//! with an error and closes the connection.
+//!
+//! ## Security
+//!
+//! By default the server doesn't authenticate the clients: anybody that can connect to it can
+//! get any PSBT that respects its [`SignOptions`] signed. Setting the same shared secret on the
+//! server and on the [`RemoteSigner`]s restricts it to the clients that know the secret, but the
+//! messages are never encrypted and the server isn't authenticated to the clients. A TCP server
+//! should only be bound to the loopback interface, or to an interface only reachable through an
+//! authenticated tunnel like SSH or WireGuard. Unix sockets can be protected with the
+//! permissions of the file.
+//!
+//! ## Example
+//!
+//! ```no_run
+//! # use std::net::TcpListener;
+//! # use std::sync::Arc;
+//! # use bitcoin::*;
+//! # use bitcoin::secp256k1::Secp256k1;
+//! # use bdk::database::*;
+//! # use bdk::descriptor::ExtendedDescriptor;
+//! # use bdk::signer::*;
+//! # use bdk::wallet::remote_signer::*;
+//! # use bdk::*;
+//! // on the signing host
+//! let secp = Secp256k1::new();
+//! let (_, keymap) = ExtendedDescriptor::parse_descriptor(&secp, "wpkh(tprv8ZgxMBicQKsPdpkqS7Eair4YxjcuuvDPNYmKX3sCniCf16tHEVrjjiSXEkFRnUH77yXc6ZcwHHcLNfjdi5qUvw3VDfgYiH5mNsj5izuiu2N/0/*)")?;
+//! let server = RemoteSignerServer::new(SignersContainer::from(keymap), SignOptions::default());
+//! server.listen_tcp(&TcpListener::bind("127.0.0.1:7777")?)?;
+//!
+//! // on the watch-only host
+//! let descriptor = "wpkh(tpubD6NzVbkrYhZ4Xferm7Pz4VnjdcDPFyjVu5K4iZXQ4pVN8Cks4pHVowTBXBKRhX64pkRyJZJN5xAKj4UDNnLPb5p2sSKXhewoYx5GbTdUFWq/0/*)";
+//! let mut wallet = Wallet::new_offline(descriptor, None, Network::Testnet, MemoryDatabase::default())?;
+//! let signer = RemoteSigner::new(RemoteSignerAddress::Tcp("127.0.0.1:7777".to_string()), SignerId::Dummy(0));
+//! wallet.add_signer(KeychainKind::External, SignerOrdering(200), Arc::new(signer));
+//! # Ok::<_, Box<dyn std::error::Error>>(())
+//! ```
+
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use bitcoin::consensus::encode::{deserialize, serialize};
+use bitcoin::hashes::hmac::{Hmac, HmacEngine};
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use rand::{thread_rng, Rng};
+
+use super::signer::{SignOptions, Signer, SignerError, SignerId, SignersContainer};
+use super::utils::SecpCtx;
+use crate::error::Error;
+
+/// Maximum size of a message, larger ones are rejected
+const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+/// Time the server waits for a request before dropping an idle connection
+const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// Size of the challenge sent by the server to authenticate the clients
+const CHALLENGE_SIZE: usize = 32;
+
+const STATUS_OK: u8 = 0;
+const STATUS_ERROR: u8 = 1;
+const STATUS_CHALLENGE: u8 = 2;
+
+fn write_message<W: Write>(writer: &mut W, message: &[u8]) -> io::Result<()> {
+    writer.write_all(&(message.len() as u32).to_be_bytes())?;
+    writer.write_all(message)?;
+    writer.flush()
+}
+
+fn read_message<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message too large",
+        ));
+    }
+
+    let mut message = vec![0u8; len];
+    reader.read_exact(&mut message)?;
+
+    Ok(message)
+}
+
+fn authenticate(secret: &[u8], challenge: &[u8]) -> Hmac<sha256::Hash> {
+    let mut engine = HmacEngine::<sha256::Hash>::new(secret);
+    engine.input(challenge);
+    Hmac::from_engine(engine)
+}
+
+/// Compare two byte strings in a time that doesn't depend on their content
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Stream whose reads can time out
+trait ReadTimeout: Read + Write {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl ReadTimeout for TcpStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+#[cfg(unix)]
+impl ReadTimeout for UnixStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+}
+
+/// Reader that times out once `deadline` is reached, even if the client keeps sending a few
+/// bytes at a time
+struct DeadlineReader<'a, S> {
+    stream: &'a mut S,
+    deadline: Instant,
+}
+
+impl<'a, S: ReadTimeout> Read for DeadlineReader<'a, S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let now = Instant::now();
+        if now >= self.deadline {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "request not received in time",
+            ));
+        }
+
+        self.stream.set_read_timeout(Some(self.deadline - now))?;
+        self.stream.read(buf)
+    }
+}
+
+/// Address of a [`RemoteSignerServer`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteSignerAddress {
+    /// TCP address, in the `host:port` format
+    Tcp(String),
+    /// Path of a Unix socket
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+/// Signer that sends the whole PSBT to a [`RemoteSignerServer`] and merges the signatures it
+/// returns
+#[derive(Debug, Clone)]
+pub struct RemoteSigner {
+    address: RemoteSignerAddress,
+    id: SignerId,
+    timeout: Option<Duration>,
+    secret: Option<Vec<u8>>,
+}
+
+impl RemoteSigner {
+    /// Create a signer that connects to `address`
+    ///
+    /// `id` is used to identify the signer in the wallet, it can be for example the fingerprint
+    /// of the keys held by the server.
+    pub fn new(address: RemoteSignerAddress, id: SignerId) -> Self {
+        RemoteSigner {
+            address,
+            id,
+            timeout: None,
+            secret: None,
+        }
+    }
+
+    /// Fail if the server doesn't reply within `timeout`
+    ///
+    /// By default the signer waits indefinitely, since the server may need a human to approve
+    /// the transaction.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Authenticate to the server with `secret`
+    ///
+    /// The server must be configured with the same [shared
+    /// secret](RemoteSignerServer::shared_secret).
+    pub fn shared_secret(mut self, secret: &[u8]) -> Self {
+        self.secret = Some(secret.to_vec());
+        self
+    }
+
+    fn request(&self, psbt: &Psbt) -> io::Result<Vec<u8>> {
+        match &self.address {
+            RemoteSignerAddress::Tcp(address) => {
+                let stream = TcpStream::connect(address)?;
+                stream.set_read_timeout(self.timeout)?;
+                self.exchange(stream, psbt)
+            }
+            #[cfg(unix)]
+            RemoteSignerAddress::Unix(path) => {
+                let stream = UnixStream::connect(path)?;
+                stream.set_read_timeout(self.timeout)?;
+                self.exchange(stream, psbt)
+            }
+        }
+    }
+
+    fn exchange<S: Read + Write>(&self, mut stream: S, psbt: &Psbt) -> io::Result<Vec<u8>> {
+        if let Some(secret) = &self.secret {
+            let challenge = read_message(&mut stream)?;
+            match challenge.split_first() {
+                Some((&STATUS_CHALLENGE, challenge)) if challenge.len() == CHALLENGE_SIZE => {
+                    write_message(&mut stream, &authenticate(secret, challenge)[..])?;
+                    let response = read_message(&mut stream)?;
+                    if response != [STATUS_OK] {
+                        return Ok(response);
+                    }
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Invalid authentication challenge",
+                    ))
+                }
+            }
+        }
+
+        write_message(&mut stream, &serialize(psbt))?;
+        read_message(&mut stream)
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn sign(
+        &self,
+        psbt: &mut Psbt,
+        _input_index: Option<usize>,
+        _secp: &SecpCtx,
+    ) -> Result<(), SignerError> {
+        let response = self
+            .request(psbt)
+            .map_err(|e| SignerError::External(e.to_string()))?;
+
+        match response.split_first() {
+            Some((&STATUS_OK, signed)) => {
+                let signed: Psbt =
+                    deserialize(signed).map_err(|e| SignerError::External(e.to_string()))?;
+                psbt.merge(signed)
+                    .map_err(|e| SignerError::External(e.to_string()))
+            }
+            Some((&STATUS_ERROR, message)) => Err(SignerError::External(
+                String::from_utf8_lossy(message).into_owned(),
+            )),
+            Some((&STATUS_CHALLENGE, _)) => Err(SignerError::External(
+                "The server requires a shared secret".to_string(),
+            )),
+            _ => Err(SignerError::External("Invalid response".to_string())),
+        }
+    }
+
+    fn sign_whole_tx(&self) -> bool {
+        true
+    }
+
+    fn id(&self, _secp: &SecpCtx) -> SignerId {
+        self.id.clone()
+    }
+}
+
+/// Server that signs the PSBTs sent by [`RemoteSigner`]s
+///
+/// See the [module documentation](self#security) before exposing it over TCP.
+#[derive(Debug)]
+pub struct RemoteSignerServer {
+    signers: SignersContainer,
+    sign_options: SignOptions,
+    read_timeout: Duration,
+    secret: Option<Vec<u8>>,
+    secp: SecpCtx,
+}
+
+impl RemoteSignerServer {
+    /// Create a server that signs with `signers`
+    ///
+    /// The PSBTs that don't respect `sign_options` are rejected.
+    pub fn new(signers: SignersContainer, sign_options: SignOptions) -> Self {
+        RemoteSignerServer {
+            signers,
+            sign_options,
+            read_timeout: DEFAULT_READ_TIMEOUT,
+            secret: None,
+            secp: Secp256k1::new(),
+        }
+    }
+
+    /// Drop the connections that don't send a complete request within `timeout`
+    ///
+    /// Connections are served one at a time, so an idle or slow client would otherwise block all
+    /// the others. The timeout applies to the whole request, not to every read, and is only
+    /// enforced by [`listen_tcp`](Self::listen_tcp) and [`listen_unix`](Self::listen_unix).
+    /// Defaults to 30 seconds.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// Only serve the clients that prove they know `secret`
+    ///
+    /// The [`RemoteSigner`]s must be configured with the same [shared
+    /// secret](RemoteSigner::shared_secret). See the [module documentation](self#protocol) for
+    /// the details of the handshake.
+    pub fn shared_secret(mut self, secret: &[u8]) -> Self {
+        self.secret = Some(secret.to_vec());
+        self
+    }
+
+    /// Sign a PSBT, like a [`RemoteSigner`] connected to this server would do
+    pub fn sign_psbt(&self, psbt: &mut Psbt) -> Result<(), SignerError> {
+        self.sign_options.check_psbt(psbt)?;
+        self.signers.sign(psbt, &self.secp)
+    }
+
+    /// Handle the requests received on `stream`, until it's closed by the client
+    pub fn serve<S: Read + Write>(&self, mut stream: S) -> Result<(), Error> {
+        self.serve_with(&mut stream, |stream| read_message(stream))
+    }
+
+    /// Handle the requests received on `stream`, dropping the connection if a request isn't
+    /// received within the read timeout
+    fn serve_with_deadline<S: ReadTimeout>(&self, mut stream: S) -> Result<(), Error> {
+        let read_timeout = self.read_timeout;
+        self.serve_with(&mut stream, |stream| {
+            read_message(&mut DeadlineReader {
+                stream,
+                deadline: Instant::now() + read_timeout,
+            })
+        })
+    }
+
+    fn serve_with<S, F>(&self, stream: &mut S, mut read_request: F) -> Result<(), Error>
+    where
+        S: Write,
+        F: FnMut(&mut S) -> io::Result<Vec<u8>>,
+    {
+        // `None` when the connection is closed or idle
+        let mut next_request = |stream: &mut S| match read_request(stream) {
+            Ok(request) => Ok(Some(request)),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(e)
+                if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut =>
+            {
+                debug!("Dropping idle connection");
+                Ok(None)
+            }
+            Err(e) => Err(Error::Generic(e.to_string())),
+        };
+
+        if let Some(secret) = &self.secret {
+            let mut challenge = [0u8; CHALLENGE_SIZE];
+            thread_rng().fill(&mut challenge);
+
+            let mut message = vec![STATUS_CHALLENGE];
+            message.extend_from_slice(&challenge);
+            write_message(stream, &message).map_err(|e| Error::Generic(e.to_string()))?;
+
+            let response = match next_request(stream)? {
+                Some(response) => response,
+                None => return Ok(()),
+            };
+            if !constant_time_eq(&response, &authenticate(secret, &challenge)[..]) {
+                let mut response = vec![STATUS_ERROR];
+                response.extend_from_slice(b"Authentication failed");
+                write_message(stream, &response).map_err(|e| Error::Generic(e.to_string()))?;
+
+                return Err(Error::Generic(
+                    "Remote signer client failed to authenticate".to_string(),
+                ));
+            }
+            write_message(stream, &[STATUS_OK]).map_err(|e| Error::Generic(e.to_string()))?;
+        }
+
+        while let Some(request) = next_request(stream)? {
+            let result = deserialize::<Psbt>(&request)
+                .map_err(|e| e.to_string())
+                .and_then(|mut psbt| {
+                    self.sign_psbt(&mut psbt)
+                        .map(|_| psbt)
+                        .map_err(|e| e.to_string())
+                });
+            let response = match result {
+                Ok(psbt) => {
+                    let mut response = vec![STATUS_OK];
+                    response.extend(serialize(&psbt));
+                    response
+                }
+                Err(message) => {
+                    debug!("Refusing to sign: {}", message);
+                    let mut response = vec![STATUS_ERROR];
+                    response.extend(message.into_bytes());
+                    response
+                }
+            };
+
+            write_message(stream, &response).map_err(|e| Error::Generic(e.to_string()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Accept connections on `listener` and serve them one at a time
+    ///
+    /// Errors on a single connection are logged and don't stop the server. Since the messages
+    /// are not encrypted, `listener` should only be bound to the loopback interface or to a
+    /// tunnel, see the [module documentation](self#security).
+    pub fn listen_tcp(&self, listener: &TcpListener) -> Result<(), Error> {
+        match listener.local_addr() {
+            Ok(address) if !address.ip().is_loopback() => warn!(
+                "Remote signer listening on {}, which is not a loopback address",
+                address
+            ),
+            _ => {}
+        }
+
+        for stream in listener.incoming() {
+            let stream = stream.map_err(|e| Error::Generic(e.to_string()))?;
+            if let Err(e) = self.serve_with_deadline(stream) {
+                error!("Error while serving a remote signer: {}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Accept connections on `listener` and serve them one at a time
+    ///
+    /// Errors on a single connection are logged and don't stop the server.
+    #[cfg(unix)]
+    pub fn listen_unix(&self, listener: &UnixListener) -> Result<(), Error> {
+        for stream in listener.incoming() {
+            let stream = stream.map_err(|e| Error::Generic(e.to_string()))?;
+            if let Err(e) = self.serve_with_deadline(stream) {
+                error!("Error while serving a remote signer: {}", e);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::*;
+    use crate::descriptor::ExtendedDescriptor;
+    use crate::signer::SignerOrdering;
+    use crate::types::KeychainKind;
+    use crate::wallet::test::get_funded_wallet;
+    use crate::wallet::AddressIndex;
+    use crate::Wallet;
+
+    const DESCRIPTOR: &str = "wpkh(tprv8ZgxMBicQKsPdpkqS7Eair4YxjcuuvDPNYmKX3sCniCf16tHEVrjjiSXEkFRnUH77yXc6ZcwHHcLNfjdi5qUvw3VDfgYiH5mNsj5izuiu2N/0/*)";
+
+    // Return a watch-only wallet and a server that holds its keys
+    fn get_wallet_and_server() -> (
+        Wallet<(), crate::database::MemoryDatabase>,
+        RemoteSignerServer,
+    ) {
+        let secp = Secp256k1::new();
+        let (descriptor, keymap) = ExtendedDescriptor::parse_descriptor(&secp, DESCRIPTOR).unwrap();
+        let (wallet, _, _) = get_funded_wallet(&descriptor.to_string());
+        let server =
+            RemoteSignerServer::new(SignersContainer::from(keymap), SignOptions::default());
+
+        (wallet, server)
+    }
+
+    fn build_psbt(wallet: &Wallet<(), crate::database::MemoryDatabase>) -> Psbt {
+        let addr = wallet.get_address(AddressIndex::New).unwrap();
+        let mut builder = wallet.build_tx();
+        builder
+            .set_single_recipient(addr.script_pubkey())
+            .drain_wallet();
+        let (psbt, _) = builder.finish().unwrap();
+
+        psbt
+    }
+
+    #[test]
+    fn test_remote_signer_tcp() {
+        let (mut wallet, server) = get_wallet_and_server();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || server.listen_tcp(&listener));
+
+        wallet.add_signer(
+            KeychainKind::External,
+            SignerOrdering(200),
+            Arc::new(RemoteSigner::new(
+                RemoteSignerAddress::Tcp(address),
+                SignerId::Dummy(0),
+            )),
+        );
+
+        let mut psbt = build_psbt(&wallet);
+        let finalized = wallet.sign(&mut psbt, SignOptions::default()).unwrap();
+        assert!(finalized);
+    }
+
+    #[test]
+    fn test_remote_signer_idle_connection() {
+        let (mut wallet, server) = get_wallet_and_server();
+        let server = server.read_timeout(Duration::from_millis(100));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || server.listen_tcp(&listener));
+
+        // a client that never sends anything doesn't block the others
+        let _idle = TcpStream::connect(&address).unwrap();
+
+        wallet.add_signer(
+            KeychainKind::External,
+            SignerOrdering(200),
+            Arc::new(
+                RemoteSigner::new(RemoteSignerAddress::Tcp(address), SignerId::Dummy(0))
+                    .timeout(Duration::from_secs(10)),
+            ),
+        );
+
+        let mut psbt = build_psbt(&wallet);
+        let finalized = wallet.sign(&mut psbt, SignOptions::default()).unwrap();
+        assert!(finalized);
+    }
+
+    #[test]
+    fn test_remote_signer_slow_connection() {
+        let (mut wallet, server) = get_wallet_and_server();
+        let server = server.read_timeout(Duration::from_millis(200));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || server.listen_tcp(&listener));
+
+        // a client that keeps sending one byte at a time doesn't block the others
+        let mut slow = TcpStream::connect(&address).unwrap();
+        thread::spawn(move || {
+            slow.write_all(&100u32.to_be_bytes())?;
+            for _ in 0..100 {
+                thread::sleep(Duration::from_millis(50));
+                slow.write_all(&[0])?;
+            }
+            Ok::<_, io::Error>(())
+        });
+
+        wallet.add_signer(
+            KeychainKind::External,
+            SignerOrdering(200),
+            Arc::new(
+                RemoteSigner::new(RemoteSignerAddress::Tcp(address), SignerId::Dummy(0))
+                    .timeout(Duration::from_secs(10)),
+            ),
+        );
+
+        let mut psbt = build_psbt(&wallet);
+        let finalized = wallet.sign(&mut psbt, SignOptions::default()).unwrap();
+        assert!(finalized);
+    }
+
+    #[test]
+    fn test_remote_signer_shared_secret() {
+        let (wallet, server) = get_wallet_and_server();
+        let server = server.shared_secret(b"secret");
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = RemoteSignerAddress::Tcp(listener.local_addr().unwrap().to_string());
+        thread::spawn(move || server.listen_tcp(&listener));
+
+        let secp = Secp256k1::new();
+        let psbt = build_psbt(&wallet);
+
+        let signer =
+            RemoteSigner::new(address.clone(), SignerId::Dummy(0)).shared_secret(b"secret");
+        let mut signed = psbt.clone();
+        signer.sign(&mut signed, None, &secp).unwrap();
+        assert_eq!(signed.inputs[0].partial_sigs.len(), 1);
+
+        let signer = RemoteSigner::new(address.clone(), SignerId::Dummy(0)).shared_secret(b"wrong");
+        assert!(matches!(
+            signer.sign(&mut psbt.clone(), None, &secp),
+            Err(SignerError::External(message)) if message == "Authentication failed"
+        ));
+
+        let signer = RemoteSigner::new(address, SignerId::Dummy(0));
+        assert!(matches!(
+            signer.sign(&mut psbt.clone(), None, &secp),
+            Err(SignerError::External(message)) if message == "The server requires a shared secret"
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_remote_signer_unix() {
+        let (mut wallet, server) = get_wallet_and_server();
+        let path = std::env::temp_dir().join(format!(
+            "bdk-remote-signer-{:?}",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        let listener = UnixListener::bind(&path).unwrap();
+        thread::spawn(move || server.listen_unix(&listener));
+
+        wallet.add_signer(
+            KeychainKind::External,
+            SignerOrdering(200),
+            Arc::new(RemoteSigner::new(
+                RemoteSignerAddress::Unix(path.clone()),
+                SignerId::Dummy(0),
+            )),
+        );
+
+        let mut psbt = build_psbt(&wallet);
+        let finalized = wallet.sign(&mut psbt, SignOptions::default()).unwrap();
+        assert!(finalized);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_remote_signer_refused() {
+        let (wallet, server) = get_wallet_and_server();
+        let mut psbt = build_psbt(&wallet);
+        psbt.inputs[0].non_witness_utxo = None;
+
+        let mut stream = io::Cursor::new(vec![]);
+        write_message(&mut stream, &serialize(&psbt)).unwrap();
+        let mut stream = io::Cursor::new(stream.into_inner());
+        let mut response = vec![];
+        server.serve(ReadWrite(&mut stream, &mut response)).unwrap();
+
+        let response = read_message(&mut io::Cursor::new(response)).unwrap();
+        assert_eq!(response[0], STATUS_ERROR);
+        assert_eq!(
+            String::from_utf8(response[1..].to_vec()).unwrap(),
+            SignerError::MissingNonWitnessUtxo.to_string()
+        );
+    }
+
+    struct ReadWrite<'a, R, W>(&'a mut R, &'a mut W);
+
+    impl<'a, R: Read, W> Read for ReadWrite<'a, R, W> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl<'a, R, W: Write> Write for ReadWrite<'a, R, W> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.1.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.1.flush()
+        }
+    }
+}
@@ -153,6 +153,8 @@ pub enum SignerError {
     /// To enable signing transactions with non-standard sighashes set
     /// [`SignOptions::allow_all_sighashes`] to `true`.
     NonStandardSighash,
+    /// Error returned by an external signer, like a hardware wallet
+    External(String),
 }
 
 impl fmt::Display for SignerError {
@@ -443,6 +445,25 @@ impl SignersContainer {
             .map(|(_, v)| v)
             .next()
     }
+
+    /// Sign `psbt` with all the signers in the container
+    pub(crate) fn sign(
+        &self,
+        psbt: &mut psbt::PartiallySignedTransaction,
+        secp: &SecpCtx,
+    ) -> Result<(), SignerError> {
+        for signer in self.0.values() {
+            if signer.sign_whole_tx() {
+                signer.sign(psbt, None, secp)?;
+            } else {
+                for index in 0..psbt.inputs.len() {
+                    signer.sign(psbt, Some(index), secp)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 /// Options for a software signer
@@ -489,6 +510,39 @@ impl Default for SignOptions {
     }
 }
 
+impl SignOptions {
+    /// Check that `psbt` can be signed with these options
+    pub(crate) fn check_psbt(
+        &self,
+        psbt: &psbt::PartiallySignedTransaction,
+    ) -> Result<(), SignerError> {
+        // If we aren't allowed to use `witness_utxo`, ensure that every input but finalized one
+        // has the `non_witness_utxo`
+        if !self.trust_witness_utxo
+            && psbt
+                .inputs
+                .iter()
+                .filter(|i| i.final_script_witness.is_none() && i.final_script_sig.is_none())
+                .any(|i| i.non_witness_utxo.is_none())
+        {
+            return Err(SignerError::MissingNonWitnessUtxo);
+        }
+
+        // If the user hasn't explicitly opted-in, refuse to sign the transaction unless every input
+        // is using `SIGHASH_ALL`
+        if !self.allow_all_sighashes
+            && !psbt
+                .inputs
+                .iter()
+                .all(|i| i.sighash_type.is_none() || i.sighash_type == Some(SigHashType::All))
+        {
+            return Err(SignerError::NonStandardSighash);
+        }
+
+        Ok(())
+    }
+}
+
 pub(crate) trait ComputeSighash {
     fn sighash(
         psbt: &psbt::PartiallySignedTransaction,
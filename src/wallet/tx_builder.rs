@@ -150,12 +150,32 @@ pub(crate) struct TxParams {
     pub(crate) add_global_xpubs: bool,
     pub(crate) include_output_redeem_witness_script: bool,
     pub(crate) bumping_fee: Option<PreviousFee>,
+    pub(crate) pay_for_ancestors: bool,
+    pub(crate) current_height: Option<u32>,
 }
 
 #[derive(Clone, Copy, Debug)]
 pub(crate) struct PreviousFee {
     pub absolute: u64,
     pub rate: f32,
+    // whether several transactions are merged in the replacement, in which case its fee must
+    // also cover the minimum relay fee of its own size (BIP-125 rule 4)
+    pub merged: bool,
+}
+
+// Total weight and fee of the unconfirmed transactions a new transaction depends on, that its fee
+// has to make up for to reach the requested fee rate as a package
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct UnconfirmedAncestors {
+    pub weight: usize,
+    pub fee: u64,
+}
+
+impl UnconfirmedAncestors {
+    // Fee missing for the ancestors to reach `fee_rate`
+    pub fn missing_fee(&self, fee_rate: FeeRate) -> f32 {
+        (fee_rate.as_sat_vb() * self.weight as f32 / 4.0 - self.fee as f32).max(0.0)
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -494,6 +514,31 @@ impl<'a, B, D: BatchDatabase, Cs: CoinSelectionAlgorithm<D>, Ctx: TxBuilderConte
         self
     }
 
+    /// Use a specific height to decide whether coinbase outputs have reached maturity
+    ///
+    /// By default the height of the chain tip seen by the last [`Wallet::sync`] is used.
+    /// "Offline" wallets don't know the current height, so they never spend coinbase outputs
+    /// unless it's provided here.
+    pub fn current_height(&mut self, height: u32) -> &mut Self {
+        self.params.current_height = Some(height);
+        self
+    }
+
+    /// Apply the fee rate to the package made of the transaction and its unconfirmed ancestors
+    ///
+    /// By default the fee rate only applies to the new transaction: if it spends unconfirmed
+    /// outputs of transactions that pay a lower fee rate, the package won't be mined at the
+    /// requested rate. With this option the fee is raised to make up for the fee missing from the
+    /// unconfirmed ancestors of the selected utxos stored in the database, and the extra cost is
+    /// reported in [`TransactionDetails::ancestors_fee`]. Ancestors whose fee is not known are
+    /// considered to pay no fee.
+    ///
+    /// Doesn't have any effect when an absolute fee is set with [`TxBuilder::fee_absolute`].
+    pub fn pay_for_unconfirmed_ancestors(&mut self) -> &mut Self {
+        self.params.pay_for_ancestors = true;
+        self
+    }
+
     /// Spend all the available inputs. This respects filters like [`TxBuilder::unspendable`] and the change policy.
     pub fn drain_wallet(&mut self) -> &mut Self {
         self.params.drain_wallet = true;
@@ -799,6 +844,7 @@ mod test {
                 },
                 txout: Default::default(),
                 keychain: KeychainKind::External,
+                is_coinbase: false,
             },
             LocalUtxo {
                 outpoint: OutPoint {
@@ -807,6 +853,7 @@ mod test {
                 },
                 txout: Default::default(),
                 keychain: KeychainKind::Internal,
+                is_coinbase: false,
             },
         ]
     }